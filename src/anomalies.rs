@@ -0,0 +1,92 @@
+//! Detection of batches that modify a very different share of the rows they
+//! scan than the batches before them, like a conversion that suddenly
+//! rewrites every row where it touched a third of them so far. That hints at
+//! a bug or a bad predicate rather than at different data.
+
+/// Rows the batches before have to have scanned before their ratio is
+/// trusted.
+const HISTORY_ROWS: u64 = 1000;
+/// Rows recent batches are collected into before their ratio is compared,
+/// so a single small batch can't raise an alarm by chance.
+const WINDOW_ROWS: u64 = 200;
+
+/// Share of the scanned rows that recent batches modified, compared with the
+/// share before.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Anomaly {
+    pub expected: f64,
+    pub actual: f64,
+}
+
+/// The rows scanned and modified by the batches of a run so far.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WriteRatio {
+    max_deviation: f64,
+    scanned: u64,
+    modified: u64,
+    window_scanned: u64,
+    window_modified: u64,
+}
+
+impl WriteRatio {
+    /// Reports an anomaly once the share of modified rows deviates by more
+    /// than `max_deviation`, like 0.5 for 50 percentage points.
+    pub fn new(max_deviation: f64) -> Self {
+        Self {
+            max_deviation,
+            ..Default::default()
+        }
+    }
+
+    /// Records a batch that modified `modified` of the `scanned` rows. Once
+    /// the recent batches scanned enough rows their ratio is compared with
+    /// the one of the batches before, and returned as an anomaly if it
+    /// deviates too much. The recent batches then start over.
+    pub fn observe(&mut self, scanned: u64, modified: u64) -> Result<(), Anomaly> {
+        self.window_scanned += scanned;
+        self.window_modified += modified;
+        if self.window_scanned < WINDOW_ROWS {
+            return Ok(());
+        }
+        let actual = self.window_modified as f64 / self.window_scanned as f64;
+        let (window_scanned, window_modified) = (self.window_scanned, self.window_modified);
+        self.window_scanned = 0;
+        self.window_modified = 0;
+        if self.scanned >= HISTORY_ROWS {
+            let expected = self.modified as f64 / self.scanned as f64;
+            if (actual - expected).abs() > self.max_deviation {
+                return Err(Anomaly { expected, actual });
+            }
+        }
+        self.scanned += window_scanned;
+        self.modified += window_modified;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sudden_rewrite() {
+        let mut ratio = WriteRatio::new(0.5);
+        for _ in 0..10 {
+            assert_eq!(ratio.observe(100, 30), Ok(()));
+        }
+        // a small batch alone is not judged
+        assert_eq!(ratio.observe(100, 100), Ok(()));
+        let anomaly = ratio.observe(100, 100).unwrap_err();
+        assert_eq!(anomaly.expected, 0.3);
+        assert_eq!(anomaly.actual, 1.0);
+        // the anomalous batches are left out of the history
+        assert_eq!(ratio.observe(200, 60), Ok(()));
+    }
+
+    #[test]
+    fn no_history() {
+        let mut ratio = WriteRatio::new(0.1);
+        assert_eq!(ratio.observe(500, 0), Ok(()));
+        assert_eq!(ratio.observe(500, 500), Ok(()));
+    }
+}
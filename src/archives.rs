@@ -0,0 +1,140 @@
+//! Archives of the solver competition JSON of auctions, as one JSON Lines
+//! object per batch of auctions next to a manifest describing it. The
+//! manifest records what the object has to contain, so `verify-archive` can
+//! check the stored objects against it later instead of trusting that writing
+//! or uploading them succeeded.
+
+use {
+    anyhow::{Context, Result},
+    serde::{Deserialize, Serialize},
+    serde_json::Value as JsonValue,
+    sha2::{Digest, Sha256},
+    std::path::Path,
+};
+
+/// Describes an archive object. Archiving the same auctions again yields the
+/// same manifest.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Name of the object in the archive directory.
+    pub object: String,
+    /// The lowest auction id in the object.
+    pub start_auction_id: i64,
+    /// The highest auction id in the object.
+    pub end_auction_id: i64,
+    /// Number of lines, one per auction.
+    pub rows: usize,
+    /// Hex encoded SHA-256 of the object.
+    pub sha256: String,
+}
+
+/// A line of an archive object.
+#[derive(Debug, Serialize, Deserialize)]
+struct Line {
+    id: i64,
+    json: Option<JsonValue>,
+}
+
+/// The object holding `competitions`, ordered by id, and its manifest.
+/// `None` if there are none.
+pub fn object(competitions: &[(i64, Option<JsonValue>)]) -> Result<Option<(Vec<u8>, Manifest)>> {
+    let (Some((start, _)), Some((end, _))) = (competitions.first(), competitions.last()) else {
+        return Ok(None);
+    };
+    let mut bytes = Vec::new();
+    for (id, json) in competitions {
+        let line = Line {
+            id: *id,
+            json: json.clone(),
+        };
+        serde_json::to_writer(&mut bytes, &line).context("serialize auction")?;
+        bytes.push(b'\n');
+    }
+    let manifest = Manifest {
+        object: format!("auctions-{start}-{end}.jsonl"),
+        start_auction_id: *start,
+        end_auction_id: *end,
+        rows: competitions.len(),
+        sha256: sha256(&bytes),
+    };
+    Ok(Some((bytes, manifest)))
+}
+
+/// File name of the manifest of `object`.
+pub fn manifest_name(object: &str) -> String {
+    format!("{object}.manifest.json")
+}
+
+/// Reads the manifests in `dir`, ordered by name.
+pub fn read_manifests(dir: &Path) -> Result<Vec<Manifest>> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir).context("read archive directory")? {
+        let path = entry.context("read archive directory")?.path();
+        if path.to_string_lossy().ends_with(".manifest.json") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    paths
+        .iter()
+        .map(|path| {
+            let file = std::fs::File::open(path).context("open manifest")?;
+            serde_json::from_reader(std::io::BufReader::new(file))
+                .with_context(|| format!("read manifest {}", path.display()))
+        })
+        .collect()
+}
+
+impl Manifest {
+    /// How `bytes`, the stored object, differs from the manifest, empty if it
+    /// matches.
+    pub fn check(&self, bytes: &[u8]) -> Vec<String> {
+        let mut problems = Vec::new();
+        let actual = sha256(bytes);
+        if actual != self.sha256 {
+            problems.push(format!("sha256 is {actual}, expected {}", self.sha256));
+        }
+        let mut rows = 0;
+        for line in bytes
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+        {
+            rows += 1;
+            match serde_json::from_slice::<Line>(line) {
+                Ok(line) if (self.start_auction_id..=self.end_auction_id).contains(&line.id) => (),
+                Ok(line) => problems.push(format!("auction {} is outside the range", line.id)),
+                Err(err) => problems.push(format!("line {rows} doesn't decode: {err}")),
+            }
+        }
+        if rows != self.rows {
+            problems.push(format!("has {rows} rows, expected {}", self.rows));
+        }
+        problems
+    }
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest() {
+        let competitions = [(3, Some(serde_json::json!({"auctionId": 3}))), (5, None)];
+        let (bytes, manifest) = object(&competitions).unwrap().unwrap();
+        assert_eq!(manifest.object, "auctions-3-5.jsonl");
+        assert_eq!((manifest.start_auction_id, manifest.end_auction_id), (3, 5));
+        assert_eq!(manifest.rows, 2);
+        assert_eq!(object(&competitions).unwrap().unwrap().1, manifest);
+        assert!(manifest.check(&bytes).is_empty());
+
+        let mut truncated = bytes.clone();
+        truncated.truncate(bytes.len() / 2);
+        assert_eq!(manifest.check(&truncated).len(), 3);
+
+        assert!(object(&[]).unwrap().is_none());
+    }
+}
@@ -1,4 +1,11 @@
-use url::Url;
+use {
+    crate::{
+        database_schema::Version, database_solver_competition::AuctionColumn, prices::Rounding,
+        report::OnError, sources::FallbackSource,
+    },
+    std::{net::SocketAddr, num::NonZeroUsize, path::PathBuf},
+    url::Url,
+};
 
 #[derive(clap::Parser)]
 pub struct Arguments {
@@ -6,4 +13,477 @@ pub struct Arguments {
     /// postgres.
     #[clap(long, env, default_value = "postgresql://")]
     pub db_url: Url,
+
+    /// Seconds a connection is idle before the database server sends TCP
+    /// keepalive probes on it, instead of the server default, usually two
+    /// hours. Keeps connections through NATs and bastions that drop idle
+    /// flows open while a run holds them without querying.
+    #[clap(long, env)]
+    pub tcp_keepalive_idle_secs: Option<u64>,
+
+    /// Seconds between TCP keepalive probes the server sends, instead of the
+    /// server default.
+    #[clap(long, env)]
+    pub tcp_keepalive_interval_secs: Option<u64>,
+
+    /// Unanswered TCP keepalive probes after which the server drops the
+    /// connection, instead of the server default.
+    #[clap(long, env)]
+    pub tcp_keepalive_count: Option<u32>,
+
+    /// Ping the idle connections of the pool every this many seconds, so
+    /// they are used often enough to survive NATs and bastions, and the ones
+    /// that broke are replaced before a migration needs them.
+    #[clap(long, env, value_parser = clap::value_parser!(u64).range(1..))]
+    pub ping_interval_secs: Option<u64>,
+
+    /// Address to serve Prometheus metrics on. Metrics are not served if not
+    /// specified.
+    #[clap(long, env)]
+    pub metrics_address: Option<SocketAddr>,
+
+    /// Write the report of the migration as JSON to this file.
+    #[clap(long, env)]
+    pub report_file: Option<PathBuf>,
+
+    /// When populating `competition_auctions`, first fetch only the metadata
+    /// of auctions and read the JSON only of those that are not skipped
+    /// anyway, to avoid detoasting large values needlessly.
+    #[clap(long, env)]
+    pub metadata_first: bool,
+
+    /// When populating `competition_auctions`, only fetch the auctions that
+    /// are not in it yet, so reruns skip migrated auctions in the query.
+    #[cfg_attr(
+        feature = "destructive",
+        clap(long, env, conflicts_with_all = ["metadata_first", "overwrite"])
+    )]
+    #[cfg_attr(
+        not(feature = "destructive"),
+        clap(long, env, conflicts_with = "metadata_first")
+    )]
+    pub only_missing: bool,
+
+    /// When populating `competition_auctions`, auctions whose JSON takes more
+    /// bytes as stored are processed alone and without reading their
+    /// solutions.
+    #[clap(long, env, default_value = "10000000")]
+    pub giant_auction_bytes: i32,
+
+    /// When populating `competition_auctions`, the sources to take the JSON of
+    /// an auction from, in this order, if the stored one is missing or doesn't
+    /// decode. The source used is noted in the annotations of the auction.
+    #[clap(long, env, value_delimiter = ',')]
+    pub json_fallbacks: Vec<FallbackSource>,
+
+    /// Directory with a `<auction id>.json` file per archived auction, in the
+    /// stored or the API format, for the `archive` fallback.
+    #[clap(long, env)]
+    pub archive_dir: Option<PathBuf>,
+
+    /// Base url of the orderbook API for the `api` fallback, like
+    /// `https://api.cow.fi/mainnet`.
+    #[clap(long, env)]
+    pub api_url: Option<Url>,
+
+    /// When populating `competition_auctions`, start at the first auction
+    /// missing from it going down from the newest one, instead of below the
+    /// lowest auction it contains. Useful after lost or wrong progress.
+    #[clap(long, env)]
+    pub resume_from_frontier: bool,
+
+    /// When populating `competition_auctions`, additionally go up from the
+    /// first auction missing from it going up from the oldest one, until
+    /// meeting the pass going down from the newest one. Implies
+    /// `--resume-from-frontier`.
+    #[clap(long, env)]
+    pub bidirectional: bool,
+
+    /// When populating `competition_auctions`, first populate the auctions in
+    /// this file, one id per line, like the ones the API failed to serve.
+    /// Implies `--resume-from-frontier`.
+    #[clap(long, env)]
+    pub priority_file: Option<PathBuf>,
+
+    /// When populating `competition_auctions`, split the auctions into this
+    /// many disjoint ranges of ids and populate them concurrently, each on
+    /// its own connection.
+    #[clap(
+        long,
+        env,
+        default_value = "1",
+        conflicts_with_all = ["resume_from_frontier", "bidirectional", "priority_file", "limit"]
+    )]
+    pub workers: NonZeroUsize,
+
+    /// When populating `competition_auctions`, give up on auctions that take
+    /// longer than this many seconds and skip them as `timeout`, instead of
+    /// stalling the run on them.
+    #[clap(long, env)]
+    pub auction_timeout_secs: Option<u64>,
+
+    /// Columns of `competition_auctions` to write when populating it, comma
+    /// separated. Leave out columns the schema of the deployment doesn't have
+    /// yet, they must be nullable or have a default. `prices` stands for both
+    /// `price_tokens` and `price_values`. The `id` is always written.
+    #[clap(
+        long,
+        env,
+        value_delimiter = ',',
+        default_value = "block,deadline,order_uids,prices,surplus_capturing_jit_order_owners"
+    )]
+    pub auction_columns: Vec<AuctionColumn>,
+
+    /// Overwrite the `--auction-columns` of auctions that are already in
+    /// `competition_auctions` instead of keeping them as they are.
+    #[cfg(feature = "destructive")]
+    #[clap(long, env)]
+    pub overwrite: bool,
+
+    /// Number of auctions `migrate-auctions` and `convert-fees` fetch and
+    /// commit together. Larger batches take less time in total but hold
+    /// locks and memory for longer.
+    #[clap(long, env, default_value = "1", value_parser = clap::value_parser!(i64).range(1..))]
+    pub batch_size: i64,
+
+    /// Stop `migrate-auctions` and `convert-fees` after processing this many
+    /// auctions, for trying them end to end on a few auctions.
+    #[clap(long, env)]
+    pub limit: Option<NonZeroUsize>,
+
+    /// Milliseconds migrations pause between batches, leaving the database
+    /// to its other users.
+    #[clap(long, env, default_value = "50")]
+    pub throttle_ms: u64,
+
+    /// Which logs to print, as a `tracing` filter like `info` or
+    /// `data_migration::run=debug,info`.
+    #[clap(long, env, default_value = "info")]
+    pub log_filter: String,
+
+    /// Log counts and token amounts in scientific notation like `1.23e18`
+    /// instead of with thousands separators like `1,234,567`.
+    #[clap(long, env)]
+    pub scientific_numbers: bool,
+
+    /// Refuse to write unless the services schema of the database, as
+    /// recorded by Flyway in `flyway_schema_history`, is at least this
+    /// version, i.e. unless the services that read the written tables are
+    /// deployed against it.
+    #[clap(long, env)]
+    pub min_schema_version: Option<Version>,
+
+    /// Write even if the database is not ready for it, because a written
+    /// table is missing or the services schema is older than
+    /// `--min-schema-version`.
+    #[clap(long, env)]
+    pub force: bool,
+
+    /// Refuse to run unless the connected database has this name, so that a
+    /// command can't be pointed at the database of another chain by mistake.
+    #[clap(long, env)]
+    pub expected_db_name: Option<String>,
+
+    /// Refuse to run unless the connected database belongs to this chain, as
+    /// recorded with `set-chain-id`.
+    #[clap(long, env)]
+    pub expected_chain_id: Option<u64>,
+
+    /// Stop the run and roll back the current batch once it wrote more than
+    /// this many rows, which is a sign of a bug rather than of a big backlog.
+    #[clap(long, env)]
+    pub max_updates: Option<u64>,
+
+    /// Stop `convert-fees` when its recent batches modify a share of the order
+    /// executions they scan that deviates by more than this from the earlier
+    /// batches, like 0.5 for 50 percentage points, instead of continuing
+    /// blindly. Off if not specified, since going down from converted recent
+    /// auctions into the era of fees in the sell token raises the share
+    /// legitimately; meant for runs within one era.
+    #[clap(long, env)]
+    pub max_write_ratio_deviation: Option<f64>,
+
+    /// CSV file with the orders `convert-fees` reads instead of the `orders`
+    /// and `jit_orders` tables, captured at a fixed time so reruns are
+    /// reproducible, see [`crate::order_snapshots`].
+    #[clap(long, env)]
+    pub orders_snapshot: Option<PathBuf>,
+
+    /// CSV file `convert-fees` appends every fee it changes to, with the fee
+    /// and token before and after and the conversion rate, see
+    /// [`crate::fee_audits`].
+    #[clap(long, env)]
+    pub fee_audit_file: Option<PathBuf>,
+
+    /// How `convert-fees` rounds fees that don't convert into a whole amount
+    /// of the surplus token.
+    #[clap(long, env, value_enum, default_value = "floor")]
+    pub fee_rounding: Rounding,
+
+    /// What to do when processing an auction fails: stop the run, skip the
+    /// auction, or retry it a few times before skipping it.
+    #[clap(long, env, value_enum, default_value = "skip")]
+    pub on_error: OnError,
+
+    /// After `migrate-auctions`, `fix-missing-auctions` and `convert-fees`,
+    /// redrive the failures of earlier runs of the same migration that are
+    /// due: after 1 more run, then after 4, 16 and so on, until an auction
+    /// failed this many times. 0 leaves failures to `redrive`.
+    #[clap(long, env, default_value = "5")]
+    pub auto_retry_attempts: i32,
+
+    /// Write the `competition_auctions` row of auctions whose JSON only fails
+    /// to decode because of its solutions, instead of skipping them. The
+    /// solutions themselves are still written all or nothing.
+    #[clap(long, env)]
+    pub partial_writes: bool,
+
+    /// Run the migration without writing anything, logging samples and counts
+    /// of the rows it would write instead.
+    #[clap(long, env)]
+    pub dry_run: bool,
+
+    /// Let `convert-fees` write the fees it would change to
+    /// `order_execution_fee_proposals` instead of `order_execution`, to be
+    /// reviewed and then written with `apply-proposals`.
+    #[clap(long, env, conflicts_with = "dry_run")]
+    pub shadow_writes: bool,
+
+    /// Publish an event per processed or skipped auction, once its batch is
+    /// committed, to a Kafka topic or NATS subject like
+    /// `kafka://broker:9092/auctions` or `nats://server:4222/auctions`.
+    #[cfg(feature = "events")]
+    #[clap(long, env)]
+    pub events_url: Option<Url>,
+
+    /// Directory to write the `competition_auctions` and `order_execution`
+    /// rows of the run to as Parquet files, batch by batch, see
+    /// [`crate::parquet_export`].
+    #[cfg(feature = "parquet")]
+    #[clap(long, env)]
+    pub parquet_dir: Option<PathBuf>,
+
+    /// Only migrate auctions with at least this id. Populate runs without
+    /// `--end-auction-id` resume below the lowest auction the earlier ones
+    /// reached, ranges populated with it are not continued from.
+    #[clap(long, env)]
+    pub start_auction_id: Option<i64>,
+
+    /// Only migrate auctions with at most this id.
+    #[clap(long, env)]
+    pub end_auction_id: Option<i64>,
+
+    /// What to do. Populates `competition_auctions` if not specified.
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(clap::Subcommand)]
+pub enum Command {
+    /// Print the JSON Schema of the solver competition JSON and exit.
+    JsonSchema {
+        /// Print the schema of the `/solver_competition` API response instead
+        /// of the one stored in the database.
+        #[clap(long)]
+        api: bool,
+    },
+    /// Populate `competition_auctions` from `solver_competitions`, the same as
+    /// running without a command.
+    MigrateAuctions,
+    /// Populate only the auctions of `solver_competitions` that are missing
+    /// from `competition_auctions`, instead of scanning the whole range again.
+    FixMissingAuctions,
+    /// Express the executed fees of orders in their surplus token: the buy
+    /// token of sell orders and the sell token of buy orders.
+    #[cfg(feature = "destructive")]
+    ConvertFees,
+    /// Fill the fee token of order executions from before the column existed
+    /// with the sell token of their order.
+    #[cfg(feature = "destructive")]
+    BackfillExecutedFeeToken,
+    /// Fold the legacy solver determined `surplus_fee` of order executions
+    /// into their `executed_fee`.
+    #[cfg(feature = "destructive")]
+    ConsolidateSurplusFee,
+    /// Write the executed amounts of legacy orders in all proposed solutions
+    /// to `proposed_trade_executions`.
+    #[cfg(feature = "destructive")]
+    RecomputeExecutedAmounts,
+    /// Rehearse the cutover against a staging copy of the database: populate
+    /// `competition_auctions`, verify it, convert the fees and verify again,
+    /// timing each step. Writes the reports of all steps to `--report-file`
+    /// and fails if any auction failed or mismatches. Pruning the migrated
+    /// solver competitions is not rehearsed, as this tool doesn't prune.
+    #[cfg(feature = "destructive")]
+    Rehearse,
+    /// Write the fees proposed by `convert-fees --shadow-writes` to
+    /// `order_execution`. Proposals whose order execution changed since are
+    /// marked stale instead.
+    #[cfg(feature = "destructive")]
+    ApplyProposals,
+    /// Undo a migration by restoring the values it backed up before
+    /// overwriting them, where they are still the ones it wrote. Values that
+    /// changed since are left alone and reported. Restored auctions lose the
+    /// part the migration wrote in their status, so it processes them again
+    /// when run again.
+    #[cfg(feature = "destructive")]
+    Rollback {
+        #[clap(value_enum)]
+        migration: RollbackMigration,
+    },
+    /// Apply changes computed offline, in one transaction: the fee changes of
+    /// a `--fee-audit-file` if `file` ends in `.csv`, else the auctions of an
+    /// `export`. Nothing is applied if a fee changed since the file was
+    /// computed.
+    #[cfg(feature = "destructive")]
+    ApplyFromFile { file: PathBuf },
+    /// Run the migrations again over only the auctions they failed to
+    /// process, as recorded in `migration_failures`.
+    Redrive,
+    /// Apply the resolutions filled into a file written by `export-failures`:
+    /// `skip` never retries the auction again, `retry` queues it for the next
+    /// `redrive`, optionally followed by global arguments to retry it with,
+    /// like `retry --auction-timeout-secs 600`.
+    ImportResolutions { file: PathBuf },
+    /// Check every JSON stored in `solver_competitions` against the schema
+    /// and report the violations.
+    ValidateJson,
+    /// Check the consistency of every row in `competition_auctions` and
+    /// print the broken ones, then compare every auction with the one derived
+    /// again from `solver_competitions` and report the mismatches. Also checks
+    /// that the fee token of every order execution is the surplus token of
+    /// its order, and converts a sample of the fees converted with
+    /// `--shadow-writes` again with `--fee-rounding`, and reports the
+    /// auctions that list an order uid more than once.
+    Verify,
+    /// Check that every order execution of a settled auction in the range has
+    /// a matching trade and vice versa, and print the orphans and how many
+    /// there are.
+    CheckTrades {
+        /// How many orphans to print at most.
+        #[clap(long, default_value = "100")]
+        limit: i64,
+    },
+    /// Compare two reports written with `--report-file` and print which
+    /// auctions got fixed or broken in between.
+    CompareReports { old: PathBuf, new: PathBuf },
+    /// Print the largest contiguous ranges of auction ids that each migration
+    /// still has to process.
+    ReportRemaining {
+        /// How many ranges to print per migration.
+        #[clap(long, default_value = "10")]
+        top: i64,
+    },
+    /// Count the order executions `convert-fees` still has to convert per
+    /// bucket of auction ids, in a single query, and print the buckets with
+    /// the most first.
+    Scope {
+        /// Auction ids per bucket.
+        #[clap(long, default_value = "100000", value_parser = clap::value_parser!(i64).range(1..))]
+        bucket_size: i64,
+        /// How many buckets to print at most.
+        #[clap(long)]
+        top: Option<i64>,
+    },
+    /// Mark the auction ids `start..=end` as owned by `owner`. Migrations
+    /// don't write auctions in ranges owned by anyone else.
+    MarkOwned {
+        start: i64,
+        end: i64,
+        #[clap(long, default_value = "services")]
+        owner: String,
+    },
+    /// Run a command against the database of every profile in a TOML file,
+    /// like `run-all --config profiles.toml -- --batch-size 10 convert-fees`,
+    /// and write one report for all of them to `--report-file`. The arguments
+    /// after `--` are those of a regular invocation, without `--db-url`.
+    RunAll {
+        /// File with a table per profile, holding its `db_url` and optionally
+        /// `args` passed before the ones after `--`.
+        #[clap(long)]
+        config: PathBuf,
+        /// Only run against these profiles, comma separated, in this order.
+        #[clap(long, value_delimiter = ',')]
+        profiles: Vec<String>,
+        /// How many profiles to run against at the same time.
+        #[clap(long, default_value = "1")]
+        concurrency: NonZeroUsize,
+        #[clap(last = true)]
+        args: Vec<String>,
+    },
+    /// Attach a note to a run or an auction, or list the existing notes if
+    /// no note is given.
+    #[clap(group(clap::ArgGroup::new("target").required(true).multiple(true)))]
+    Annotate {
+        #[clap(long, group = "target")]
+        run_id: Option<i64>,
+        #[clap(long, group = "target")]
+        auction_id: Option<i64>,
+        #[clap(long)]
+        note: Option<String>,
+    },
+    /// Record the chain the database belongs to, which `--expected-chain-id`
+    /// is checked against.
+    SetChainId { chain_id: u64 },
+    /// Show which parts of an auction the migrations have written.
+    AuctionStatus { auction_id: i64 },
+    /// Export the auctions in `competition_auctions` within the range as JSON
+    /// Lines, one object per auction.
+    #[cfg(feature = "export")]
+    Export {
+        /// File to write to, stdout by default, in which case `--log-filter
+        /// off` keeps the logs out of the export.
+        #[clap(long)]
+        output: Option<PathBuf>,
+    },
+    /// Export the auctions skipped in a report written with `--report-file`,
+    /// with context from the database, for triage in a spreadsheet. The
+    /// empty `resolution` column is there to be filled in.
+    #[cfg(feature = "export")]
+    ExportFailures {
+        report: PathBuf,
+        #[clap(long, value_enum, default_value = "csv")]
+        format: crate::failures::ExportFormat,
+        /// File to write to, logs go to stdout.
+        #[clap(long)]
+        output: PathBuf,
+    },
+    /// Write the solver competition JSON of the auctions in the range to
+    /// `--archive-dir`, as a JSON Lines object per `--batch-size` auctions,
+    /// each with a manifest of its auction ids, rows and SHA-256.
+    #[cfg(feature = "export")]
+    Archive,
+    /// Check the objects in `--archive-dir` against their manifests and
+    /// record which ones match in `migration_archives`.
+    #[cfg(feature = "export")]
+    VerifyArchive,
+    /// Upload an export to a Google Cloud Storage bucket in chunks. An
+    /// interrupted upload of the same file continues where it stopped when
+    /// run again, as recorded in `migration_uploads`.
+    #[cfg(feature = "object-storage")]
+    Upload {
+        file: PathBuf,
+        /// The upload endpoint of the bucket, like
+        /// `https://storage.googleapis.com/upload/storage/v1/b/<bucket>/o`.
+        #[clap(long)]
+        url: Url,
+        /// Name of the object, the file name by default.
+        #[clap(long)]
+        object: Option<String>,
+        /// OAuth access token to start the upload with.
+        #[clap(long, env = "UPLOAD_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+        /// Size of the chunks sent at once, rounded down to a multiple of
+        /// 256 KiB.
+        #[clap(long, default_value = "8388608")]
+        chunk_bytes: NonZeroUsize,
+    },
+}
+
+/// Migrations that can be rolled back.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum RollbackMigration {
+    /// Restore the fees `convert-fees` and `apply-proposals` overwrote from
+    /// `fee_conversion_backup`.
+    ConvertFees,
 }
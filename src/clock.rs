@@ -0,0 +1,59 @@
+//! The source of the time migrations wait on between batches and before
+//! retries, so tests can run code that pauses for minutes without waiting.
+//!
+//! Only these waits go through it. The durations in reports and the deadlines
+//! of `--auction-timeout-secs` measure how long the work really took, so they
+//! stay on [`std::time::Instant`].
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+#[derive(Clone, Debug, Default)]
+pub enum Clock {
+    /// Waits in real time.
+    #[default]
+    System,
+    /// Returns from waits right away, only adding up how long they would have
+    /// taken.
+    Manual(Arc<Mutex<Duration>>),
+}
+
+impl Clock {
+    pub fn manual() -> Self {
+        Self::Manual(Default::default())
+    }
+
+    /// Waits for `duration`.
+    pub async fn sleep(&self, duration: Duration) {
+        match self {
+            Self::System => tokio::time::sleep(duration).await,
+            Self::Manual(elapsed) => *elapsed.lock().unwrap() += duration,
+        }
+    }
+
+    /// How long a manual clock has waited in total, zero for the system clock.
+    pub fn waited(&self) -> Duration {
+        match self {
+            Self::System => Duration::ZERO,
+            Self::Manual(elapsed) => *elapsed.lock().unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual() {
+        let clock = Clock::manual();
+        futures::executor::block_on(async {
+            clock.sleep(Duration::from_secs(600)).await;
+            clock.clone().sleep(Duration::from_secs(5)).await;
+        });
+        assert_eq!(clock.waited(), Duration::from_secs(605));
+        assert_eq!(Clock::System.waited(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,110 @@
+//! Conversions between `U256` and the arbitrary precision types used to store
+//! token amounts in `NUMERIC` Postgres columns.
+//!
+//! Every value written by this tool is an unsigned 256 bit integer, so the
+//! round trip `big_decimal_to_u256(&u256_to_big_decimal(&x)) == Some(x)` holds
+//! for all `x`. The reverse direction is partial: decimals with a non-zero
+//! fractional part, negative values and values above `U256::MAX` don't have a
+//! `U256` representation and are rejected.
+
+use {
+    anyhow::{ensure, Result},
+    bigdecimal::{
+        num_bigint::{Sign, ToBigInt},
+        BigDecimal,
+    },
+    num::{BigInt, BigUint},
+    primitive_types::U256,
+};
+
+pub fn u256_to_big_uint(input: &U256) -> BigUint {
+    let mut bytes = [0; 32];
+    input.to_big_endian(&mut bytes);
+    BigUint::from_bytes_be(&bytes)
+}
+
+/// Converts a `U256` into a `BigDecimal` with scale 0. Never fails.
+pub fn u256_to_big_decimal(u256: &U256) -> BigDecimal {
+    let big_uint = u256_to_big_uint(u256);
+    BigDecimal::from(BigInt::from(big_uint))
+}
+
+/// Fails if the value does not fit into 256 bits.
+pub fn big_uint_to_u256(input: &BigUint) -> Result<U256> {
+    let bytes = input.to_bytes_be();
+    ensure!(bytes.len() <= 32, "too large");
+    Ok(U256::from_big_endian(&bytes))
+}
+
+/// Fails if the value is negative or does not fit into 256 bits.
+pub fn big_int_to_u256(input: &BigInt) -> Result<U256> {
+    ensure!(input.sign() != Sign::Minus, "negative");
+    big_uint_to_u256(input.magnitude())
+}
+
+/// Converts a `BigDecimal` into a `U256`.
+///
+/// The scale of the input doesn't matter as long as the value is integral, so
+/// `1`, `1.000` and `1e0` all decode to the same number. Returns `None` if the
+/// value has a non-zero fractional part, is negative or exceeds `U256::MAX`.
+pub fn big_decimal_to_u256(big_decimal: &BigDecimal) -> Option<U256> {
+    if !big_decimal.is_integer() {
+        return None;
+    }
+    let big_int = big_decimal.to_bigint()?;
+    big_int_to_u256(&big_int).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::str::FromStr};
+
+    #[test]
+    fn round_trip() {
+        for value in [
+            U256::zero(),
+            U256::one(),
+            U256::from(1_000_000_000_000_000_000u128),
+            U256::MAX - 1,
+            U256::MAX,
+        ] {
+            let decimal = u256_to_big_decimal(&value);
+            assert_eq!(decimal.to_string(), value.to_string());
+            assert_eq!(big_decimal_to_u256(&decimal), Some(value));
+        }
+    }
+
+    #[test]
+    fn big_decimal_scale() {
+        for input in ["1", "1.0", "1.000", "1e0", "0.1e1", "100e-2"] {
+            let decimal = BigDecimal::from_str(input).unwrap();
+            assert_eq!(big_decimal_to_u256(&decimal), Some(1.into()), "{input}");
+        }
+        let decimal = BigDecimal::from_str("1e3").unwrap();
+        assert_eq!(big_decimal_to_u256(&decimal), Some(1000.into()));
+    }
+
+    #[test]
+    fn big_decimal_fractional() {
+        for input in ["0.5", "1.01", "1e-1"] {
+            let decimal = BigDecimal::from_str(input).unwrap();
+            assert_eq!(big_decimal_to_u256(&decimal), None, "{input}");
+        }
+    }
+
+    #[test]
+    fn big_decimal_sign() {
+        let decimal = BigDecimal::from_str("-1").unwrap();
+        assert_eq!(big_decimal_to_u256(&decimal), None);
+        let decimal = BigDecimal::from_str("-0").unwrap();
+        assert_eq!(big_decimal_to_u256(&decimal), Some(U256::zero()));
+    }
+
+    #[test]
+    fn big_decimal_overflow() {
+        let max = u256_to_big_decimal(&U256::MAX);
+        assert_eq!(big_decimal_to_u256(&(max + BigDecimal::from(1))), None);
+        let decimal = BigDecimal::from_str("1e78").unwrap();
+        assert_eq!(big_decimal_to_u256(&decimal), None);
+    }
+}
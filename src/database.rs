@@ -1,8 +1,93 @@
-use {sqlx::PgPool, std::num::NonZeroUsize};
+use {
+    crate::{
+        clock::Clock, database_audit::MIGRATION_SETTING,
+        database_solver_competition::AuctionColumn, fee_audits::FeeAudit,
+        order_snapshots::OrderSnapshot, prices::Rounding, report::OnError, sinks::SharedSink,
+        sources::Fallbacks,
+    },
+    anyhow::{bail, Result},
+    sqlx::{postgres::PgPoolOptions, Connection, PgConnection, PgPool},
+    std::{
+        num::NonZeroUsize,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        },
+        time::Duration,
+    },
+    tracing::warn,
+};
+
+/// Contiguous range of auction ids `start..=end` containing `count` auctions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, sqlx::FromRow)]
+pub struct IdRange {
+    pub start: i64,
+    pub end: i64,
+    pub count: i64,
+}
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub insert_batch_size: NonZeroUsize,
+    /// Log what migrations would write instead of writing it.
+    pub dry_run: bool,
+    /// Write converted fees to `order_execution_fee_proposals` instead of
+    /// `order_execution`.
+    pub shadow_writes: bool,
+    /// Columns of `competition_auctions` populated auctions are written to.
+    pub auction_columns: Vec<AuctionColumn>,
+    /// The schema detected at startup, recorded with every run, see
+    /// [`crate::database_schema`].
+    pub schema_variant: Option<String>,
+    /// Overwrite auctions that already exist instead of keeping them.
+    pub overwrite: bool,
+    /// Where populating takes the JSON of auctions from whose stored one is
+    /// missing or doesn't decode.
+    pub fallbacks: Fallbacks,
+    /// Orders the fee conversion reads instead of the tables, if given.
+    pub orders_snapshot: Option<Arc<OrderSnapshot>>,
+    /// Audit trail of the fees the fee conversion changes, if given.
+    pub fee_audit: Option<Arc<std::sync::Mutex<FeeAudit>>>,
+    /// How to react to failing to process an auction.
+    pub on_error: OnError,
+    /// Write the auction rows of auctions whose solutions don't decode.
+    pub partial_writes: bool,
+    /// Stop the run before committing a batch that brings the rows written
+    /// beyond this.
+    pub max_updates: Option<u64>,
+    /// Stop the run when batches modify a share of the rows they scan that
+    /// deviates more from the earlier batches, see [`crate::anomalies`].
+    pub max_write_ratio_deviation: Option<f64>,
+    /// How converting fees into another token rounds.
+    pub fee_rounding: Rounding,
+    /// What migrations wait on between batches and before retries.
+    pub clock: Clock,
+    /// Where the written rows are copied to besides Postgres.
+    pub sinks: Vec<SharedSink>,
+    /// Rows written so far, shared by all clones of the config.
+    pub updated_rows: Arc<AtomicU64>,
+}
+
+impl Config {
+    /// Counts `rows` more written rows.
+    pub fn count_updates(&self, rows: u64) {
+        self.updated_rows.fetch_add(rows, Ordering::Relaxed);
+    }
+
+    /// Fails if more rows were written than `max_updates`. Migrations check
+    /// this before committing each batch, so the batch that crosses the
+    /// limit is rolled back.
+    pub fn check_max_updates(&self) -> Result<()> {
+        let updated = self.updated_rows.load(Ordering::Relaxed);
+        match self.max_updates {
+            Some(max_updates) if updated > max_updates => bail!(
+                "wrote {updated} rows, more than --max-updates {max_updates}, rolled back the \
+                 current batch; check the run and invoke it again with a higher bound if the \
+                 volume is expected"
+            ),
+            _ => Ok(()),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -11,11 +96,189 @@ pub struct Postgres {
     pub config: Config,
 }
 
+/// How the connections of the pool are set up.
+#[derive(Clone, Debug)]
+pub struct PoolOptions {
+    pub max_connections: u32,
+    /// The migrations writing through the pool, which their writes are
+    /// recorded for in `migration_audit_log`, see [`crate::database_audit`].
+    pub migration: Option<String>,
+    /// The `tcp_keepalives_idle`, `tcp_keepalives_interval` and
+    /// `tcp_keepalives_count` of the server side of the connections, the
+    /// server defaults if `None`. Probes in either direction keep NATs from
+    /// dropping connections that are idle between queries.
+    pub tcp_keepalive_idle: Option<Duration>,
+    pub tcp_keepalive_interval: Option<Duration>,
+    pub tcp_keepalive_count: Option<u32>,
+    /// How often the idle connections of the pool are pinged, never if
+    /// `None`.
+    pub ping_interval: Option<Duration>,
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            migration: None,
+            tcp_keepalive_idle: None,
+            tcp_keepalive_interval: None,
+            tcp_keepalive_count: None,
+            ping_interval: None,
+        }
+    }
+}
+
+impl PoolOptions {
+    /// The settings every connection starts its session with.
+    fn settings(&self) -> Vec<(&'static str, String)> {
+        let secs = |duration: &Duration| duration.as_secs().to_string();
+        [
+            (MIGRATION_SETTING, self.migration.clone()),
+            (
+                "tcp_keepalives_idle",
+                self.tcp_keepalive_idle.as_ref().map(secs),
+            ),
+            (
+                "tcp_keepalives_interval",
+                self.tcp_keepalive_interval.as_ref().map(secs),
+            ),
+            (
+                "tcp_keepalives_count",
+                self.tcp_keepalive_count.map(|count| count.to_string()),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value?)))
+        .collect()
+    }
+}
+
+/// Sets the session `settings` of the connection.
+async fn set_settings(
+    ex: &mut PgConnection,
+    settings: &[(&'static str, String)],
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT set_config($1, $2, false);"#;
+
+    for (name, value) in settings {
+        sqlx::query(QUERY)
+            .bind(*name)
+            .bind(value.as_str())
+            .execute(&mut *ex)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Limits each statement of the current transaction to `timeout`, or
+/// restores the limit of the session with `None`, until the transaction ends
+/// or the savepoint it is set in is rolled back. The server cancels
+/// statements that take longer, with an error [`is_statement_timeout`]
+/// recognizes.
+pub async fn set_statement_timeout(
+    ex: &mut PgConnection,
+    timeout: Option<Duration>,
+) -> Result<(), sqlx::Error> {
+    const SET: &str = r#"
+        SELECT set_config('statement_timeout', $1, true);"#;
+    const RESET: &str = r#"
+        SET LOCAL statement_timeout TO DEFAULT;"#;
+
+    match timeout {
+        Some(timeout) => {
+            // 0 would be no limit
+            let milliseconds = timeout.as_millis().max(1);
+            sqlx::query(SET)
+                .bind(milliseconds.to_string())
+                .execute(ex)
+                .await?
+        }
+        None => sqlx::query(RESET).execute(ex).await?,
+    };
+    Ok(())
+}
+
+/// Whether `err` is caused by a statement that was cancelled for exceeding
+/// the `statement_timeout`.
+pub fn is_statement_timeout(err: &(dyn std::error::Error + 'static)) -> bool {
+    // query_canceled
+    const QUERY_CANCELED: &str = "57014";
+    std::iter::successors(Some(err), |err| err.source()).any(|err| {
+        matches!(
+            err.downcast_ref::<sqlx::Error>(),
+            Some(sqlx::Error::Database(err)) if err.code().as_deref() == Some(QUERY_CANCELED)
+        )
+    })
+}
+
+/// Pings the idle connections of `pool` every `interval` until it is closed.
+/// Connections that fail the ping are closed, the pool opens new ones when
+/// they are needed.
+async fn ping_idle_connections(pool: PgPool, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        if pool.is_closed() {
+            return;
+        }
+        // held together so each idle connection is pinged once
+        let idle = pool.num_idle();
+        let connections: Vec<_> = std::iter::from_fn(|| pool.try_acquire())
+            .take(idle)
+            .collect();
+        for mut connection in connections {
+            if let Err(err) = connection.ping().await {
+                warn!("closing a connection that failed a ping: {err}");
+                drop(connection.detach());
+            }
+        }
+    }
+}
+
 impl Postgres {
     pub async fn new(url: &str, insert_batch_size: NonZeroUsize) -> sqlx::Result<Self> {
+        Self::connect(url, insert_batch_size, PoolOptions::default()).await
+    }
+
+    /// Like [`Self::new`], with the pool set up according to `options`.
+    pub async fn connect(
+        url: &str,
+        insert_batch_size: NonZeroUsize,
+        options: PoolOptions,
+    ) -> sqlx::Result<Self> {
+        let mut pool = PgPoolOptions::new().max_connections(options.max_connections);
+        let settings = options.settings();
+        if !settings.is_empty() {
+            pool = pool.after_connect(move |ex, _| {
+                let settings = settings.clone();
+                Box::pin(async move { set_settings(ex, &settings).await })
+            });
+        }
+        let pool = pool.connect(url).await?;
+        if let Some(interval) = options.ping_interval {
+            tokio::spawn(ping_idle_connections(pool.clone(), interval));
+        }
         Ok(Self {
-            pool: PgPool::connect(url).await?,
-            config: Config { insert_batch_size },
+            pool,
+            config: Config {
+                insert_batch_size,
+                dry_run: false,
+                shadow_writes: false,
+                auction_columns: AuctionColumn::ALL.to_vec(),
+                schema_variant: None,
+                overwrite: false,
+                fallbacks: Default::default(),
+                orders_snapshot: None,
+                fee_audit: None,
+                on_error: Default::default(),
+                partial_writes: false,
+                max_updates: None,
+                max_write_ratio_deviation: None,
+                fee_rounding: Default::default(),
+                clock: Default::default(),
+                sinks: Vec::new(),
+                updated_rows: Default::default(),
+            },
         })
     }
 
@@ -0,0 +1,133 @@
+//! Which parts of each auction the migrations have written, as bits in
+//! `migration_auction_status`, so whether an auction is fully normalized is a
+//! single row lookup.
+
+use sqlx::PgConnection;
+
+/// The `competition_auctions` row.
+pub const AUCTION: i32 = 1;
+/// The prices in the `competition_auctions` row.
+pub const PRICES: i32 = 1 << 1;
+/// The `proposed_trade_executions` of all solutions.
+pub const SOLUTIONS: i32 = 1 << 2;
+/// The converted fees in `order_execution`.
+pub const EXECUTIONS: i32 = 1 << 3;
+/// The settlements, linked to the auction by their transaction hashes.
+pub const TX_HASHES: i32 = 1 << 4;
+
+/// Every part, with a name for each.
+pub const PARTS: [(i32, &str); 5] = [
+    (AUCTION, "auction"),
+    (PRICES, "prices"),
+    (SOLUTIONS, "solutions"),
+    (EXECUTIONS, "executions"),
+    (TX_HASHES, "tx_hashes"),
+];
+
+/// The status of a fully normalized auction.
+pub const COMPLETE: i32 = AUCTION | PRICES | SOLUTIONS | EXECUTIONS | TX_HASHES;
+
+/// Not a part but a flag: the auction has no solutions, so no winner whose
+/// executions and settlements could be written.
+pub const NO_WINNER: i32 = 1 << 5;
+
+/// The parts written of a fully normalized auction with `status`, which lacks
+/// the execution level ones if it has no winner.
+pub fn complete(status: i32) -> i32 {
+    match status & NO_WINNER {
+        0 => COMPLETE,
+        _ => COMPLETE & !(EXECUTIONS | TX_HASHES),
+    }
+}
+
+/// The names of the parts in `status`, in the order of [`PARTS`].
+pub fn names(status: i32) -> Vec<&'static str> {
+    PARTS
+        .iter()
+        .filter(|(part, _)| status & part != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+/// Records that `parts` of each of `auction_ids` are written, in addition to
+/// the parts recorded before.
+pub async fn mark(
+    ex: &mut PgConnection,
+    auction_ids: &[i64],
+    parts: i32,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+        INSERT INTO migration_auction_status (auction_id, status)
+        SELECT id, $2 FROM unnest($1::bigint[]) AS ids (id)
+        ON CONFLICT (auction_id) DO UPDATE SET
+            status = migration_auction_status.status | EXCLUDED.status,
+            updated_at = now();"#;
+
+    if auction_ids.is_empty() || parts == 0 {
+        return Ok(());
+    }
+    sqlx::query(QUERY)
+        .bind(auction_ids)
+        .bind(parts)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Records that `parts` of each of `auction_ids` are no longer written, like
+/// after they were rolled back.
+pub async fn unmark(
+    ex: &mut PgConnection,
+    auction_ids: &[i64],
+    parts: i32,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+        UPDATE migration_auction_status
+        SET status = status & ~$2, updated_at = now()
+        WHERE auction_id = ANY($1);"#;
+
+    if auction_ids.is_empty() || parts == 0 {
+        return Ok(());
+    }
+    sqlx::query(QUERY)
+        .bind(auction_ids)
+        .bind(parts)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Fetches the status of `auction_id`, `None` if nothing of it is written.
+pub async fn fetch(ex: &mut PgConnection, auction_id: i64) -> Result<Option<i32>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT status
+        FROM migration_auction_status
+        WHERE auction_id = $1;"#;
+
+    sqlx::query_scalar(QUERY)
+        .bind(auction_id)
+        .fetch_optional(ex)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn part_names() {
+        assert!(names(0).is_empty());
+        assert_eq!(names(AUCTION | SOLUTIONS), ["auction", "solutions"]);
+        assert_eq!(names(COMPLETE).len(), PARTS.len());
+        assert!(names(NO_WINNER).is_empty());
+    }
+
+    #[test]
+    fn complete_without_winner() {
+        assert_eq!(complete(AUCTION), COMPLETE);
+        assert_eq!(
+            names(complete(AUCTION | NO_WINNER)),
+            ["auction", "prices", "solutions"]
+        );
+    }
+}
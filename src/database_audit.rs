@@ -0,0 +1,46 @@
+//! The audit log of the writes of the tool, `migration_audit_log`. Every row
+//! a migration inserts or updates in the tables it migrates is recorded with
+//! its primary key, its values before and after the write, the migration and
+//! the transaction, so what a run did can be reconstructed batch by batch.
+//!
+//! The rows are recorded by the statements writing them, with the [`audited`]
+//! CTE, so the writes of the services to the same tables cost nothing extra.
+//! Only writes of connections that set the migration they write for in
+//! [`MIGRATION_SETTING`] are recorded, like the pool does given
+//! [`crate::database::PoolOptions::migration`].
+
+/// The session setting holding the migrations the writes of a connection
+/// belong to.
+pub const MIGRATION_SETTING: &str = "data_migration.migration";
+
+/// The `audited` CTE of a statement writing to `table`, which records the
+/// rows of `written` in `migration_audit_log`.
+///
+/// `written` is a CTE, or a join of CTEs, with the values of every written row
+/// in `new_values` and the values updated rows had before in `old_values`,
+/// NULL for inserted rows. `primary_key` are the primary key columns of
+/// `table`.
+pub fn audited(table: &str, primary_key: &[&str], written: &str) -> String {
+    let primary_key: Vec<_> = primary_key
+        .iter()
+        .map(|column| format!("'{column}', new_values -> '{column}'"))
+        .collect();
+    format!(
+        r#"
+        audited AS (
+            INSERT INTO migration_audit_log
+                (migration, table_name, operation, pk, old_values, new_values, batch_id)
+            SELECT
+                current_setting('{MIGRATION_SETTING}'),
+                '{table}',
+                CASE WHEN old_values IS NULL THEN 'INSERT' ELSE 'UPDATE' END,
+                jsonb_build_object({}),
+                old_values,
+                new_values,
+                txid_current()
+            FROM {written}
+            WHERE NULLIF(current_setting('{MIGRATION_SETTING}', true), '') IS NOT NULL
+        )"#,
+        primary_key.join(", ")
+    )
+}
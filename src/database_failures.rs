@@ -0,0 +1,238 @@
+use sqlx::{PgConnection, QueryBuilder};
+
+/// What helps triaging a failed auction without opening the database.
+#[derive(Clone, Debug, Default, Eq, PartialEq, sqlx::FromRow)]
+pub struct FailureContext {
+    pub auction_id: i64,
+    /// Size of the solver competition JSON as text, `None` if it is null or
+    /// the auction has none.
+    pub json_bytes: Option<i32>,
+    /// Distinct kinds of the executed orders of the auction, comma separated.
+    pub order_kinds: Option<String>,
+}
+
+/// Fetches the context of each of `auction_ids`, ordered by id.
+pub async fn fetch_contexts(
+    ex: &mut PgConnection,
+    auction_ids: &[i64],
+) -> Result<Vec<FailureContext>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT
+            ids.id AS auction_id,
+            octet_length(sc.json::text) AS json_bytes,
+            (
+                SELECT string_agg(DISTINCT COALESCE(o.kind, j.kind)::text, ',')
+                FROM order_execution oe
+                LEFT JOIN orders o ON o.uid = oe.order_uid
+                LEFT JOIN jit_orders j ON j.uid = oe.order_uid
+                WHERE oe.auction_id = ids.id
+            ) AS order_kinds
+        FROM unnest($1::bigint[]) AS ids (id)
+        LEFT JOIN solver_competitions sc ON sc.id = ids.id
+        ORDER BY ids.id ASC;"#;
+
+    sqlx::query_as(QUERY).bind(auction_ids).fetch_all(ex).await
+}
+
+/// An auction a migration failed to process, kept until it is resolved.
+#[derive(Clone, Debug, Eq, PartialEq, sqlx::FromRow)]
+pub struct Failure {
+    pub migration: String,
+    pub auction_id: i64,
+    pub reason: String,
+    pub error: String,
+    /// Where the raw data of the auction is, like `solver_competitions:123`.
+    pub payload: String,
+    pub attempts: i32,
+    /// Global arguments, space separated, to retry the auction with instead
+    /// of those of the redrive.
+    pub retry_args: Option<String>,
+}
+
+/// Records that `migration` failed to process each auction of `failures`
+/// for a reason with an error. Failures recorded before count one more
+/// attempt and are unresolved again, unless they are tombstoned.
+pub async fn record(
+    ex: &mut PgConnection,
+    migration: &str,
+    failures: &[(i64, &str, &str)],
+) -> Result<(), sqlx::Error> {
+    if failures.is_empty() {
+        return Ok(());
+    }
+    let mut query = QueryBuilder::new(
+        "INSERT INTO migration_failures (migration, auction_id, reason, error, payload) ",
+    );
+    query.push_values(failures, |mut row, (auction_id, reason, error)| {
+        row.push_bind(migration)
+            .push_bind(auction_id)
+            .push_bind(reason)
+            .push_bind(error)
+            .push_bind(format!("solver_competitions:{auction_id}"));
+    });
+    query.push(
+        r#"
+        ON CONFLICT (migration, auction_id, reason) DO UPDATE SET
+            error = EXCLUDED.error,
+            attempts = migration_failures.attempts + 1,
+            last_failed_at = now(),
+            resolved_at = CASE
+                WHEN migration_failures.tombstoned_at IS NULL THEN NULL
+                ELSE migration_failures.resolved_at
+            END;"#,
+    );
+    query.build().execute(ex).await?;
+    Ok(())
+}
+
+/// Fetches the unresolved failures, ordered by migration and auction id.
+pub async fn fetch_unresolved(ex: &mut PgConnection) -> Result<Vec<Failure>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT migration, auction_id, reason, error, payload, attempts, retry_args
+        FROM migration_failures
+        WHERE resolved_at IS NULL
+        ORDER BY migration ASC, auction_id ASC, reason ASC;"#;
+
+    sqlx::query_as(QUERY).fetch_all(ex).await
+}
+
+/// How many more runs of its migration have to start after each failure of
+/// an auction before it is retried automatically again, see [`is_due`].
+pub const RETRY_SPACING: i64 = 4;
+
+/// Whether a failure that happened `attempts` times is due for an automatic
+/// retry, `runs_since` runs of its migration after it last failed: after 1
+/// run, then 4, 16 and so on, until it failed `max_attempts` times.
+pub fn is_due(attempts: i32, runs_since: i64, max_attempts: i32) -> bool {
+    let Ok(spacing) = u32::try_from(attempts - 1) else {
+        return false;
+    };
+    attempts < max_attempts
+        && RETRY_SPACING
+            .checked_pow(spacing)
+            .is_some_and(|runs| runs_since >= runs)
+}
+
+/// An unresolved failure with the number of runs of its migration started
+/// since it last failed.
+#[derive(Clone, Debug, Eq, PartialEq, sqlx::FromRow)]
+pub struct PendingFailure {
+    #[sqlx(flatten)]
+    pub failure: Failure,
+    pub runs_since: i64,
+}
+
+/// Fetches the unresolved failures of `migration` that aren't tombstoned,
+/// ordered by auction id.
+pub async fn fetch_pending(
+    ex: &mut PgConnection,
+    migration: &str,
+) -> Result<Vec<PendingFailure>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT
+            f.migration, f.auction_id, f.reason, f.error, f.payload, f.attempts, f.retry_args,
+            (
+                SELECT COUNT(*)
+                FROM migration_runs r
+                WHERE r.migration = f.migration AND r.started_at > f.last_failed_at
+            ) AS runs_since
+        FROM migration_failures f
+        WHERE f.migration = $1 AND f.resolved_at IS NULL AND f.tombstoned_at IS NULL
+        ORDER BY f.auction_id ASC, f.reason ASC;"#;
+
+    sqlx::query_as(QUERY).bind(migration).fetch_all(ex).await
+}
+
+/// Marks all failures of `migration` in `auction_ids` as resolved.
+pub async fn resolve(
+    ex: &mut PgConnection,
+    migration: &str,
+    auction_ids: &[i64],
+) -> Result<u64, sqlx::Error> {
+    const QUERY: &str = r#"
+        UPDATE migration_failures
+        SET resolved_at = now()
+        WHERE migration = $1 AND auction_id = ANY($2) AND resolved_at IS NULL;"#;
+
+    let result = sqlx::query(QUERY)
+        .bind(migration)
+        .bind(auction_ids)
+        .execute(ex)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Resolves the failure of `migration` to process `auction_id` for `reason`
+/// for good, recording it first if it wasn't yet. It is never retried again.
+pub async fn tombstone(
+    ex: &mut PgConnection,
+    migration: &str,
+    auction_id: i64,
+    reason: &str,
+    error: &str,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+        INSERT INTO migration_failures
+            (migration, auction_id, reason, error, payload, resolved_at, tombstoned_at)
+        VALUES ($1, $2, $3, $4, 'solver_competitions:' || $2, now(), now())
+        ON CONFLICT (migration, auction_id, reason) DO UPDATE SET
+            resolved_at = now(),
+            tombstoned_at = now();"#;
+
+    sqlx::query(QUERY)
+        .bind(migration)
+        .bind(auction_id)
+        .bind(reason)
+        .bind(error)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Queues the failure of `migration` to process `auction_id` for `reason`
+/// for the next redrive with `retry_args`, recording it first if it wasn't
+/// yet. Lifts a tombstone.
+pub async fn retry(
+    ex: &mut PgConnection,
+    migration: &str,
+    auction_id: i64,
+    reason: &str,
+    error: &str,
+    retry_args: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+        INSERT INTO migration_failures
+            (migration, auction_id, reason, error, payload, retry_args)
+        VALUES ($1, $2, $3, $4, 'solver_competitions:' || $2, $5)
+        ON CONFLICT (migration, auction_id, reason) DO UPDATE SET
+            resolved_at = NULL,
+            tombstoned_at = NULL,
+            retry_args = EXCLUDED.retry_args;"#;
+
+    sqlx::query(QUERY)
+        .bind(migration)
+        .bind(auction_id)
+        .bind(reason)
+        .bind(error)
+        .bind(retry_args)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_spacing() {
+        assert!(!is_due(1, 0, 5));
+        assert!(is_due(1, 1, 5));
+        assert!(!is_due(2, 3, 5));
+        assert!(is_due(2, 4, 5));
+        assert!(!is_due(3, 15, 5));
+        assert!(is_due(3, 16, 5));
+        assert!(!is_due(5, 1_000_000, 5));
+        assert!(!is_due(0, 1, 5));
+    }
+}
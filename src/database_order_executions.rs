@@ -7,7 +7,10 @@ use sqlx::{
 };
 use std::fmt::{self, Debug, Formatter};
 
-use crate::database_orders::{Address, OrderUid};
+use crate::{
+    database::IdRange,
+    database_orders::{Address, OrderUid},
+};
 
 /// Wrapper type for fixed size byte arrays compatible with sqlx's Postgres
 /// implementation.
@@ -65,12 +68,14 @@ impl<const N: usize> Encode<'_, Postgres> for ByteArray<N> {
     }
 }
 
-#[derive(Clone, Debug, sqlx::FromRow)]
+#[derive(Clone, Debug, PartialEq, sqlx::FromRow)]
 pub struct OrderExecution {
     pub order_uid: OrderUid,
     pub auction_id: i64,
     pub executed_fee: BigDecimal,
-    pub executed_fee_token: Address,
+    /// NULL in early rows, from before the column was added. By convention
+    /// those fees are in the sell token of the order.
+    pub executed_fee_token: Option<Address>,
 }
 
 pub async fn fetch(
@@ -80,22 +85,686 @@ pub async fn fetch(
     const QUERY: &str = r#"
         SELECT order_uid, auction_id, executed_fee, executed_fee_token
         FROM order_execution
-        WHERE auction_id = $1;"#;
+        WHERE auction_id = $1
+        ORDER BY order_uid;"#;
 
     sqlx::query_as(QUERY).bind(auction_id).fetch_all(ex).await
 }
 
+/// Keeps the first backup of an order execution, so a rollback restores the
+/// fee from before any conversion, unless that backup was restored or skipped
+/// already. The fee written is always the latest, which a rollback expects to
+/// find in the order execution.
+const BACKUP_CONFLICT: &str = r#"
+    ON CONFLICT (order_uid, auction_id) DO UPDATE SET
+        run_id = CASE WHEN fee_conversion_backup.restored_at IS NULL
+                AND fee_conversion_backup.skipped_at IS NULL
+            THEN fee_conversion_backup.run_id ELSE EXCLUDED.run_id END,
+        executed_fee = CASE WHEN fee_conversion_backup.restored_at IS NULL
+                AND fee_conversion_backup.skipped_at IS NULL
+            THEN fee_conversion_backup.executed_fee ELSE EXCLUDED.executed_fee END,
+        executed_fee_token = CASE WHEN fee_conversion_backup.restored_at IS NULL
+                AND fee_conversion_backup.skipped_at IS NULL
+            THEN fee_conversion_backup.executed_fee_token ELSE EXCLUDED.executed_fee_token END,
+        backed_up_at = CASE WHEN fee_conversion_backup.restored_at IS NULL
+                AND fee_conversion_backup.skipped_at IS NULL
+            THEN fee_conversion_backup.backed_up_at ELSE now() END,
+        written_fee = EXCLUDED.written_fee,
+        written_fee_token = EXCLUDED.written_fee_token,
+        restored_at = NULL,
+        skipped_at = NULL"#;
+
+/// The values an order execution had before a write, joined as `prev`, and
+/// after it, as `oe`, for the [`audited`] CTE.
+const AUDITED_VALUES: &str = "to_jsonb(prev) AS old_values, to_jsonb(oe) AS new_values";
+
+/// Records the order executions `written` returns the [`AUDITED_VALUES`] of,
+/// see [`crate::database_audit::audited`].
+fn audited(written: &str) -> String {
+    crate::database_audit::audited("order_execution", &["order_uid", "auction_id"], written)
+}
+
+/// Fee changes as one array per column, bound as `$1` to `$6` of queries
+/// reading them with [`UNNEST_CHANGES`].
+struct ChangeColumns {
+    order_uids: Vec<OrderUid>,
+    auction_ids: Vec<i64>,
+    old_fees: Vec<BigDecimal>,
+    old_tokens: Vec<Option<Address>>,
+    new_fees: Vec<BigDecimal>,
+    new_tokens: Vec<Option<Address>>,
+}
+
+const UNNEST_CHANGES: &str = r#"
+    UNNEST($1::bytea[], $2::bigint[], $3::numeric[], $4::bytea[], $5::numeric[], $6::bytea[])
+        AS c(order_uid, auction_id, old_fee, old_token, new_fee, new_token)"#;
+
+impl ChangeColumns {
+    fn new(changes: &[(OrderExecution, OrderExecution)]) -> Self {
+        let mut columns = Self {
+            order_uids: Vec::with_capacity(changes.len()),
+            auction_ids: Vec::with_capacity(changes.len()),
+            old_fees: Vec::with_capacity(changes.len()),
+            old_tokens: Vec::with_capacity(changes.len()),
+            new_fees: Vec::with_capacity(changes.len()),
+            new_tokens: Vec::with_capacity(changes.len()),
+        };
+        for (old, new) in changes {
+            columns.order_uids.push(old.order_uid);
+            columns.auction_ids.push(old.auction_id);
+            columns.old_fees.push(old.executed_fee.clone());
+            columns.old_tokens.push(old.executed_fee_token);
+            columns.new_fees.push(new.executed_fee.clone());
+            columns.new_tokens.push(new.executed_fee_token);
+        }
+        columns
+    }
+}
+
+/// Replaces the fee and fee token of each order execution of `changes` with
+/// the ones of the second element, if they are still the ones of the first,
+/// in one statement, and returns the order executions that were changed.
+/// Backs up the replaced fees in `fee_conversion_backup`, for run `run_id` if
+/// the changes come from one.
 pub async fn update(
     ex: &mut PgConnection,
-    order_execution: OrderExecution,
+    run_id: Option<i64>,
+    changes: &[(OrderExecution, OrderExecution)],
+) -> Result<Vec<(OrderUid, i64)>, sqlx::Error> {
+    let query = format!(
+        r#"
+        WITH changes AS (
+            SELECT * FROM {UNNEST_CHANGES}
+        ),
+        backup AS (
+            INSERT INTO fee_conversion_backup (
+                order_uid, auction_id, run_id, executed_fee, executed_fee_token,
+                written_fee, written_fee_token
+            )
+            SELECT oe.order_uid, oe.auction_id, $7::bigint, oe.executed_fee, oe.executed_fee_token,
+                c.new_fee, c.new_token
+            FROM order_execution oe
+            JOIN changes c ON oe.order_uid = c.order_uid AND oe.auction_id = c.auction_id
+            WHERE oe.executed_fee = c.old_fee
+                AND oe.executed_fee_token IS NOT DISTINCT FROM c.old_token
+            {BACKUP_CONFLICT}
+        ),
+        written AS (
+            UPDATE order_execution oe
+            SET executed_fee = c.new_fee, executed_fee_token = c.new_token
+            FROM changes c
+            JOIN order_execution prev
+                ON prev.order_uid = c.order_uid AND prev.auction_id = c.auction_id
+            WHERE oe.order_uid = c.order_uid
+                AND oe.auction_id = c.auction_id
+                AND oe.executed_fee = c.old_fee
+                AND oe.executed_fee_token IS NOT DISTINCT FROM c.old_token
+            RETURNING oe.order_uid, oe.auction_id, {AUDITED_VALUES}
+        ),
+        {audited}
+        SELECT order_uid, auction_id
+        FROM written;"#,
+        audited = audited("written"),
+    );
+
+    let columns = ChangeColumns::new(changes);
+    sqlx::query_as(&query)
+        .bind(columns.order_uids)
+        .bind(columns.auction_ids)
+        .bind(columns.old_fees)
+        .bind(columns.old_tokens)
+        .bind(columns.new_fees)
+        .bind(columns.new_tokens)
+        .bind(run_id)
+        .fetch_all(ex)
+        .await
+}
+
+/// A backup in `fee_conversion_backup` that [`restore_backup`] went through.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct RestoredBackup {
+    pub order_uid: OrderUid,
+    pub auction_id: i64,
+    /// Whether the backed up fee was written back. Backups of order
+    /// executions that changed since the fee was written, or of which the
+    /// written fee isn't known, are skipped.
+    pub restored: bool,
+}
+
+/// Writes the fee and fee token backed up in `fee_conversion_backup` back to
+/// up to `limit` order executions, where they still have the fee and fee
+/// token the backed up write left, and returns the backups gone through.
+pub async fn restore_backup(
+    ex: &mut PgConnection,
+    limit: i64,
+) -> Result<Vec<RestoredBackup>, sqlx::Error> {
+    let query = format!(
+        r#"
+        WITH batch AS (
+            SELECT order_uid, auction_id, executed_fee, executed_fee_token,
+                written_fee, written_fee_token
+            FROM fee_conversion_backup
+            WHERE restored_at IS NULL AND skipped_at IS NULL
+            ORDER BY auction_id, order_uid
+            LIMIT $1
+            FOR UPDATE
+        ),
+        restored AS (
+            UPDATE order_execution oe
+            SET executed_fee = b.executed_fee, executed_fee_token = b.executed_fee_token
+            FROM batch b
+            JOIN order_execution prev
+                ON prev.order_uid = b.order_uid AND prev.auction_id = b.auction_id
+            WHERE oe.order_uid = b.order_uid
+                AND oe.auction_id = b.auction_id
+                AND oe.executed_fee = b.written_fee
+                AND oe.executed_fee_token IS NOT DISTINCT FROM b.written_fee_token
+            RETURNING oe.order_uid, oe.auction_id, {AUDITED_VALUES}
+        ),
+        {audited}
+        UPDATE fee_conversion_backup f
+        SET restored_at = CASE WHEN r.order_uid IS NOT NULL THEN now() END,
+            skipped_at = CASE WHEN r.order_uid IS NULL THEN now() END
+        FROM batch b
+        LEFT JOIN restored r ON r.order_uid = b.order_uid AND r.auction_id = b.auction_id
+        WHERE f.order_uid = b.order_uid AND f.auction_id = b.auction_id
+        RETURNING f.order_uid, f.auction_id, f.restored_at IS NOT NULL AS restored;"#,
+        audited = audited("restored"),
+    );
+
+    sqlx::query_as(&query).bind(limit).fetch_all(ex).await
+}
+
+/// Number of backups not restored or skipped yet.
+pub async fn count_backups(ex: &mut PgConnection) -> Result<i64, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT COUNT(*)
+        FROM fee_conversion_backup
+        WHERE restored_at IS NULL AND skipped_at IS NULL;"#;
+
+    sqlx::query_scalar(QUERY).fetch_one(ex).await
+}
+
+/// The order executions of the auctions `$1..=$2` with the kind and surplus
+/// token of their order, taken from `orders` if it is in both tables.
+const WITH_SURPLUS_TOKENS: &str = r#"
+    WITH executions AS (
+        SELECT oe.order_uid, oe.auction_id, oe.executed_fee_token, o.kind::text AS kind,
+            CASE o.kind WHEN 'sell' THEN o.buy_token ELSE o.sell_token END AS surplus_token
+        FROM order_execution oe
+        CROSS JOIN LATERAL (
+            SELECT sell_token, buy_token, kind, 0 AS source FROM orders WHERE uid = oe.order_uid
+            UNION ALL
+            SELECT sell_token, buy_token, kind, 1 FROM jit_orders WHERE uid = oe.order_uid
+            ORDER BY source
+            LIMIT 1
+        ) o
+        WHERE oe.auction_id BETWEEN $1 AND $2
+    )"#;
+
+/// Order executions of an order kind, and how many of them have a fee token
+/// other than the surplus token of their order.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct FeeTokenCount {
+    pub kind: String,
+    pub checked: i64,
+    pub wrong: i64,
+}
+
+/// Counts the order executions of the auctions `first..=last` by order kind.
+/// Order executions of unknown orders are left out.
+pub async fn count_fee_tokens(
+    ex: &mut PgConnection,
+    first: i64,
+    last: i64,
+) -> Result<Vec<FeeTokenCount>, sqlx::Error> {
+    let query = format!(
+        r#"{WITH_SURPLUS_TOKENS}
+        SELECT kind, COUNT(*) AS checked,
+            COUNT(*) FILTER (WHERE executed_fee_token IS DISTINCT FROM surplus_token) AS wrong
+        FROM executions
+        GROUP BY kind;"#
+    );
+    sqlx::query_as(&query)
+        .bind(first)
+        .bind(last)
+        .fetch_all(ex)
+        .await
+}
+
+/// An order execution whose fee is not in the surplus token of its order.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct WrongFeeToken {
+    pub order_uid: OrderUid,
+    pub auction_id: i64,
+    pub kind: String,
+    pub executed_fee_token: Option<Address>,
+    pub surplus_token: Address,
+}
+
+/// The order executions of the auctions `first..=last` whose fee is not in
+/// the surplus token of their order.
+pub async fn fetch_wrong_fee_tokens(
+    ex: &mut PgConnection,
+    first: i64,
+    last: i64,
+) -> Result<Vec<WrongFeeToken>, sqlx::Error> {
+    let query = format!(
+        r#"{WITH_SURPLUS_TOKENS}
+        SELECT order_uid, auction_id, kind, executed_fee_token, surplus_token
+        FROM executions
+        WHERE executed_fee_token IS DISTINCT FROM surplus_token
+        ORDER BY auction_id, order_uid;"#
+    );
+    sqlx::query_as(&query)
+        .bind(first)
+        .bind(last)
+        .fetch_all(ex)
+        .await
+}
+
+/// An order execution whose fee was changed by applying a proposal, with the
+/// fee it had before.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct AppliedFeeChange {
+    pub order_uid: OrderUid,
+    pub auction_id: i64,
+    pub old_fee: BigDecimal,
+    pub old_token: Option<Address>,
+    pub executed_fee: BigDecimal,
+    pub executed_fee_token: Option<Address>,
+}
+
+/// Up to `limit` order executions of the auctions `first..=last` whose fee
+/// was written by `apply_proposals`.
+pub async fn fetch_applied_fee_changes(
+    ex: &mut PgConnection,
+    first: i64,
+    last: i64,
+    limit: i64,
+) -> Result<Vec<AppliedFeeChange>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT p.order_uid, p.auction_id, p.old_fee, p.old_token, oe.executed_fee,
+            oe.executed_fee_token
+        FROM order_execution_fee_proposals p
+        JOIN order_execution oe ON oe.order_uid = p.order_uid AND oe.auction_id = p.auction_id
+        WHERE p.status = 'applied' AND p.auction_id BETWEEN $1 AND $2
+        ORDER BY p.auction_id DESC, p.order_uid
+        LIMIT $3;"#;
+
+    sqlx::query_as(QUERY)
+        .bind(first)
+        .bind(last)
+        .bind(limit)
+        .fetch_all(ex)
+        .await
+}
+
+/// Proposes to replace the fee of each order execution of `changes` with
+/// the one of the second element, replacing earlier proposals for them.
+pub async fn propose(
+    ex: &mut PgConnection,
+    run_id: i64,
+    changes: &[(OrderExecution, OrderExecution)],
 ) -> Result<(), sqlx::Error> {
-    // update existing row in order execution (primary key being order_uid + auction_id) with new values of executed fee and executed fee token
+    let query = format!(
+        r#"
+        INSERT INTO order_execution_fee_proposals
+            (order_uid, auction_id, run_id, old_fee, old_token, new_fee, new_token)
+        SELECT c.order_uid, c.auction_id, $7, c.old_fee, c.old_token, c.new_fee, c.new_token
+        FROM {UNNEST_CHANGES}
+        ON CONFLICT (order_uid, auction_id) DO UPDATE SET
+            run_id = EXCLUDED.run_id,
+            old_fee = EXCLUDED.old_fee,
+            old_token = EXCLUDED.old_token,
+            new_fee = EXCLUDED.new_fee,
+            new_token = EXCLUDED.new_token,
+            status = 'proposed',
+            proposed_at = now(),
+            resolved_at = NULL;"#
+    );
+
+    let columns = ChangeColumns::new(changes);
+    sqlx::query(&query)
+        .bind(columns.order_uids)
+        .bind(columns.auction_ids)
+        .bind(columns.old_fees)
+        .bind(columns.old_tokens)
+        .bind(columns.new_fees)
+        .bind(columns.new_tokens)
+        .bind(run_id)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Number of proposals not applied yet.
+pub async fn count_proposals(ex: &mut PgConnection) -> Result<i64, sqlx::Error> {
     const QUERY: &str = r#"
-        UPDATE order_execution
-        SET executed_fee = $1, executed_fee_token = $2
-        WHERE order_uid = $3 AND auction_id = $4;"#;
+        SELECT COUNT(*)
+        FROM order_execution_fee_proposals
+        WHERE status = 'proposed';"#;
 
-    sqlx::query(QUERY)
+    sqlx::query_scalar(QUERY).fetch_one(ex).await
+}
+
+/// Writes the fees of up to `limit` proposals to their order executions, and
+/// returns how many were applied and how many were stale. Proposals are
+/// stale if the fee of their order execution changed since, or the order
+/// execution is gone; those are left as they are. Backs up the fees it
+/// replaces in `fee_conversion_backup`.
+pub async fn apply_proposals(ex: &mut PgConnection, limit: i64) -> Result<(i64, i64), sqlx::Error> {
+    let query = format!(
+        r#"
+        WITH batch AS (
+            SELECT order_uid, auction_id
+            FROM order_execution_fee_proposals
+            WHERE status = 'proposed'
+            ORDER BY auction_id, order_uid
+            LIMIT $1
+            FOR UPDATE
+        ),
+        backup AS (
+            INSERT INTO fee_conversion_backup (
+                order_uid, auction_id, run_id, executed_fee, executed_fee_token,
+                written_fee, written_fee_token
+            )
+            SELECT oe.order_uid, oe.auction_id, p.run_id, oe.executed_fee, oe.executed_fee_token,
+                p.new_fee, p.new_token
+            FROM order_execution oe
+            JOIN order_execution_fee_proposals p
+                ON p.order_uid = oe.order_uid AND p.auction_id = oe.auction_id
+            JOIN batch b ON b.order_uid = p.order_uid AND b.auction_id = p.auction_id
+            WHERE oe.executed_fee = p.old_fee
+                AND oe.executed_fee_token IS NOT DISTINCT FROM p.old_token
+            {BACKUP_CONFLICT}
+        ),
+        applied AS (
+            UPDATE order_execution oe
+            SET executed_fee = p.new_fee, executed_fee_token = p.new_token
+            FROM order_execution_fee_proposals p
+            JOIN batch b ON b.order_uid = p.order_uid AND b.auction_id = p.auction_id
+            JOIN order_execution prev
+                ON prev.order_uid = p.order_uid AND prev.auction_id = p.auction_id
+            WHERE oe.order_uid = p.order_uid
+                AND oe.auction_id = p.auction_id
+                AND oe.executed_fee = p.old_fee
+                AND oe.executed_fee_token IS NOT DISTINCT FROM p.old_token
+            RETURNING oe.order_uid, oe.auction_id, {AUDITED_VALUES}
+        ),
+        {audited},
+        resolved AS (
+            UPDATE order_execution_fee_proposals p
+            SET status = CASE
+                    WHEN EXISTS (
+                        SELECT 1 FROM applied a
+                        WHERE a.order_uid = p.order_uid AND a.auction_id = p.auction_id
+                    )
+                    THEN 'applied'
+                    ELSE 'stale'
+                END,
+                resolved_at = now()
+            FROM batch b
+            WHERE b.order_uid = p.order_uid AND b.auction_id = p.auction_id
+            RETURNING p.status
+        )
+        SELECT
+            COUNT(*) FILTER (WHERE status = 'applied'),
+            COUNT(*) FILTER (WHERE status = 'stale')
+        FROM resolved;"#,
+        audited = audited("applied"),
+    );
+
+    sqlx::query_as(&query).bind(limit).fetch_one(ex).await
+}
+
+/// Returns the `limit` largest ranges of consecutive auctions (among those with
+/// order executions) that still have executions with the fee in the token of
+/// their order that isn't its surplus token, or without fee token, i.e. that
+/// the fee conversion has not processed yet.
+pub async fn fetch_unconverted_ranges(
+    ex: &mut PgConnection,
+    limit: i64,
+) -> Result<Vec<IdRange>, sqlx::Error> {
+    const QUERY: &str = r#"
+        WITH source AS (
+            SELECT
+                oe.auction_id AS id,
+                BOOL_OR(
+                    oe.executed_fee_token IS NULL
+                    OR (
+                        COALESCE(o.kind, j.kind) = 'sell'
+                        AND oe.executed_fee_token = COALESCE(o.sell_token, j.sell_token)
+                    )
+                    OR (
+                        COALESCE(o.kind, j.kind) = 'buy'
+                        AND oe.executed_fee_token = COALESCE(o.buy_token, j.buy_token)
+                    )
+                ) AS missing,
+                ROW_NUMBER() OVER (ORDER BY oe.auction_id) AS rn
+            FROM order_execution oe
+            LEFT JOIN orders o ON o.uid = oe.order_uid
+            LEFT JOIN jit_orders j ON j.uid = oe.order_uid
+            GROUP BY oe.auction_id
+        ),
+        islands AS (
+            SELECT id, rn - ROW_NUMBER() OVER (ORDER BY id) AS island
+            FROM source
+            WHERE missing
+        )
+        SELECT MIN(id) AS start, MAX(id) AS end, COUNT(*) AS count
+        FROM islands
+        GROUP BY island
+        ORDER BY count DESC, start DESC
+        LIMIT $1;"#;
+
+    sqlx::query_as(QUERY).bind(limit).fetch_all(ex).await
+}
+
+/// Order executions of a bucket of auction ids that the fee conversion has
+/// not processed yet, by why they need it.
+#[derive(Clone, Debug, Eq, PartialEq, sqlx::FromRow)]
+pub struct UnconvertedBucket {
+    pub start: i64,
+    pub end: i64,
+    /// Of sell orders, with the fee in the sell token.
+    pub sell: i64,
+    /// Of buy orders, with the fee in the buy token.
+    pub buy: i64,
+    pub without_token: i64,
+}
+
+impl UnconvertedBucket {
+    pub fn total(&self) -> i64 {
+        self.sell + self.buy + self.without_token
+    }
+}
+
+/// Counts the order executions the fee conversion has not processed yet, like
+/// [`fetch_unconverted_ranges`] finds them, per bucket of `bucket_size`
+/// auction ids, and returns up to `limit` buckets with the most first.
+pub async fn count_unconverted_buckets(
+    ex: &mut PgConnection,
+    bucket_size: i64,
+    limit: i64,
+) -> Result<Vec<UnconvertedBucket>, sqlx::Error> {
+    const QUERY: &str = r#"
+        WITH unconverted AS (
+            SELECT
+                FLOOR(oe.auction_id::numeric / $1)::bigint * $1 AS start,
+                oe.executed_fee_token IS NULL AS without_token,
+                COALESCE(o.kind, j.kind) AS kind
+            FROM order_execution oe
+            LEFT JOIN orders o ON o.uid = oe.order_uid
+            LEFT JOIN jit_orders j ON j.uid = oe.order_uid
+            WHERE oe.executed_fee_token IS NULL
+                OR (
+                    COALESCE(o.kind, j.kind) = 'sell'
+                    AND oe.executed_fee_token = COALESCE(o.sell_token, j.sell_token)
+                )
+                OR (
+                    COALESCE(o.kind, j.kind) = 'buy'
+                    AND oe.executed_fee_token = COALESCE(o.buy_token, j.buy_token)
+                )
+        )
+        SELECT
+            start,
+            start + $1 - 1 AS end,
+            COUNT(*) FILTER (WHERE NOT without_token AND kind = 'sell') AS sell,
+            COUNT(*) FILTER (WHERE NOT without_token AND kind = 'buy') AS buy,
+            COUNT(*) FILTER (WHERE without_token) AS without_token
+        FROM unconverted
+        GROUP BY start
+        ORDER BY COUNT(*) DESC, start DESC
+        LIMIT $2;"#;
+
+    sqlx::query_as(QUERY)
+        .bind(bucket_size)
+        .bind(limit)
+        .fetch_all(ex)
+        .await
+}
+
+/// Returns up to `limit` ids of auctions below `auction_id`, highest first,
+/// that have order executions without fee token.
+pub async fn fetch_auctions_without_fee_token(
+    ex: &mut PgConnection,
+    auction_id: i64,
+    limit: i64,
+) -> Result<Vec<i64>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT DISTINCT auction_id
+        FROM order_execution
+        WHERE executed_fee_token IS NULL AND auction_id < $1
+        ORDER BY auction_id DESC
+        LIMIT $2;"#;
+
+    sqlx::query_scalar(QUERY)
+        .bind(auction_id)
+        .bind(limit)
+        .fetch_all(ex)
+        .await
+}
+
+/// Sets the missing fee tokens of the order executions of `auction_id` to the
+/// sell token of their order. Returns the number of updated executions and
+/// the uids of the executions whose order is in neither `orders` nor
+/// `jit_orders` and so still have no fee token.
+pub async fn backfill_fee_token(
+    ex: &mut PgConnection,
+    auction_id: i64,
+) -> Result<(u64, Vec<OrderUid>), sqlx::Error> {
+    let update = format!(
+        r#"
+        WITH written AS (
+            UPDATE order_execution oe
+            SET executed_fee_token = COALESCE(
+                (SELECT sell_token FROM orders WHERE uid = oe.order_uid),
+                (SELECT sell_token FROM jit_orders WHERE uid = oe.order_uid)
+            )
+            FROM order_execution prev
+            WHERE prev.order_uid = oe.order_uid
+                AND prev.auction_id = oe.auction_id
+                AND oe.auction_id = $1
+                AND oe.executed_fee_token IS NULL
+            RETURNING {AUDITED_VALUES}
+        ),
+        {audited}
+        SELECT COUNT(*)
+        FROM written;"#,
+        audited = audited("written"),
+    );
+    const REMAINING: &str = r#"
+        SELECT order_uid
+        FROM order_execution
+        WHERE auction_id = $1 AND executed_fee_token IS NULL
+        ORDER BY order_uid;"#;
+
+    let updated: i64 = sqlx::query_scalar(&update)
+        .bind(auction_id)
+        .fetch_one(&mut *ex)
+        .await?;
+    let remaining = sqlx::query_scalar(REMAINING)
+        .bind(auction_id)
+        .fetch_all(ex)
+        .await?;
+    Ok((updated as u64, remaining))
+}
+
+/// An order execution from the window in which solver determined fees were
+/// stored in `surplus_fee`, separately from the signed fee in `executed_fee`.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct LegacySurplusFee {
+    pub order_uid: OrderUid,
+    pub auction_id: i64,
+    pub executed_fee: Option<BigDecimal>,
+    pub executed_fee_token: Option<Address>,
+    pub surplus_fee: BigDecimal,
+}
+
+/// Whether `order_execution` still has the legacy `surplus_fee` column.
+pub async fn has_surplus_fee_column(ex: &mut PgConnection) -> Result<bool, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT EXISTS (
+            SELECT 1
+            FROM information_schema.columns
+            WHERE table_name = 'order_execution' AND column_name = 'surplus_fee'
+        );"#;
+
+    sqlx::query_scalar(QUERY).fetch_one(ex).await
+}
+
+/// Returns the order executions with a legacy surplus fee of the `limit`
+/// highest auctions below `auction_id`, highest auction first.
+pub async fn fetch_legacy_surplus_fees(
+    ex: &mut PgConnection,
+    auction_id: i64,
+    limit: i64,
+) -> Result<Vec<LegacySurplusFee>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT order_uid, auction_id, executed_fee, executed_fee_token, surplus_fee
+        FROM order_execution
+        WHERE surplus_fee IS NOT NULL AND auction_id IN (
+            SELECT DISTINCT auction_id
+            FROM order_execution
+            WHERE surplus_fee IS NOT NULL AND auction_id < $1
+            ORDER BY auction_id DESC
+            LIMIT $2
+        )
+        ORDER BY auction_id DESC, order_uid;"#;
+
+    sqlx::query_as(QUERY)
+        .bind(auction_id)
+        .bind(limit)
+        .fetch_all(ex)
+        .await
+}
+
+/// Replaces the fee of an order execution with the consolidated one and
+/// clears its legacy surplus fee, so it can't be added twice.
+pub async fn consolidate_surplus_fee(
+    ex: &mut PgConnection,
+    order_execution: OrderExecution,
+) -> Result<(), sqlx::Error> {
+    let query = format!(
+        r#"
+        WITH written AS (
+            UPDATE order_execution oe
+            SET executed_fee = $1, executed_fee_token = $2, surplus_fee = NULL
+            FROM order_execution prev
+            WHERE prev.order_uid = oe.order_uid
+                AND prev.auction_id = oe.auction_id
+                AND oe.order_uid = $3
+                AND oe.auction_id = $4
+            RETURNING {AUDITED_VALUES}
+        ),
+        {audited}
+        SELECT COUNT(*)
+        FROM written;"#,
+        audited = audited("written"),
+    );
+
+    sqlx::query(&query)
         .bind(order_execution.executed_fee)
         .bind(order_execution.executed_fee_token)
         .bind(order_execution.order_uid)
@@ -105,3 +774,98 @@ pub async fn update(
 
     Ok(())
 }
+
+/// An order execution of a settled auction without matching trade, or a trade
+/// of a settled auction without matching order execution.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct TradeMismatch {
+    pub auction_id: i64,
+    pub order_uid: OrderUid,
+    /// `"trade"` or `"order_execution"`, whichever is missing.
+    pub missing: String,
+}
+
+/// The `mismatches` between `order_execution` and `trades` in the settled
+/// auctions `$1..=$2`, as [`TradeMismatch`] rows.
+///
+/// A trade belongs to the auction of the settlement event following it in the
+/// same block, like the indexer assigns them.
+const TRADE_MISMATCHES: &str = r#"
+    WITH settled_trades AS (
+        SELECT t.order_uid, s.auction_id
+        FROM trades t
+        JOIN LATERAL (
+            SELECT auction_id
+            FROM settlements s
+            WHERE s.block_number = t.block_number AND s.log_index > t.log_index
+            ORDER BY s.log_index ASC
+            LIMIT 1
+        ) s ON s.auction_id BETWEEN $1 AND $2
+    ),
+    settled_executions AS (
+        SELECT order_uid, auction_id
+        FROM order_execution
+        WHERE auction_id BETWEEN $1 AND $2
+            AND auction_id IN (SELECT auction_id FROM settlements)
+    ),
+    mismatches AS (
+        SELECT auction_id, order_uid, 'trade' AS missing
+        FROM settled_executions
+        EXCEPT
+        SELECT auction_id, order_uid, 'trade'
+        FROM settled_trades
+        UNION ALL
+        (
+            SELECT auction_id, order_uid, 'order_execution'
+            FROM settled_trades
+            EXCEPT
+            SELECT auction_id, order_uid, 'order_execution'
+            FROM settled_executions
+        )
+    )"#;
+
+/// Returns up to `limit` mismatches between `order_execution` and `trades` in
+/// the settled auctions `first..=last`, highest auction first.
+pub async fn fetch_trade_mismatches(
+    ex: &mut PgConnection,
+    first: i64,
+    last: i64,
+    limit: i64,
+) -> Result<Vec<TradeMismatch>, sqlx::Error> {
+    let query = format!(
+        r#"{TRADE_MISMATCHES}
+        SELECT auction_id, order_uid, missing
+        FROM mismatches
+        ORDER BY auction_id DESC, missing, order_uid
+        LIMIT $3;"#
+    );
+
+    sqlx::query_as(&query)
+        .bind(first)
+        .bind(last)
+        .bind(limit)
+        .fetch_all(ex)
+        .await
+}
+
+/// Counts the mismatches between `order_execution` and `trades` in the
+/// settled auctions `first..=last` by what is missing, `"trade"` or
+/// `"order_execution"`.
+pub async fn count_trade_mismatches(
+    ex: &mut PgConnection,
+    first: i64,
+    last: i64,
+) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    let query = format!(
+        r#"{TRADE_MISMATCHES}
+        SELECT missing, COUNT(*)
+        FROM mismatches
+        GROUP BY missing;"#
+    );
+
+    sqlx::query_as(&query)
+        .bind(first)
+        .bind(last)
+        .fetch_all(ex)
+        .await
+}
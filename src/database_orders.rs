@@ -1,10 +1,15 @@
+use primitive_types::H256;
 use sqlx::{
     encode::IsNull,
     error::BoxDynError,
     postgres::{PgArgumentBuffer, PgHasArrayType, PgTypeInfo, PgValueFormat, PgValueRef},
     Decode, Encode, PgConnection, Postgres, Type,
 };
-use std::fmt::{self, Debug, Formatter};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    str::FromStr,
+};
 
 /// Wrapper type for fixed size byte arrays compatible with sqlx's Postgres
 /// implementation.
@@ -62,8 +67,44 @@ impl<const N: usize> Encode<'_, Postgres> for ByteArray<N> {
     }
 }
 
+/// Parses the `0x` prefixed hex strings the [`Debug`] implementation prints.
+impl<const N: usize> FromStr for ByteArray<N> {
+    type Err = hex::FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bytes = [0u8; N];
+        hex::decode_to_slice(s.strip_prefix("0x").unwrap_or(s), &mut bytes)?;
+        Ok(Self(bytes))
+    }
+}
+
 pub type Address = ByteArray<20>;
 pub type OrderUid = ByteArray<56>;
+pub type TransactionHash = ByteArray<32>;
+
+impl From<H256> for TransactionHash {
+    fn from(hash: H256) -> Self {
+        Self(hash.0)
+    }
+}
+
+impl From<TransactionHash> for H256 {
+    fn from(hash: TransactionHash) -> Self {
+        Self(hash.0)
+    }
+}
+
+impl OrderUid {
+    /// The owner embedded in the uid, after the 32 byte order digest.
+    pub fn owner(&self) -> Address {
+        ByteArray(self.0[32..52].try_into().unwrap())
+    }
+
+    /// The validTo timestamp embedded in the uid, its last 4 bytes.
+    pub fn valid_to(&self) -> u32 {
+        u32::from_be_bytes(self.0[52..56].try_into().unwrap())
+    }
+}
 
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, sqlx::Type)]
 #[sqlx(type_name = "OrderKind")]
@@ -81,6 +122,43 @@ pub struct Order {
     pub kind: OrderKind,
 }
 
+impl Order {
+    /// The token the surplus of the order, and so its fee, is expressed in:
+    /// the buy token of sell orders and the sell token of buy orders.
+    pub fn surplus_token(&self) -> Address {
+        match self.kind {
+            OrderKind::Sell => self.buy_token,
+            OrderKind::Buy => self.sell_token,
+        }
+    }
+
+    /// The token a fee in `fee_token` has to be converted to, its surplus
+    /// token, if it is in the other token of the order. `None` for fees that
+    /// are in the surplus token already, or in neither token of the order.
+    pub fn fee_conversion(&self, fee_token: Address) -> Option<Address> {
+        let surplus_token = self.surplus_token();
+        (fee_token != surplus_token
+            && (fee_token == self.sell_token || fee_token == self.buy_token))
+            .then_some(surplus_token)
+    }
+}
+
+/// The table an order was found in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OrderSource {
+    Orders,
+    JitOrders,
+}
+
+impl OrderSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Orders => "orders",
+            Self::JitOrders => "jit_orders",
+        }
+    }
+}
+
 pub async fn fetch_from_orders(
     ex: &mut PgConnection,
     order_uid: &OrderUid,
@@ -110,3 +188,130 @@ pub async fn fetch_from_jit_orders(
         .fetch_optional(ex)
         .await
 }
+
+/// Fetches the order from `orders`, falling back to `jit_orders`.
+pub async fn fetch(
+    ex: &mut PgConnection,
+    order_uid: &OrderUid,
+) -> Result<Option<(Order, OrderSource)>, sqlx::Error> {
+    if let Some(order) = fetch_from_orders(ex, order_uid).await? {
+        return Ok(Some((order, OrderSource::Orders)));
+    }
+    Ok(fetch_from_jit_orders(ex, order_uid)
+        .await?
+        .map(|order| (order, OrderSource::JitOrders)))
+}
+
+/// An order with the uid it was fetched by.
+#[derive(sqlx::FromRow)]
+struct UidOrder {
+    uid: OrderUid,
+    #[sqlx(flatten)]
+    order: Order,
+}
+
+/// Fetches those of `order_uids` that are in `orders`, in one query.
+pub async fn fetch_many_from_orders(
+    ex: &mut PgConnection,
+    order_uids: &[OrderUid],
+) -> Result<HashMap<OrderUid, Order>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT uid, sell_token, buy_token, kind
+        FROM orders
+        WHERE uid = ANY($1);"#;
+
+    let orders: Vec<UidOrder> = sqlx::query_as(QUERY).bind(order_uids).fetch_all(ex).await?;
+    Ok(orders.into_iter().map(|row| (row.uid, row.order)).collect())
+}
+
+/// Fetches those of `order_uids` that are in `jit_orders`, in one query.
+pub async fn fetch_many_from_jit_orders(
+    ex: &mut PgConnection,
+    order_uids: &[OrderUid],
+) -> Result<HashMap<OrderUid, Order>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT uid, sell_token, buy_token, kind
+        FROM jit_orders
+        WHERE uid = ANY($1);"#;
+
+    let orders: Vec<UidOrder> = sqlx::query_as(QUERY).bind(order_uids).fetch_all(ex).await?;
+    Ok(orders.into_iter().map(|row| (row.uid, row.order)).collect())
+}
+
+/// Like [`fetch`] for all of `order_uids` at once, in at most two queries.
+/// Orders in neither table are left out.
+pub async fn fetch_many(
+    ex: &mut PgConnection,
+    order_uids: &[OrderUid],
+) -> Result<HashMap<OrderUid, (Order, OrderSource)>, sqlx::Error> {
+    let mut orders: HashMap<_, _> = fetch_many_from_orders(ex, order_uids)
+        .await?
+        .into_iter()
+        .map(|(uid, order)| (uid, (order, OrderSource::Orders)))
+        .collect();
+    let missing: Vec<_> = order_uids
+        .iter()
+        .filter(|uid| !orders.contains_key(*uid))
+        .copied()
+        .collect();
+    if missing.is_empty() {
+        return Ok(orders);
+    }
+    orders.extend(
+        fetch_many_from_jit_orders(ex, &missing)
+            .await?
+            .into_iter()
+            .map(|(uid, order)| (uid, (order, OrderSource::JitOrders))),
+    );
+    Ok(orders)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_transaction_hash() {
+        let hash = H256::repeat_byte(0xab);
+        let parsed: TransactionHash = format!("{hash:?}").parse().unwrap();
+        assert_eq!(parsed, TransactionHash::from(hash));
+        assert_eq!(H256::from(parsed), hash);
+        assert_eq!(format!("{parsed:?}").parse::<TransactionHash>(), Ok(parsed));
+        // too short for a hash
+        assert!("0xabab".parse::<TransactionHash>().is_err());
+    }
+
+    #[test]
+    fn surplus_token() {
+        let order = |kind| Order {
+            sell_token: ByteArray([1; 20]),
+            buy_token: ByteArray([2; 20]),
+            kind,
+        };
+        assert_eq!(order(OrderKind::Sell).surplus_token(), ByteArray([2; 20]));
+        assert_eq!(order(OrderKind::Buy).surplus_token(), ByteArray([1; 20]));
+    }
+
+    #[test]
+    fn fee_conversion() {
+        let (sell_token, buy_token) = (ByteArray([1; 20]), ByteArray([2; 20]));
+        let order = |kind| Order {
+            sell_token,
+            buy_token,
+            kind,
+        };
+
+        let sell = order(OrderKind::Sell);
+        assert_eq!(sell.fee_conversion(sell_token), Some(buy_token));
+        assert_eq!(sell.fee_conversion(buy_token), None);
+
+        // the surplus of buy orders, and so their fee, is in the sell token
+        let buy = order(OrderKind::Buy);
+        assert_eq!(buy.fee_conversion(buy_token), Some(sell_token));
+        assert_eq!(buy.fee_conversion(sell_token), None);
+
+        // fees in a token the order doesn't trade are left alone
+        assert_eq!(sell.fee_conversion(ByteArray([3; 20])), None);
+        assert_eq!(buy.fee_conversion(ByteArray([3; 20])), None);
+    }
+}
@@ -0,0 +1,69 @@
+//! Checks of the privileges of the connected role, so missing grants are
+//! reported up front instead of failing in the middle of a run.
+
+use sqlx::PgConnection;
+
+/// A privilege the connected role needs.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Grant {
+    /// `privilege` on `table`. Tables that don't exist are not checked, the
+    /// migrations using them do nothing in that case.
+    Table {
+        table: &'static str,
+        privilege: &'static str,
+    },
+    /// `privilege` on the current schema.
+    Schema { privilege: &'static str },
+    /// Ownership of `table`, needed to alter it. Not checked if the table
+    /// doesn't exist.
+    Owner { table: &'static str },
+}
+
+/// Returns the statements granting the privileges from `grants` that the
+/// connected role lacks, without duplicates. They are meant to be run by an
+/// admin, so they name the role explicitly.
+pub async fn missing(ex: &mut PgConnection, grants: &[Grant]) -> Result<Vec<String>, sqlx::Error> {
+    let role: String = sqlx::query_scalar("SELECT current_user;")
+        .fetch_one(&mut *ex)
+        .await?;
+    let mut missing = Vec::new();
+    for grant in grants {
+        let statement = match *grant {
+            Grant::Table { table, privilege } => {
+                const QUERY: &str = r#"
+                    SELECT to_regclass($1) IS NULL OR has_table_privilege($1, $2);"#;
+                let granted: bool = sqlx::query_scalar(QUERY)
+                    .bind(table)
+                    .bind(privilege)
+                    .fetch_one(&mut *ex)
+                    .await?;
+                (!granted).then(|| format!("GRANT {privilege} ON TABLE {table} TO {role};"))
+            }
+            Grant::Schema { privilege } => {
+                const QUERY: &str = r#"
+                    SELECT current_schema(), has_schema_privilege(current_schema(), $1);"#;
+                let (schema, granted): (String, bool) = sqlx::query_as(QUERY)
+                    .bind(privilege)
+                    .fetch_one(&mut *ex)
+                    .await?;
+                (!granted).then(|| format!("GRANT {privilege} ON SCHEMA {schema} TO {role};"))
+            }
+            Grant::Owner { table } => {
+                const QUERY: &str = r#"
+                    SELECT to_regclass($1) IS NULL OR pg_has_role(
+                        (SELECT relowner FROM pg_class WHERE oid = to_regclass($1)),
+                        'USAGE'
+                    );"#;
+                let granted: bool = sqlx::query_scalar(QUERY)
+                    .bind(table)
+                    .fetch_one(&mut *ex)
+                    .await?;
+                (!granted).then(|| format!("ALTER TABLE {table} OWNER TO {role};"))
+            }
+        };
+        if let Some(statement) = statement.filter(|statement| !missing.contains(statement)) {
+            missing.push(statement);
+        }
+    }
+    Ok(missing)
+}
@@ -0,0 +1,68 @@
+//! The normalized tables of proposed solutions written by the services.
+
+use {crate::database_orders::OrderUid, bigdecimal::BigDecimal, sqlx::PgConnection};
+
+/// Executed amounts of an order in one proposed solution.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct TradeExecution {
+    pub auction_id: i64,
+    /// Position of the solution in the `solutions` of the solver competition.
+    pub solution_uid: i64,
+    pub order_uid: OrderUid,
+    pub executed_sell: BigDecimal,
+    pub executed_buy: BigDecimal,
+}
+
+/// Whether the database has the `proposed_trade_executions` table.
+pub async fn has_trade_executions_table(ex: &mut PgConnection) -> Result<bool, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT EXISTS (
+            SELECT 1
+            FROM information_schema.tables
+            WHERE table_name = 'proposed_trade_executions'
+        );"#;
+
+    sqlx::query_scalar(QUERY).fetch_one(ex).await
+}
+
+/// Inserts the trade execution or overwrites the amounts of an existing one.
+pub async fn upsert_trade_execution(
+    ex: &mut PgConnection,
+    trade_execution: TradeExecution,
+) -> Result<(), sqlx::Error> {
+    let query = format!(
+        r#"
+        WITH prev AS (
+            SELECT auction_id, solution_uid, order_uid, to_jsonb(t) AS old_values
+            FROM proposed_trade_executions t
+            WHERE auction_id = $1 AND solution_uid = $2 AND order_uid = $3
+        ),
+        written AS (
+            INSERT INTO proposed_trade_executions AS t
+                (auction_id, solution_uid, order_uid, executed_sell, executed_buy)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (auction_id, solution_uid, order_uid)
+            DO UPDATE SET executed_sell = $4, executed_buy = $5
+            RETURNING t.auction_id, t.solution_uid, t.order_uid, to_jsonb(t) AS new_values
+        ),
+        {}
+        SELECT COUNT(*)
+        FROM written;"#,
+        crate::database_audit::audited(
+            "proposed_trade_executions",
+            &["auction_id", "solution_uid", "order_uid"],
+            "written LEFT JOIN prev USING (auction_id, solution_uid, order_uid)",
+        )
+    );
+
+    sqlx::query(&query)
+        .bind(trade_execution.auction_id)
+        .bind(trade_execution.solution_uid)
+        .bind(trade_execution.order_uid)
+        .bind(trade_execution.executed_sell)
+        .bind(trade_execution.executed_buy)
+        .execute(ex)
+        .await?;
+
+    Ok(())
+}
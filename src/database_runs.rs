@@ -0,0 +1,683 @@
+use {
+    crate::archives::Manifest,
+    sqlx::{
+        types::chrono::{DateTime, Utc},
+        PgConnection,
+    },
+};
+
+/// Creates the tables in which this tool keeps track of its own runs, if they
+/// don't exist yet.
+pub async fn create_tables(ex: &mut PgConnection) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+        CREATE TABLE IF NOT EXISTS migration_runs (
+            id BIGSERIAL PRIMARY KEY,
+            migration TEXT NOT NULL,
+            started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            finished_at TIMESTAMPTZ
+        );
+        ALTER TABLE migration_runs ADD COLUMN IF NOT EXISTS snapshot_max_id BIGINT;
+        ALTER TABLE migration_runs ADD COLUMN IF NOT EXISTS schema_variant TEXT;
+        CREATE TABLE IF NOT EXISTS migration_annotations (
+            id BIGSERIAL PRIMARY KEY,
+            run_id BIGINT REFERENCES migration_runs (id),
+            auction_id BIGINT,
+            note TEXT NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+        CREATE TABLE IF NOT EXISTS migration_ownership (
+            id BIGSERIAL PRIMARY KEY,
+            start_id BIGINT NOT NULL,
+            end_id BIGINT NOT NULL,
+            owner TEXT NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+        CREATE TABLE IF NOT EXISTS migration_frontier (
+            id BIGSERIAL PRIMARY KEY,
+            run_id BIGINT NOT NULL REFERENCES migration_runs (id),
+            migration TEXT NOT NULL,
+            start_id BIGINT NOT NULL,
+            end_id BIGINT NOT NULL,
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+        ALTER TABLE migration_frontier ADD COLUMN IF NOT EXISTS kind TEXT;
+        CREATE TABLE IF NOT EXISTS migration_progress (
+            migration TEXT PRIMARY KEY,
+            run_id BIGINT NOT NULL REFERENCES migration_runs (id),
+            last_auction_id BIGINT NOT NULL,
+            started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+        ALTER TABLE migration_progress
+            ADD COLUMN IF NOT EXISTS start_id BIGINT,
+            ADD COLUMN IF NOT EXISTS end_id BIGINT;
+        CREATE TABLE IF NOT EXISTS migration_failures (
+            migration TEXT NOT NULL,
+            auction_id BIGINT NOT NULL,
+            reason TEXT NOT NULL,
+            error TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            attempts INT NOT NULL DEFAULT 1,
+            first_failed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            last_failed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            resolved_at TIMESTAMPTZ,
+            PRIMARY KEY (migration, auction_id, reason)
+        );
+        ALTER TABLE migration_failures
+            ADD COLUMN IF NOT EXISTS tombstoned_at TIMESTAMPTZ,
+            ADD COLUMN IF NOT EXISTS retry_args TEXT;
+        CREATE TABLE IF NOT EXISTS migration_auction_status (
+            auction_id BIGINT PRIMARY KEY,
+            status INT NOT NULL,
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+        CREATE TABLE IF NOT EXISTS migration_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+        CREATE TABLE IF NOT EXISTS migration_uploads (
+            object TEXT PRIMARY KEY,
+            run_id BIGINT NOT NULL REFERENCES migration_runs (id),
+            size BIGINT NOT NULL,
+            session_url TEXT NOT NULL,
+            uploaded_bytes BIGINT NOT NULL DEFAULT 0,
+            started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            completed_at TIMESTAMPTZ
+        );
+        CREATE TABLE IF NOT EXISTS migration_archives (
+            object TEXT PRIMARY KEY,
+            run_id BIGINT REFERENCES migration_runs (id),
+            start_id BIGINT NOT NULL,
+            end_id BIGINT NOT NULL,
+            rows BIGINT NOT NULL,
+            sha256 TEXT NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            verified_at TIMESTAMPTZ
+        );
+        CREATE TABLE IF NOT EXISTS order_execution_fee_proposals (
+            order_uid BYTEA NOT NULL,
+            auction_id BIGINT NOT NULL,
+            run_id BIGINT NOT NULL REFERENCES migration_runs (id),
+            old_fee NUMERIC(78, 0) NOT NULL,
+            old_token BYTEA,
+            new_fee NUMERIC(78, 0) NOT NULL,
+            new_token BYTEA NOT NULL,
+            status TEXT NOT NULL DEFAULT 'proposed',
+            proposed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            resolved_at TIMESTAMPTZ,
+            PRIMARY KEY (order_uid, auction_id)
+        );
+        CREATE TABLE IF NOT EXISTS fee_conversion_backup (
+            order_uid BYTEA NOT NULL,
+            auction_id BIGINT NOT NULL,
+            run_id BIGINT REFERENCES migration_runs (id),
+            executed_fee NUMERIC(78, 0) NOT NULL,
+            executed_fee_token BYTEA,
+            backed_up_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            restored_at TIMESTAMPTZ,
+            PRIMARY KEY (order_uid, auction_id)
+        );
+        ALTER TABLE fee_conversion_backup
+            ADD COLUMN IF NOT EXISTS written_fee NUMERIC(78, 0),
+            ADD COLUMN IF NOT EXISTS written_fee_token BYTEA,
+            ADD COLUMN IF NOT EXISTS skipped_at TIMESTAMPTZ;
+        CREATE TABLE IF NOT EXISTS migration_audit_log (
+            id BIGSERIAL PRIMARY KEY,
+            migration TEXT NOT NULL,
+            table_name TEXT NOT NULL,
+            operation TEXT NOT NULL,
+            pk JSONB NOT NULL,
+            old_values JSONB,
+            new_values JSONB NOT NULL,
+            batch_id BIGINT NOT NULL,
+            logged_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+        CREATE INDEX IF NOT EXISTS migration_audit_log_batch_id
+            ON migration_audit_log (batch_id);"#;
+
+    sqlx::raw_sql(QUERY).execute(ex).await?;
+    Ok(())
+}
+
+/// Records the start of a run of `migration` and returns its id.
+/// `snapshot_max_id` is the highest source auction id at the start of the
+/// run, which the run never processes beyond. `schema_variant` describes the
+/// schema the run found, see [`crate::database_schema::Schema`].
+pub async fn start(
+    ex: &mut PgConnection,
+    migration: &str,
+    snapshot_max_id: Option<i64>,
+    schema_variant: Option<&str>,
+) -> Result<i64, sqlx::Error> {
+    const QUERY: &str = r#"
+        INSERT INTO migration_runs (migration, snapshot_max_id, schema_variant)
+        VALUES ($1, $2, $3)
+        RETURNING id;"#;
+
+    sqlx::query_scalar(QUERY)
+        .bind(migration)
+        .bind(snapshot_max_id)
+        .bind(schema_variant)
+        .fetch_one(ex)
+        .await
+}
+
+pub async fn finish(ex: &mut PgConnection, id: i64) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+        UPDATE migration_runs
+        SET finished_at = now()
+        WHERE id = $1;"#;
+
+    sqlx::query(QUERY).bind(id).execute(ex).await?;
+    Ok(())
+}
+
+/// Key of the setting holding the chain the database belongs to.
+pub const CHAIN_ID: &str = "chain_id";
+
+/// Returns the value of the setting `key`, if it is set.
+pub async fn fetch_setting(
+    ex: &mut PgConnection,
+    key: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    const QUERY: &str = "SELECT value FROM migration_settings WHERE key = $1;";
+
+    sqlx::query_scalar(QUERY).bind(key).fetch_optional(ex).await
+}
+
+/// Sets the setting `key` to `value`, replacing its previous value.
+pub async fn save_setting(
+    ex: &mut PgConnection,
+    key: &str,
+    value: &str,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+        INSERT INTO migration_settings (key, value)
+        VALUES ($1, $2)
+        ON CONFLICT (key) DO UPDATE SET
+            value = EXCLUDED.value,
+            updated_at = now();"#;
+
+    sqlx::query(QUERY).bind(key).bind(value).execute(ex).await?;
+    Ok(())
+}
+
+/// An upload of an export to object storage, see [`crate::uploads`].
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct Upload {
+    pub size: i64,
+    pub session_url: String,
+    pub uploaded_bytes: i64,
+    pub completed: bool,
+}
+
+/// Fetches the last upload to `object`, if there was one.
+pub async fn fetch_upload(
+    ex: &mut PgConnection,
+    object: &str,
+) -> Result<Option<Upload>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT size, session_url, uploaded_bytes, completed_at IS NOT NULL AS completed
+        FROM migration_uploads
+        WHERE object = $1;"#;
+
+    sqlx::query_as(QUERY).bind(object).fetch_optional(ex).await
+}
+
+/// Records that run `run_id` started a new upload of `size` bytes to
+/// `object` in the session at `session_url`, replacing an earlier one.
+pub async fn start_upload(
+    ex: &mut PgConnection,
+    object: &str,
+    run_id: i64,
+    size: i64,
+    session_url: &str,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+        INSERT INTO migration_uploads (object, run_id, size, session_url)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (object) DO UPDATE SET
+            run_id = EXCLUDED.run_id,
+            size = EXCLUDED.size,
+            session_url = EXCLUDED.session_url,
+            uploaded_bytes = 0,
+            started_at = now(),
+            updated_at = now(),
+            completed_at = NULL;"#;
+
+    sqlx::query(QUERY)
+        .bind(object)
+        .bind(run_id)
+        .bind(size)
+        .bind(session_url)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Records that run `run_id` got the upload to `object` to `uploaded_bytes`,
+/// and whether that completed it.
+pub async fn save_upload_progress(
+    ex: &mut PgConnection,
+    object: &str,
+    run_id: i64,
+    uploaded_bytes: i64,
+    completed: bool,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+        UPDATE migration_uploads
+        SET run_id = $2,
+            uploaded_bytes = $3,
+            updated_at = now(),
+            completed_at = CASE WHEN $4 THEN now() END
+        WHERE object = $1;"#;
+
+    sqlx::query(QUERY)
+        .bind(object)
+        .bind(run_id)
+        .bind(uploaded_bytes)
+        .bind(completed)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Records that run `run_id` wrote the archive object of `manifest`. An
+/// earlier verification of the object stays valid if its content is the same.
+pub async fn save_archive(
+    ex: &mut PgConnection,
+    run_id: i64,
+    manifest: &Manifest,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+        INSERT INTO migration_archives (object, run_id, start_id, end_id, rows, sha256)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (object) DO UPDATE SET
+            run_id = EXCLUDED.run_id,
+            start_id = EXCLUDED.start_id,
+            end_id = EXCLUDED.end_id,
+            rows = EXCLUDED.rows,
+            sha256 = EXCLUDED.sha256,
+            created_at = now(),
+            verified_at = CASE
+                WHEN migration_archives.sha256 = EXCLUDED.sha256
+                THEN migration_archives.verified_at
+            END;"#;
+
+    sqlx::query(QUERY)
+        .bind(&manifest.object)
+        .bind(run_id)
+        .bind(manifest.start_auction_id)
+        .bind(manifest.end_auction_id)
+        .bind(manifest.rows as i64)
+        .bind(&manifest.sha256)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Records whether the stored archive object of `manifest` matched it when
+/// it was last checked. Only verified objects count as archived.
+pub async fn save_archive_verification(
+    ex: &mut PgConnection,
+    manifest: &Manifest,
+    verified: bool,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+        INSERT INTO migration_archives (object, start_id, end_id, rows, sha256, verified_at)
+        VALUES ($1, $2, $3, $4, $5, CASE WHEN $6 THEN now() END)
+        ON CONFLICT (object) DO UPDATE SET
+            start_id = EXCLUDED.start_id,
+            end_id = EXCLUDED.end_id,
+            rows = EXCLUDED.rows,
+            sha256 = EXCLUDED.sha256,
+            verified_at = EXCLUDED.verified_at;"#;
+
+    sqlx::query(QUERY)
+        .bind(&manifest.object)
+        .bind(manifest.start_auction_id)
+        .bind(manifest.end_auction_id)
+        .bind(manifest.rows as i64)
+        .bind(&manifest.sha256)
+        .bind(verified)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Takes the session level advisory lock of `migration` if no other session
+/// holds it, and returns whether it did. The lock is held until the
+/// connection of `ex` closes.
+pub async fn try_lock(ex: &mut PgConnection, migration: &str) -> Result<bool, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT pg_try_advisory_lock(hashtext('data-migration'), hashtext($1));"#;
+
+    sqlx::query_scalar(QUERY)
+        .bind(migration)
+        .fetch_one(ex)
+        .await
+}
+
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct Annotation {
+    pub run_id: Option<i64>,
+    pub auction_id: Option<i64>,
+    pub note: String,
+    pub created_at: DateTime<Utc>,
+}
+
+pub async fn annotate(
+    ex: &mut PgConnection,
+    run_id: Option<i64>,
+    auction_id: Option<i64>,
+    note: &str,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+        INSERT INTO migration_annotations (run_id, auction_id, note)
+        VALUES ($1, $2, $3);"#;
+
+    sqlx::query(QUERY)
+        .bind(run_id)
+        .bind(auction_id)
+        .bind(note)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Fetches the annotations matching all given ids, oldest first.
+pub async fn fetch_annotations(
+    ex: &mut PgConnection,
+    run_id: Option<i64>,
+    auction_id: Option<i64>,
+) -> Result<Vec<Annotation>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT run_id, auction_id, note, created_at
+        FROM migration_annotations
+        WHERE ($1::bigint IS NULL OR run_id = $1)
+        AND ($2::bigint IS NULL OR auction_id = $2)
+        ORDER BY created_at ASC;"#;
+
+    sqlx::query_as(QUERY)
+        .bind(run_id)
+        .bind(auction_id)
+        .fetch_all(ex)
+        .await
+}
+
+/// Range of auction ids `start_id..=end_id` that only `owner` may write.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct Ownership {
+    pub start_id: i64,
+    pub end_id: i64,
+    pub owner: String,
+}
+
+/// Returns the owner of the first of `ranges` containing `auction_id`.
+pub fn owner_of(ranges: &[Ownership], auction_id: i64) -> Option<&str> {
+    ranges
+        .iter()
+        .find(|range| (range.start_id..=range.end_id).contains(&auction_id))
+        .map(|range| range.owner.as_str())
+}
+
+pub async fn mark_owned(
+    ex: &mut PgConnection,
+    start_id: i64,
+    end_id: i64,
+    owner: &str,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+        INSERT INTO migration_ownership (start_id, end_id, owner)
+        VALUES ($1, $2, $3);"#;
+
+    sqlx::query(QUERY)
+        .bind(start_id)
+        .bind(end_id)
+        .bind(owner)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Fetches the ranges owned by anyone but `owner`, lowest first.
+pub async fn fetch_foreign_ownership(
+    ex: &mut PgConnection,
+    owner: &str,
+) -> Result<Vec<Ownership>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT start_id, end_id, owner
+        FROM migration_ownership
+        WHERE owner <> $1
+        ORDER BY start_id ASC;"#;
+
+    sqlx::query_as(QUERY).bind(owner).fetch_all(ex).await
+}
+
+/// How the auctions of a range in `migration_frontier` were completed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FrontierKind {
+    /// By a pass going down without upper bound, which continues the ranges
+    /// of the passes before it, so every auction above it is covered.
+    Descending,
+    /// By the pass going up from the oldest auctions of `--bidirectional`.
+    Ascending,
+    /// By a pass through a range ending at `--end-auction-id`.
+    Bounded,
+    /// Out of order, ahead of the other passes.
+    Prioritized,
+}
+
+impl FrontierKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Descending => "descending",
+            Self::Ascending => "ascending",
+            Self::Bounded => "bounded",
+            Self::Prioritized => "prioritized",
+        }
+    }
+}
+
+/// Publishes that `migration` completed the auctions `start_id..=end_id` in
+/// run `run_id`, so readers like the orderbook know which auctions they can
+/// serve from the migrated tables. Returns the id of the range to extend it
+/// with [`update_frontier`].
+pub async fn insert_frontier(
+    ex: &mut PgConnection,
+    run_id: i64,
+    migration: &str,
+    start_id: i64,
+    end_id: i64,
+    kind: FrontierKind,
+) -> Result<i64, sqlx::Error> {
+    const QUERY: &str = r#"
+        INSERT INTO migration_frontier (run_id, migration, start_id, end_id, kind)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id;"#;
+
+    sqlx::query_scalar(QUERY)
+        .bind(run_id)
+        .bind(migration)
+        .bind(start_id)
+        .bind(end_id)
+        .bind(kind.as_str())
+        .fetch_one(ex)
+        .await
+}
+
+/// Returns the lowest auction the passes of `migration` going down without
+/// upper bound reached, from the ranges they published with
+/// [`insert_frontier`]. Databases migrated before ranges were published have
+/// none, for them it is the lowest auction in `competition_auctions` outside
+/// of the ranges completed otherwise.
+pub async fn fetch_lowest_covered(
+    ex: &mut PgConnection,
+    migration: &str,
+) -> Result<Option<i64>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT COALESCE(
+            (
+                SELECT MIN(start_id)
+                FROM migration_frontier
+                WHERE migration = $1 AND kind = $2
+            ),
+            (
+                SELECT MIN(ca.id)
+                FROM competition_auctions ca
+                WHERE NOT EXISTS (
+                    SELECT 1
+                    FROM migration_frontier f
+                    WHERE f.migration = $1
+                        AND f.kind <> $2
+                        AND ca.id BETWEEN f.start_id AND f.end_id
+                )
+            )
+        );"#;
+
+    sqlx::query_scalar(QUERY)
+        .bind(migration)
+        .bind(FrontierKind::Descending.as_str())
+        .fetch_one(ex)
+        .await
+}
+
+pub async fn update_frontier(
+    ex: &mut PgConnection,
+    id: i64,
+    start_id: i64,
+    end_id: i64,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+        UPDATE migration_frontier
+        SET start_id = $2, end_id = $3, updated_at = now()
+        WHERE id = $1;"#;
+
+    sqlx::query(QUERY)
+        .bind(id)
+        .bind(start_id)
+        .bind(end_id)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Checkpoints that run `run_id` of `migration` committed everything down to
+/// `last_auction_id`, replacing the checkpoint of any earlier run.
+pub async fn save_progress(
+    ex: &mut PgConnection,
+    migration: &str,
+    run_id: i64,
+    last_auction_id: i64,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+        INSERT INTO migration_progress (migration, run_id, last_auction_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (migration) DO UPDATE
+        SET started_at = CASE
+                WHEN migration_progress.run_id = EXCLUDED.run_id
+                THEN migration_progress.started_at
+                ELSE now()
+            END,
+            run_id = EXCLUDED.run_id,
+            last_auction_id = EXCLUDED.last_auction_id,
+            updated_at = now();"#;
+
+    sqlx::query(QUERY)
+        .bind(migration)
+        .bind(run_id)
+        .bind(last_auction_id)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Returns the last auction checkpointed by a run of `migration` that never
+/// finished, e.g. because it crashed. Nothing is returned once a run finished.
+pub async fn fetch_progress(
+    ex: &mut PgConnection,
+    migration: &str,
+) -> Result<Option<i64>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT p.last_auction_id
+        FROM migration_progress p
+        JOIN migration_runs r ON r.id = p.run_id
+        WHERE p.migration = $1 AND r.finished_at IS NULL;"#;
+
+    sqlx::query_scalar(QUERY)
+        .bind(migration)
+        .fetch_optional(ex)
+        .await
+}
+
+/// Checkpoint of one worker of a run, which populates `start_id..=end_id`
+/// going down and has committed everything from `end_id` down to
+/// `last_auction_id`, or nothing if `last_auction_id` is above `end_id`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, sqlx::FromRow)]
+pub struct WorkerProgress {
+    pub start_id: i64,
+    pub end_id: i64,
+    pub last_auction_id: i64,
+}
+
+/// Checkpoints `progress` of a worker of run `run_id` of `migration`. The
+/// checkpoints of workers are kept apart from the one of [`save_progress`]
+/// by their range.
+pub async fn save_worker_progress(
+    ex: &mut PgConnection,
+    migration: &str,
+    run_id: i64,
+    progress: WorkerProgress,
+) -> Result<(), sqlx::Error> {
+    const QUERY: &str = r#"
+        INSERT INTO migration_progress
+            (migration, run_id, last_auction_id, start_id, end_id)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (migration) DO UPDATE
+        SET started_at = CASE
+                WHEN migration_progress.run_id = EXCLUDED.run_id
+                THEN migration_progress.started_at
+                ELSE now()
+            END,
+            run_id = EXCLUDED.run_id,
+            last_auction_id = EXCLUDED.last_auction_id,
+            updated_at = now();"#;
+
+    sqlx::query(QUERY)
+        .bind(format!(
+            "{migration}/{}..={}",
+            progress.start_id, progress.end_id
+        ))
+        .bind(run_id)
+        .bind(progress.last_auction_id)
+        .bind(progress.start_id)
+        .bind(progress.end_id)
+        .execute(ex)
+        .await?;
+    Ok(())
+}
+
+/// Returns the checkpoints of the workers of the last run of `migration`
+/// that used workers, if it never finished. Highest range first.
+pub async fn fetch_worker_progress(
+    ex: &mut PgConnection,
+    migration: &str,
+) -> Result<Vec<WorkerProgress>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT p.start_id, p.end_id, p.last_auction_id
+        FROM migration_progress p
+        JOIN migration_runs r ON r.id = p.run_id
+        WHERE p.run_id = (
+            SELECT MAX(run_id)
+            FROM migration_progress
+            WHERE migration LIKE $1 || '/%' AND start_id IS NOT NULL
+        )
+        AND p.migration LIKE $1 || '/%'
+        AND p.start_id IS NOT NULL
+        AND r.finished_at IS NULL
+        ORDER BY p.start_id DESC;"#;
+
+    sqlx::query_as(QUERY).bind(migration).fetch_all(ex).await
+}
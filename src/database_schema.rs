@@ -0,0 +1,159 @@
+//! Detection of the optional parts of the schema of the connected database,
+//! so the same binary works on deployments whose schemas drifted apart.
+
+use {
+    crate::database_solver_competition::AuctionColumn,
+    anyhow::{Context, Result},
+    sqlx::PgConnection,
+    std::{
+        fmt::{self, Display, Formatter},
+        str::FromStr,
+    },
+};
+
+/// The optional parts of the schema the connected database has.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Schema {
+    /// Columns of `competition_auctions`.
+    pub auction_columns: Vec<AuctionColumn>,
+    /// `order_execution` still has the legacy `surplus_fee` column.
+    pub surplus_fee: bool,
+    /// The `proposed_trade_executions` table.
+    pub trade_executions: bool,
+}
+
+/// Describes the schema in one line, like
+/// `competition_auctions(block,deadline) surplus_fee`.
+impl Display for Schema {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let columns: Vec<_> = self
+            .auction_columns
+            .iter()
+            .map(AuctionColumn::as_str)
+            .collect();
+        write!(f, "competition_auctions({})", columns.join(","))?;
+        if self.surplus_fee {
+            f.write_str(" surplus_fee")?;
+        }
+        if self.trade_executions {
+            f.write_str(" proposed_trade_executions")?;
+        }
+        Ok(())
+    }
+}
+
+pub async fn fetch(ex: &mut PgConnection) -> Result<Schema, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT column_name::text
+        FROM information_schema.columns
+        WHERE table_schema = current_schema() AND table_name = 'competition_auctions';"#;
+
+    let columns: Vec<String> = sqlx::query_scalar(QUERY).fetch_all(&mut *ex).await?;
+    let auction_columns = AuctionColumn::ALL
+        .into_iter()
+        .filter(|column| {
+            column
+                .names()
+                .iter()
+                .all(|name| columns.iter().any(|column| column == name))
+        })
+        .collect();
+    Ok(Schema {
+        auction_columns,
+        surplus_fee: crate::database_order_executions::has_surplus_fee_column(ex).await?,
+        trade_executions: crate::database_proposed_solutions::has_trade_executions_table(ex)
+            .await?,
+    })
+}
+
+/// Returns those of `tables` that don't exist.
+pub async fn fetch_missing_tables(
+    ex: &mut PgConnection,
+    tables: &[&str],
+) -> Result<Vec<String>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT name
+        FROM unnest($1::text[]) AS name
+        WHERE to_regclass(name) IS NULL;"#;
+
+    sqlx::query_scalar(QUERY).bind(tables).fetch_all(ex).await
+}
+
+/// Version of a migration of the services schema, like `83` or `1.2`.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Version(Vec<u64>);
+
+impl FromStr for Version {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        s.split('.')
+            .map(|part| part.parse().with_context(|| format!("invalid version {s}")))
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let parts: Vec<_> = self.0.iter().map(u64::to_string).collect();
+        f.write_str(&parts.join("."))
+    }
+}
+
+/// The version of the highest migration of the services schema that was
+/// applied successfully, from the history Flyway keeps. `None` if there is
+/// no history.
+/// Returns the name of the connected database.
+pub async fn fetch_database_name(ex: &mut PgConnection) -> Result<String, sqlx::Error> {
+    sqlx::query_scalar("SELECT current_database()::text;")
+        .fetch_one(ex)
+        .await
+}
+
+pub async fn fetch_services_version(ex: &mut PgConnection) -> Result<Option<Version>> {
+    const EXISTS: &str = "SELECT to_regclass('flyway_schema_history') IS NOT NULL;";
+    const QUERY: &str = r#"
+        SELECT version
+        FROM flyway_schema_history
+        WHERE success AND version IS NOT NULL;"#;
+
+    let exists: bool = sqlx::query_scalar(EXISTS).fetch_one(&mut *ex).await?;
+    if !exists {
+        return Ok(None);
+    }
+    let versions: Vec<String> = sqlx::query_scalar(QUERY).fetch_all(ex).await?;
+    versions
+        .iter()
+        .map(|version| version.parse::<Version>())
+        .try_fold(None, |max, version| Ok(max.max(Some(version?))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let schema = Schema {
+            auction_columns: vec![AuctionColumn::Block, AuctionColumn::Prices],
+            surplus_fee: true,
+            trade_executions: false,
+        };
+        assert_eq!(
+            schema.to_string(),
+            "competition_auctions(block,prices) surplus_fee"
+        );
+        assert_eq!(Schema::default().to_string(), "competition_auctions()");
+    }
+
+    #[test]
+    fn version() {
+        let version = |s: &str| s.parse::<Version>().unwrap();
+        assert!(version("083") > version("9"));
+        assert!(version("1.10") > version("1.9"));
+        assert!(version("2") > version("1.9"));
+        assert_eq!(version("083").to_string(), "83");
+        assert!("1.x".parse::<Version>().is_err());
+    }
+}
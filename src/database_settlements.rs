@@ -0,0 +1,110 @@
+use {
+    crate::database_orders::{OrderUid, TransactionHash},
+    bigdecimal::BigDecimal,
+    sqlx::PgConnection,
+};
+
+/// An order traded in a settlement, with the amounts of its `Trade` event.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct Trade {
+    pub order_uid: OrderUid,
+    /// The sold amount including the fee.
+    pub sell_amount: BigDecimal,
+    pub buy_amount: BigDecimal,
+    pub fee_amount: BigDecimal,
+}
+
+/// Fetches the hashes of the transactions that settled `auction_id`, in the
+/// order they were mined.
+pub async fn fetch_transaction_hashes(
+    ex: &mut PgConnection,
+    auction_id: i64,
+) -> Result<Vec<TransactionHash>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT tx_hash
+        FROM settlements
+        WHERE auction_id = $1
+        ORDER BY block_number ASC, log_index ASC;"#;
+
+    sqlx::query_scalar(QUERY)
+        .bind(auction_id)
+        .fetch_all(ex)
+        .await
+}
+
+/// Fetches the trades of the settlements of `auction_id`, in the order they
+/// were mined.
+///
+/// A trade belongs to the settlement event following it in the same block,
+/// like the indexer assigns them.
+pub async fn fetch_trades(
+    ex: &mut PgConnection,
+    auction_id: i64,
+) -> Result<Vec<Trade>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT t.order_uid, t.sell_amount, t.buy_amount, t.fee_amount
+        FROM settlements s
+        JOIN trades t
+            ON t.block_number = s.block_number AND t.log_index < s.log_index
+        WHERE s.auction_id = $1 AND NOT EXISTS (
+            SELECT 1
+            FROM settlements p
+            WHERE p.block_number = s.block_number
+                AND p.log_index > t.log_index
+                AND p.log_index < s.log_index
+        )
+        ORDER BY t.block_number ASC, t.log_index ASC;"#;
+
+    sqlx::query_as(QUERY).bind(auction_id).fetch_all(ex).await
+}
+
+/// Fetches the auction that `tx_hash` settled, if the settlement is indexed
+/// and linked to an auction.
+pub async fn fetch_auction_id(
+    ex: &mut PgConnection,
+    tx_hash: &TransactionHash,
+) -> Result<Option<i64>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT auction_id
+        FROM settlements
+        WHERE tx_hash = $1 AND auction_id IS NOT NULL
+        LIMIT 1;"#;
+
+    sqlx::query_scalar(QUERY)
+        .bind(tx_hash)
+        .fetch_optional(ex)
+        .await
+}
+
+/// Links the settlements of `tx_hash` that are not linked yet to
+/// `auction_id`. Returns how many settlements were linked.
+pub async fn save_auction_id(
+    ex: &mut PgConnection,
+    tx_hash: &TransactionHash,
+    auction_id: i64,
+) -> Result<u64, sqlx::Error> {
+    let query = format!(
+        r#"
+        WITH written AS (
+            UPDATE settlements s
+            SET auction_id = $2
+            FROM settlements prev
+            WHERE prev.block_number = s.block_number
+                AND prev.log_index = s.log_index
+                AND s.tx_hash = $1
+                AND s.auction_id IS NULL
+            RETURNING to_jsonb(prev) AS old_values, to_jsonb(s) AS new_values
+        ),
+        {}
+        SELECT COUNT(*)
+        FROM written;"#,
+        crate::database_audit::audited("settlements", &["block_number", "log_index"], "written")
+    );
+
+    let linked: i64 = sqlx::query_scalar(&query)
+        .bind(tx_hash)
+        .bind(auction_id)
+        .fetch_one(ex)
+        .await?;
+    Ok(linked as u64)
+}
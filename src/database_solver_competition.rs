@@ -1,19 +1,21 @@
+use crate::{conversions::big_decimal_to_u256, database::IdRange};
 use anyhow::ensure;
-use anyhow::Result;
-use bigdecimal::{
-    num_bigint::{Sign, ToBigInt},
-    BigDecimal,
-};
-use num::{BigInt, BigUint};
-use primitive_types::U256;
+use anyhow::{Context, Result};
+use bigdecimal::BigDecimal;
+use primitive_types::{H160, U256};
 use sqlx::{
     encode::IsNull,
     error::BoxDynError,
     postgres::{PgArgumentBuffer, PgHasArrayType, PgTypeInfo, PgValueFormat, PgValueRef},
     types::JsonValue,
-    Decode, Encode, PgConnection, Postgres, Type,
+    Decode, Encode, PgConnection, Postgres, QueryBuilder, Type,
+};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug, Display, Formatter},
+    num::NonZeroUsize,
+    str::FromStr,
 };
-use std::fmt::{self, Debug, Formatter};
 
 /// Wrapper type for fixed size byte arrays compatible with sqlx's Postgres
 /// implementation.
@@ -77,69 +79,364 @@ pub type OrderUid = ByteArray<56>;
 #[derive(Clone, Debug, sqlx::FromRow)]
 pub struct SolverCompetition {
     pub id: i64,
-    pub json: JsonValue,
+    /// Null for a few historic auctions.
+    pub json: Option<JsonValue>,
 }
 
 #[derive(Clone, Debug, sqlx::FromRow)]
 pub struct RichSolverCompetition {
     pub id: i64,
-    pub json: JsonValue,
+    /// Null for a few historic auctions.
+    pub json: Option<JsonValue>,
     pub deadline: i64,
     pub surplus_capturing_jit_order_owners: Vec<Address>,
+    /// Size of the JSON as stored, if fetched.
+    #[sqlx(default)]
+    pub json_bytes: Option<i32>,
+    /// Number of solutions if they were removed from `json`, see
+    /// [`fetch_without_solutions`].
+    #[sqlx(default)]
+    pub solution_count: Option<i32>,
+}
+
+/// Order in which auction ids are gone through.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Direction {
+    Descending,
+    Ascending,
+}
+
+impl Direction {
+    fn order(&self) -> &'static str {
+        match self {
+            Self::Descending => "DESC",
+            Self::Ascending => "ASC",
+        }
+    }
 }
 
 /// Migrate all the auctions from the solver_competitions table to the auctions
 /// table. This is a one-time migration.
 ///
-/// Entries are fetched going from higher auction_id to lower auction_id.
+/// Entries strictly between `after` and `before` are fetched in `direction`.
+/// The JSON of entries larger than `max_json_bytes` as stored is not fetched.
 pub async fn fetch_batch(
     ex: &mut PgConnection,
-    auction_id: i64,
+    after: i64,
+    before: i64,
+    direction: Direction,
     batch_size: i64,
+    max_json_bytes: i32,
 ) -> Result<Vec<RichSolverCompetition>, sqlx::Error> {
-    const QUERY: &str = r#"
+    fetch_batch_filtered(ex, after, before, direction, batch_size, max_json_bytes, "").await
+}
+
+/// Like [`fetch_batch`] but only fetches the competitions that have no row in
+/// competition_auctions yet, so reruns skip migrated auctions in the query
+/// instead of reading and writing them again.
+pub async fn fetch_batch_missing(
+    ex: &mut PgConnection,
+    after: i64,
+    before: i64,
+    direction: Direction,
+    batch_size: i64,
+    max_json_bytes: i32,
+) -> Result<Vec<RichSolverCompetition>, sqlx::Error> {
+    const MISSING: &str =
+        "AND NOT EXISTS (SELECT 1 FROM competition_auctions ca WHERE ca.id = sc.id)";
+
+    fetch_batch_filtered(
+        ex,
+        after,
+        before,
+        direction,
+        batch_size,
+        max_json_bytes,
+        MISSING,
+    )
+    .await
+}
+
+/// [`fetch_batch`] with `filter` appended to the conditions on `sc`.
+async fn fetch_batch_filtered(
+    ex: &mut PgConnection,
+    after: i64,
+    before: i64,
+    direction: Direction,
+    batch_size: i64,
+    max_json_bytes: i32,
+    filter: &str,
+) -> Result<Vec<RichSolverCompetition>, sqlx::Error> {
+    let query = format!(
+        r#"
         SELECT 
         sc.id as id, 
-        sc.json as json, 
+        CASE WHEN pg_column_size(sc.json) > $4 THEN NULL ELSE sc.json END AS json,
+        pg_column_size(sc.json) AS json_bytes,
         COALESCE(ss.block_deadline, 0) AS deadline,
         COALESCE(jit.owners, ARRAY[]::bytea[]) AS surplus_capturing_jit_order_owners
         FROM solver_competitions sc
         LEFT JOIN settlement_scores ss ON sc.id = ss.auction_id
         LEFT JOIN surplus_capturing_jit_order_owners jit ON sc.id = jit.auction_id
-        WHERE sc.id < $1
-        ORDER BY sc.id DESC
-        LIMIT $2;"#;
+        WHERE sc.id > $1 AND sc.id < $2 {filter}
+        ORDER BY sc.id {}
+        LIMIT $3;"#,
+        direction.order()
+    );
+
+    sqlx::query_as(&query)
+        .bind(after)
+        .bind(before)
+        .bind(batch_size)
+        .bind(max_json_bytes)
+        .fetch_all(ex)
+        .await
+}
+
+/// Fetches a single solver competition with its solutions replaced by an
+/// empty array, so only the much smaller rest of a giant JSON is transferred.
+/// The number of solutions is returned in `solution_count`.
+pub async fn fetch_without_solutions(
+    ex: &mut PgConnection,
+    auction_id: i64,
+) -> Result<Option<RichSolverCompetition>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT
+        sc.id as id,
+        jsonb_set(sc.json::jsonb, '{solutions}', '[]')::json AS json,
+        pg_column_size(sc.json) AS json_bytes,
+        jsonb_array_length(sc.json::jsonb->'solutions') AS solution_count,
+        COALESCE(ss.block_deadline, 0) AS deadline,
+        COALESCE(jit.owners, ARRAY[]::bytea[]) AS surplus_capturing_jit_order_owners
+        FROM solver_competitions sc
+        LEFT JOIN settlement_scores ss ON sc.id = ss.auction_id
+        LEFT JOIN surplus_capturing_jit_order_owners jit ON sc.id = jit.auction_id
+        WHERE sc.id = $1;"#;
 
-        sqlx::query_as(QUERY)
+    sqlx::query_as(QUERY)
         .bind(auction_id)
+        .fetch_optional(ex)
+        .await
+}
+
+/// What can be known about a solver competition without reading its JSON.
+#[derive(Clone, Copy, Debug, sqlx::FromRow)]
+pub struct Metadata {
+    pub id: i64,
+    pub json_is_null: bool,
+    /// Size of the JSON as stored, possibly compressed.
+    pub json_bytes: Option<i32>,
+}
+
+/// Like [`fetch_batch`] but only reads the JSON of competitions for which
+/// `fetch_json` returns true and that are not null, which avoids detoasting
+/// large values that would be skipped anyway. The others are returned with
+/// `json: None`.
+pub async fn fetch_batch_metadata_first(
+    ex: &mut PgConnection,
+    after: i64,
+    before: i64,
+    direction: Direction,
+    batch_size: i64,
+    fetch_json: impl Fn(&Metadata) -> bool,
+) -> Result<Vec<RichSolverCompetition>, sqlx::Error> {
+    let metadata_query = format!(
+        r#"
+        SELECT id, json IS NULL AS json_is_null, pg_column_size(json) AS json_bytes
+        FROM solver_competitions
+        WHERE id > $1 AND id < $2
+        ORDER BY id {}
+        LIMIT $3;"#,
+        direction.order()
+    );
+    let full_query = format!(
+        r#"
+        SELECT
+        sc.id as id,
+        sc.json as json,
+        COALESCE(ss.block_deadline, 0) AS deadline,
+        COALESCE(jit.owners, ARRAY[]::bytea[]) AS surplus_capturing_jit_order_owners
+        FROM solver_competitions sc
+        LEFT JOIN settlement_scores ss ON sc.id = ss.auction_id
+        LEFT JOIN surplus_capturing_jit_order_owners jit ON sc.id = jit.auction_id
+        WHERE sc.id = ANY($1)
+        ORDER BY sc.id {};"#,
+        direction.order()
+    );
+
+    let metadata: Vec<Metadata> = sqlx::query_as(&metadata_query)
+        .bind(after)
+        .bind(before)
         .bind(batch_size)
+        .fetch_all(&mut *ex)
+        .await?;
+    let ids: Vec<i64> = metadata
+        .iter()
+        .filter(|metadata| !metadata.json_is_null && fetch_json(metadata))
+        .map(|metadata| metadata.id)
+        .collect();
+    let mut full: Vec<RichSolverCompetition> = match ids.is_empty() {
+        true => Vec::new(),
+        false => sqlx::query_as(&full_query).bind(&ids).fetch_all(ex).await?,
+    };
+
+    // both are ordered by id in `direction`
+    let mut full = full.drain(..).peekable();
+    Ok(metadata
+        .into_iter()
+        .map(|metadata| match full.next_if(|row| row.id == metadata.id) {
+            Some(row) => row,
+            None => RichSolverCompetition {
+                id: metadata.id,
+                json: None,
+                deadline: 0,
+                surplus_capturing_jit_order_owners: Vec::new(),
+                json_bytes: metadata.json_bytes,
+                solution_count: None,
+            },
+        })
+        .collect())
+}
+
+/// Returns the highest auction id in the solver_competitions table.
+///
+/// Runs fetch it when they start and never process auctions above it, so
+/// auctions arriving after that are left to follow-up runs. The batch fetches
+/// exclude the ids they are given as bounds, so runs start from one above it.
+pub async fn fetch_max_id(ex: &mut PgConnection) -> Result<Option<i64>, sqlx::Error> {
+    const QUERY: &str = "SELECT MAX(id) FROM solver_competitions;";
+
+    sqlx::query_scalar(QUERY).fetch_one(ex).await
+}
+
+pub async fn fetch_min_id(ex: &mut PgConnection) -> Result<Option<i64>, sqlx::Error> {
+    const QUERY: &str = "SELECT MIN(id) FROM solver_competitions;";
+
+    sqlx::query_scalar(QUERY).fetch_one(ex).await
+}
+
+/// Returns those of `auction_ids` that are in competition_auctions.
+pub async fn fetch_existing(
+    ex: &mut PgConnection,
+    auction_ids: &[i64],
+) -> Result<Vec<i64>, sqlx::Error> {
+    const QUERY: &str = "SELECT id FROM competition_auctions WHERE id = ANY($1);";
+
+    sqlx::query_scalar(QUERY)
+        .bind(auction_ids)
+        .fetch_all(ex)
+        .await
+}
+
+/// Returns up to `limit` ids strictly between `after` and `before` that have a
+/// JSON in solver_competitions but no row in competition_auctions, highest
+/// first.
+pub async fn fetch_missing(
+    ex: &mut PgConnection,
+    after: i64,
+    before: i64,
+    limit: i64,
+) -> Result<Vec<i64>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT sc.id
+        FROM solver_competitions sc
+        WHERE sc.id > $1 AND sc.id < $2
+            AND sc.json IS NOT NULL
+            AND NOT EXISTS (SELECT 1 FROM competition_auctions ca WHERE ca.id = sc.id)
+        ORDER BY sc.id DESC
+        LIMIT $3;"#;
+
+    sqlx::query_scalar(QUERY)
+        .bind(after)
+        .bind(before)
+        .bind(limit)
         .fetch_all(ex)
         .await
 }
 
+/// Returns the auction id up to `max_id` that has a JSON in
+/// solver_competitions but no row in competition_auctions and is reached first
+/// in `direction`: going down from `max_id` this is the frontier of the
+/// contiguous coverage below it, going up from the oldest auction the frontier
+/// of the coverage above it. Auctions skipped by earlier runs count as gaps
+/// too.
+pub async fn fetch_frontier(
+    ex: &mut PgConnection,
+    max_id: i64,
+    direction: Direction,
+) -> Result<Option<i64>, sqlx::Error> {
+    let query = format!(
+        r#"
+        SELECT sc.id
+        FROM solver_competitions sc
+        WHERE sc.id <= $1
+            AND sc.json IS NOT NULL
+            AND NOT EXISTS (SELECT 1 FROM competition_auctions ca WHERE ca.id = sc.id)
+        ORDER BY sc.id {}
+        LIMIT 1;"#,
+        direction.order()
+    );
+
+    sqlx::query_scalar(&query)
+        .bind(max_id)
+        .fetch_optional(ex)
+        .await
+}
+
 /// Get a batch of solver competitions from the solver_competitions table.
+///
+/// Entries strictly between `after` and `before` are fetched going from higher
+/// auction_id to lower auction_id.
 pub async fn fetch_competition_order_execution(
     ex: &mut PgConnection,
-    auction_id: i64,
+    after: i64,
+    before: i64,
     batch_size: i64,
 ) -> Result<Vec<SolverCompetition>, sqlx::Error> {
     const QUERY: &str = r#"
         SELECT 
         sc.id as id, 
-        sc.json as json,
+        sc.json as json
         FROM solver_competitions sc
-        WHERE sc.id < $1
+        WHERE sc.id > $1 AND sc.id < $2
         ORDER BY sc.id DESC
-        LIMIT $2;"#;
+        LIMIT $3;"#;
 
     sqlx::query_as(QUERY)
-        .bind(auction_id)
+        .bind(after)
+        .bind(before)
         .bind(batch_size)
         .fetch_all(ex)
         .await
 }
 
-#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+/// Returns the `limit` largest ranges of consecutive `solver_competitions`
+/// rows that have no entry in `competition_auctions` yet.
+pub async fn fetch_unmigrated_ranges(
+    ex: &mut PgConnection,
+    limit: i64,
+) -> Result<Vec<IdRange>, sqlx::Error> {
+    const QUERY: &str = r#"
+        WITH source AS (
+            SELECT sc.id, ca.id IS NULL AS missing, ROW_NUMBER() OVER (ORDER BY sc.id) AS rn
+            FROM solver_competitions sc
+            LEFT JOIN competition_auctions ca ON sc.id = ca.id
+        ),
+        islands AS (
+            SELECT id, rn - ROW_NUMBER() OVER (ORDER BY id) AS island
+            FROM source
+            WHERE missing
+        )
+        SELECT MIN(id) AS start, MAX(id) AS end, COUNT(*) AS count
+        FROM islands
+        GROUP BY island
+        ORDER BY count DESC, start DESC
+        LIMIT $1;"#;
+
+    sqlx::query_as(QUERY).bind(limit).fetch_all(ex).await
+}
+
+#[derive(Debug, Clone, Default, PartialEq, sqlx::FromRow)]
 pub struct Auction {
     pub id: i64,
     pub block: i64,
@@ -151,52 +448,372 @@ pub struct Auction {
     pub surplus_capturing_jit_order_owners: Vec<Address>,
 }
 
-pub async fn save(ex: &mut PgConnection, auction: Auction) -> Result<(), sqlx::Error> {
+impl Auction {
+    /// Decodes the stored external native prices into a map from token to
+    /// price. Fails if the parallel arrays have different lengths or a stored
+    /// value is not a valid `U256`.
+    pub fn prices(&self) -> Result<BTreeMap<H160, U256>> {
+        ensure!(
+            self.price_tokens.len() == self.price_values.len(),
+            "price_tokens and price_values have different lengths"
+        );
+        self.price_tokens
+            .iter()
+            .zip(&self.price_values)
+            .map(|(token, value)| {
+                let price = big_decimal_to_u256(value)
+                    .with_context(|| format!("invalid price {value} for token {token:?}"))?;
+                Ok((H160(token.0), price))
+            })
+            .collect()
+    }
+
+    /// The names of the fields in which `other` differs from this auction.
+    pub fn differing_fields(&self, other: &Self) -> Vec<&'static str> {
+        [
+            ("id", self.id != other.id),
+            ("block", self.block != other.block),
+            ("deadline", self.deadline != other.deadline),
+            ("order_uids", self.order_uids != other.order_uids),
+            ("price_tokens", self.price_tokens != other.price_tokens),
+            ("price_values", self.price_values != other.price_values),
+            (
+                "surplus_capturing_jit_order_owners",
+                self.surplus_capturing_jit_order_owners != other.surplus_capturing_jit_order_owners,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(field, differs)| differs.then_some(field))
+        .collect()
+    }
+
+    /// Sorts the external native prices by token and drops repeated entries,
+    /// so the stored arrays don't depend on the order of the source. Fails if
+    /// the arrays have different lengths or a token has conflicting prices.
+    pub fn canonicalize_prices(&mut self) -> Result<()> {
+        ensure!(
+            self.price_tokens.len() == self.price_values.len(),
+            "price_tokens and price_values have different lengths"
+        );
+        let mut prices: Vec<_> = std::mem::take(&mut self.price_tokens)
+            .into_iter()
+            .zip(std::mem::take(&mut self.price_values))
+            .collect();
+        prices.sort_by_key(|(token, _)| token.0);
+        for pair in prices.windows(2) {
+            let ((token, a), (next, b)) = (&pair[0], &pair[1]);
+            ensure!(
+                token != next || a == b,
+                "conflicting prices {a} and {b} for token {token:?}"
+            );
+        }
+        prices.dedup_by(|(next, _), (token, _)| token == next);
+        (self.price_tokens, self.price_values) = prices.into_iter().unzip();
+        Ok(())
+    }
+}
+
+/// A column of `competition_auctions` that [`save`] can leave out, for
+/// deployments whose schema doesn't have it yet. The `id` is always written.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AuctionColumn {
+    Block,
+    Deadline,
+    OrderUids,
+    /// Both `price_tokens` and `price_values`, which only make sense together.
+    Prices,
+    SurplusCapturingJitOrderOwners,
+}
+
+impl AuctionColumn {
+    pub const ALL: [Self; 5] = [
+        Self::Block,
+        Self::Deadline,
+        Self::OrderUids,
+        Self::Prices,
+        Self::SurplusCapturingJitOrderOwners,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Block => "block",
+            Self::Deadline => "deadline",
+            Self::OrderUids => "order_uids",
+            Self::Prices => "prices",
+            Self::SurplusCapturingJitOrderOwners => "surplus_capturing_jit_order_owners",
+        }
+    }
+
+    /// The names of the columns in the table.
+    pub fn names(&self) -> &'static [&'static str] {
+        match self {
+            Self::Prices => &["price_tokens", "price_values"],
+            Self::Block => &["block"],
+            Self::Deadline => &["deadline"],
+            Self::OrderUids => &["order_uids"],
+            Self::SurplusCapturingJitOrderOwners => &["surplus_capturing_jit_order_owners"],
+        }
+    }
+}
+
+impl Display for AuctionColumn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AuctionColumn {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|column| column.as_str() == s)
+            .with_context(|| format!("unknown auction column {s}"))
+    }
+}
+
+/// Fetches a single auction from the competition_auctions table.
+pub async fn fetch(ex: &mut PgConnection, id: i64) -> Result<Option<Auction>, sqlx::Error> {
     const QUERY: &str = r#"
-INSERT INTO competition_auctions (id, block, deadline, order_uids, price_tokens, price_values, surplus_capturing_jit_order_owners)
-VALUES ($1, $2, $3, $4, $5, $6, $7)
-    ;"#;
-
-    sqlx::query(QUERY)
-        .bind(auction.id)
-        .bind(auction.block)
-        .bind(auction.deadline)
-        .bind(auction.order_uids)
-        .bind(auction.price_tokens)
-        .bind(auction.price_values)
-        .bind(auction.surplus_capturing_jit_order_owners)
-        .execute(ex)
-        .await?;
+        SELECT id, block, deadline, order_uids, price_tokens, price_values, surplus_capturing_jit_order_owners
+        FROM competition_auctions
+        WHERE id = $1;"#;
 
-    Ok(())
+    sqlx::query_as(QUERY).bind(id).fetch_optional(ex).await
 }
 
-pub fn u256_to_big_uint(input: &U256) -> BigUint {
-    let mut bytes = [0; 32];
-    input.to_big_endian(&mut bytes);
-    BigUint::from_bytes_be(&bytes)
+/// Fetches up to `limit` auctions with `after < id < before` from the
+/// competition_auctions table, ordered by ascending id.
+pub async fn fetch_page(
+    ex: &mut PgConnection,
+    after: i64,
+    before: i64,
+    limit: i64,
+) -> Result<Vec<Auction>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT id, block, deadline, order_uids, price_tokens, price_values, surplus_capturing_jit_order_owners
+        FROM competition_auctions
+        WHERE id > $1 AND id < $2
+        ORDER BY id ASC
+        LIMIT $3;"#;
+
+    sqlx::query_as(QUERY)
+        .bind(after)
+        .bind(before)
+        .bind(limit)
+        .fetch_all(ex)
+        .await
 }
 
-pub fn u256_to_big_decimal(u256: &U256) -> BigDecimal {
-    let big_uint = u256_to_big_uint(u256);
-    BigDecimal::from(BigInt::from(big_uint))
+/// Fetches all auctions with `from <= id <= to` from the competition_auctions
+/// table, ordered by ascending id.
+pub async fn fetch_range(
+    ex: &mut PgConnection,
+    from: i64,
+    to: i64,
+) -> Result<Vec<Auction>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT id, block, deadline, order_uids, price_tokens, price_values, surplus_capturing_jit_order_owners
+        FROM competition_auctions
+        WHERE id >= $1 AND id <= $2
+        ORDER BY id ASC;"#;
+
+    sqlx::query_as(QUERY)
+        .bind(from)
+        .bind(to)
+        .fetch_all(ex)
+        .await
+}
+
+/// Violations of the integrity of the array columns of one row of
+/// `competition_auctions`.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct ArrayViolations {
+    pub id: i64,
+    pub violations: Vec<String>,
+}
+
+/// Checks the array columns of the `limit` rows of `competition_auctions`
+/// below `auction_id`, highest first. Returns every checked row, with no
+/// violations if it is fine. The checks run in the database, so rows that
+/// could not be decoded are checked too.
+pub async fn fetch_array_violations(
+    ex: &mut PgConnection,
+    auction_id: i64,
+    limit: i64,
+) -> Result<Vec<ArrayViolations>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT id, ARRAY_REMOVE(ARRAY[
+            CASE WHEN cardinality(price_tokens) <> cardinality(price_values)
+                THEN 'price_tokens and price_values have different lengths' END,
+            CASE WHEN EXISTS (SELECT 1 FROM unnest(order_uids) uid WHERE length(uid) IS DISTINCT FROM 56)
+                THEN 'order uid that is not 56 bytes' END,
+            CASE WHEN EXISTS (SELECT 1 FROM unnest(price_tokens) token WHERE length(token) IS DISTINCT FROM 20)
+                THEN 'price token that is not 20 bytes' END,
+            CASE WHEN EXISTS (
+                SELECT 1 FROM unnest(surplus_capturing_jit_order_owners) owner
+                WHERE length(owner) IS DISTINCT FROM 20
+            )
+                THEN 'surplus capturing jit order owner that is not 20 bytes' END,
+            CASE WHEN EXISTS (SELECT 1 FROM unnest(price_values) value WHERE value IS NULL OR value < 0)
+                THEN 'price value that is negative or null' END
+        ], NULL) AS violations
+        FROM competition_auctions
+        WHERE id < $1
+        ORDER BY id DESC
+        LIMIT $2;"#;
+
+    sqlx::query_as(QUERY)
+        .bind(auction_id)
+        .bind(limit)
+        .fetch_all(ex)
+        .await
 }
 
-pub fn big_uint_to_u256(input: &BigUint) -> Result<U256> {
-    let bytes = input.to_bytes_be();
-    ensure!(bytes.len() <= 32, "too large");
-    Ok(U256::from_big_endian(&bytes))
+/// Inserts the auction with canonical price arrays, see
+/// [`Auction::canonicalize_prices`]. Only writes the id and `columns`, the
+/// others get their default. An auction that already exists is kept as it
+/// is, unless `overwrite` is set, so reruns and overlapping ranges are safe.
+pub async fn save(
+    ex: &mut PgConnection,
+    auction: Auction,
+    columns: &[AuctionColumn],
+    overwrite: bool,
+) -> Result<()> {
+    save_batch(ex, vec![auction], columns, NonZeroUsize::MIN, overwrite).await?;
+    Ok(())
 }
 
-pub fn big_int_to_u256(input: &BigInt) -> Result<U256> {
-    ensure!(input.sign() != Sign::Minus, "negative");
-    big_uint_to_u256(input.magnitude())
+/// Inserts the auctions like [`save`], up to `insert_batch_size` of them in
+/// each statement. Returns how many rows were inserted or overwritten.
+pub async fn save_batch(
+    ex: &mut PgConnection,
+    mut auctions: Vec<Auction>,
+    columns: &[AuctionColumn],
+    insert_batch_size: NonZeroUsize,
+    overwrite: bool,
+) -> Result<u64> {
+    for auction in &mut auctions {
+        auction.canonicalize_prices()?;
+    }
+
+    // in a fixed order, without repeated columns
+    let columns: Vec<_> = AuctionColumn::ALL
+        .into_iter()
+        .filter(|column| columns.contains(column))
+        .collect();
+    let mut rows = 0;
+    let mut auctions = auctions.into_iter().peekable();
+    while auctions.peek().is_some() {
+        let batch: Vec<_> = auctions.by_ref().take(insert_batch_size.get()).collect();
+        let ids: Vec<i64> = batch.iter().map(|auction| auction.id).collect();
+        let mut query = QueryBuilder::new(
+            "WITH prev AS (SELECT id, to_jsonb(ca) AS old_values FROM competition_auctions ca \
+             WHERE id = ANY(",
+        );
+        query.push_bind(ids);
+        query.push(")), written AS (INSERT INTO competition_auctions AS ca (id");
+        for name in columns.iter().flat_map(AuctionColumn::names) {
+            query.push(", ").push(name);
+        }
+        query.push(") ");
+        query.push_values(batch, |mut row, mut auction| {
+            row.push_bind(auction.id);
+            for column in &columns {
+                match column {
+                    AuctionColumn::Block => row.push_bind(auction.block),
+                    AuctionColumn::Deadline => row.push_bind(auction.deadline),
+                    AuctionColumn::OrderUids => {
+                        row.push_bind(std::mem::take(&mut auction.order_uids))
+                    }
+                    AuctionColumn::Prices => row
+                        .push_bind(std::mem::take(&mut auction.price_tokens))
+                        .push_bind(std::mem::take(&mut auction.price_values)),
+                    AuctionColumn::SurplusCapturingJitOrderOwners => row.push_bind(std::mem::take(
+                        &mut auction.surplus_capturing_jit_order_owners,
+                    )),
+                };
+            }
+        });
+        match overwrite && !columns.is_empty() {
+            true => {
+                query.push(" ON CONFLICT (id) DO UPDATE SET ");
+                let mut set = query.separated(", ");
+                for name in columns.iter().flat_map(AuctionColumn::names) {
+                    set.push(format_args!("{name} = EXCLUDED.{name}"));
+                }
+            }
+            false => {
+                query.push(" ON CONFLICT (id) DO NOTHING");
+            }
+        }
+        query.push(" RETURNING id, to_jsonb(ca) AS new_values), ");
+        query.push(crate::database_audit::audited(
+            "competition_auctions",
+            &["id"],
+            "written LEFT JOIN prev USING (id)",
+        ));
+        query.push(" SELECT COUNT(*) FROM written;");
+
+        let written: i64 = query
+            .build_query_scalar()
+            .fetch_one(&mut *ex)
+            .await
+            .context("insert auctions")?;
+        rows += written as u64;
+    }
+
+    Ok(rows)
 }
 
-pub fn big_decimal_to_u256(big_decimal: &BigDecimal) -> Option<U256> {
-    if !big_decimal.is_integer() {
-        return None;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonicalize_prices() {
+        let auction = |prices: &[(u8, u32)]| Auction {
+            price_tokens: prices.iter().map(|(t, _)| ByteArray([*t; 20])).collect(),
+            price_values: prices.iter().map(|(_, v)| BigDecimal::from(*v)).collect(),
+            ..Default::default()
+        };
+
+        let mut unsorted = auction(&[(3, 30), (1, 10), (2, 20), (1, 10)]);
+        unsorted.canonicalize_prices().unwrap();
+        let sorted = auction(&[(1, 10), (2, 20), (3, 30)]);
+        assert_eq!(unsorted.price_tokens, sorted.price_tokens);
+        assert_eq!(unsorted.price_values, sorted.price_values);
+
+        assert!(auction(&[(1, 10), (1, 11)]).canonicalize_prices().is_err());
+        let mut mismatch = auction(&[(1, 10)]);
+        mismatch.price_values.clear();
+        assert!(mismatch.canonicalize_prices().is_err());
+    }
+
+    #[test]
+    fn differing_fields() {
+        let auction = Auction {
+            id: 1,
+            block: 2,
+            price_tokens: vec![ByteArray([1; 20])],
+            price_values: vec![BigDecimal::from(10)],
+            ..Default::default()
+        };
+        assert!(auction.differing_fields(&auction).is_empty());
+        let other = Auction {
+            block: 3,
+            price_values: vec![BigDecimal::from(11)],
+            ..auction.clone()
+        };
+        assert_eq!(auction.differing_fields(&other), ["block", "price_values"]);
+    }
+
+    #[test]
+    fn parse_auction_column() {
+        for column in AuctionColumn::ALL {
+            assert_eq!(column.as_str().parse::<AuctionColumn>().unwrap(), column);
+        }
+        assert!("price_tokens".parse::<AuctionColumn>().is_err());
     }
-    let big_int = big_decimal.to_bigint()?;
-    big_int_to_u256(&big_int).ok()
 }
@@ -0,0 +1,31 @@
+use sqlx::PgConnection;
+
+/// Size and bloat of a table according to the statistics Postgres keeps,
+/// which lag behind the actual table a little.
+#[derive(Clone, Debug, Default, Eq, PartialEq, sqlx::FromRow)]
+pub struct TableStats {
+    pub table: String,
+    /// Size including indexes and TOAST.
+    pub total_bytes: i64,
+    pub live_tuples: i64,
+    /// Tuples left behind by updates and deletes until the next vacuum.
+    pub dead_tuples: i64,
+}
+
+/// Fetches the statistics of those of `tables` that exist, ordered by name.
+pub async fn fetch_stats(
+    ex: &mut PgConnection,
+    tables: &[&str],
+) -> Result<Vec<TableStats>, sqlx::Error> {
+    const QUERY: &str = r#"
+        SELECT
+            relname::text AS table,
+            pg_total_relation_size(relid) AS total_bytes,
+            n_live_tup AS live_tuples,
+            n_dead_tup AS dead_tuples
+        FROM pg_stat_user_tables
+        WHERE relname = ANY($1)
+        ORDER BY relname ASC;"#;
+
+    sqlx::query_as(QUERY).bind(tables).fetch_all(ex).await
+}
@@ -0,0 +1,191 @@
+//! Events about the auctions a migration processed, published to a Kafka
+//! topic or a NATS subject once their batch is committed, so downstream
+//! consumers can react as historic rows become available.
+
+use {
+    anyhow::{anyhow, Result},
+    futures::future::BoxFuture,
+    serde::Serialize,
+    std::sync::OnceLock,
+    tokio::sync::mpsc::{self, UnboundedSender},
+    tracing::warn,
+};
+
+/// What a migration did with an auction.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// The auction was migrated, possibly without some order executions.
+    Processed,
+    /// The auction was skipped entirely.
+    Skipped,
+}
+
+/// Published as JSON, on Kafka keyed by the auction id.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Event {
+    pub auction_id: i64,
+    pub migration: String,
+    pub action: Action,
+}
+
+static SENDER: OnceLock<UnboundedSender<Event>> = OnceLock::new();
+
+/// Queues `events` for the publisher, if one was started.
+pub fn publish(events: impl IntoIterator<Item = Event>) {
+    if let Some(sender) = SENDER.get() {
+        for event in events {
+            // the publisher only stops with the process
+            let _ = sender.send(event);
+        }
+    }
+}
+
+/// Where events are published. Kafka and NATS are behind the `events`
+/// feature, see `connect`.
+pub trait Publisher: Send + Sync + 'static {
+    fn publish<'a>(&'a self, events: &'a [Event]) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Publishes the events queued with [`publish`] from then on with
+/// `publisher` in the background. Events that fail to be published are
+/// logged and dropped.
+pub fn start(publisher: Box<dyn Publisher>) -> Result<()> {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    SENDER
+        .set(sender)
+        .map_err(|_| anyhow!("events are already published"))?;
+    tokio::spawn(async move {
+        while let Some(event) = receiver.recv().await {
+            // send everything queued in the meantime together
+            let mut events = vec![event];
+            while let Ok(event) = receiver.try_recv() {
+                events.push(event);
+            }
+            if let Err(err) = publisher.publish(&events).await {
+                warn!("failed to publish {} events: {:?}", events.len(), err);
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(feature = "events")]
+pub use brokers::connect;
+
+#[cfg(feature = "events")]
+mod brokers {
+    use {
+        super::{Event, Publisher},
+        anyhow::{bail, ensure, Context, Result},
+        futures::{future::BoxFuture, FutureExt},
+        rskafka::{
+            client::{
+                partition::{Compression, PartitionClient, UnknownTopicHandling},
+                ClientBuilder,
+            },
+            record::Record,
+        },
+        url::Url,
+    };
+
+    /// Connects to `url`, like `kafka://broker:9092/topic` or
+    /// `nats://server:4222/subject`.
+    pub async fn connect(url: &Url) -> Result<Box<dyn Publisher>> {
+        Ok(Box::new(Broker::connect(url).await?))
+    }
+
+    enum Broker {
+        Nats {
+            client: async_nats::Client,
+            subject: String,
+        },
+        /// Publishes to the first partition only, so consumers see the events
+        /// in order.
+        Kafka(PartitionClient),
+    }
+
+    impl Broker {
+        async fn connect(url: &Url) -> Result<Self> {
+            let target = url.path().trim_start_matches('/').to_string();
+            ensure!(!target.is_empty(), "{url} names no topic or subject");
+            let host = url.host_str().context("events url without host")?;
+            Ok(match url.scheme() {
+                "nats" => Self::Nats {
+                    client: async_nats::connect(format!("{host}:{}", url.port().unwrap_or(4222)))
+                        .await
+                        .context("connect to NATS")?,
+                    subject: target,
+                },
+                "kafka" => {
+                    let client =
+                        ClientBuilder::new(vec![format!("{host}:{}", url.port().unwrap_or(9092))])
+                            .build()
+                            .await
+                            .context("connect to Kafka")?;
+                    Self::Kafka(
+                        client
+                            .partition_client(target, 0, UnknownTopicHandling::Retry)
+                            .await
+                            .context("Kafka topic")?,
+                    )
+                }
+                scheme => bail!("unsupported events url scheme {scheme}"),
+            })
+        }
+
+        async fn send(&self, events: &[Event]) -> Result<()> {
+            match self {
+                Self::Nats { client, subject } => {
+                    for event in events {
+                        client
+                            .publish(subject.clone(), serde_json::to_vec(event)?.into())
+                            .await?;
+                    }
+                    client.flush().await?;
+                }
+                Self::Kafka(partition) => {
+                    let records = events
+                        .iter()
+                        .map(|event| {
+                            Ok(Record {
+                                key: Some(event.auction_id.to_string().into_bytes()),
+                                value: Some(serde_json::to_vec(event)?),
+                                headers: Default::default(),
+                                timestamp: rskafka::chrono::Utc::now(),
+                            })
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    partition
+                        .produce(records, Compression::NoCompression)
+                        .await?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl Publisher for Broker {
+        fn publish<'a>(&'a self, events: &'a [Event]) -> BoxFuture<'a, Result<()>> {
+            self.send(events).boxed()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let event = Event {
+            auction_id: 7,
+            migration: "populate_historic_auctions".to_string(),
+            action: Action::Skipped,
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"auction_id":7,"migration":"populate_historic_auctions","action":"skipped"}"#
+        );
+    }
+}
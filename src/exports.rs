@@ -0,0 +1,141 @@
+//! Migrated auctions as JSON Lines, one object per `competition_auctions`
+//! row, for loading into other tools without access to the database.
+//! Addresses and order uids are 0x prefixed hex strings and prices decimal
+//! strings, so no precision is lost. `apply-from-file` reads them back.
+
+use {
+    crate::database_solver_competition::{Address, Auction, ByteArray},
+    anyhow::{Context, Result},
+    serde::{Deserialize, Serialize},
+    std::io::{BufRead, Write},
+};
+
+/// A line of an export.
+#[derive(Debug, Serialize, Deserialize)]
+struct Line {
+    id: i64,
+    block: i64,
+    deadline: i64,
+    order_uids: Vec<String>,
+    price_tokens: Vec<String>,
+    price_values: Vec<String>,
+    surplus_capturing_jit_order_owners: Vec<String>,
+}
+
+impl From<&Auction> for Line {
+    fn from(auction: &Auction) -> Self {
+        let hex = |values: &[Address]| -> Vec<String> {
+            values.iter().map(|value| format!("{value:?}")).collect()
+        };
+        Self {
+            id: auction.id,
+            block: auction.block,
+            deadline: auction.deadline,
+            order_uids: auction
+                .order_uids
+                .iter()
+                .map(|uid| format!("{uid:?}"))
+                .collect(),
+            price_tokens: hex(&auction.price_tokens),
+            price_values: auction
+                .price_values
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            surplus_capturing_jit_order_owners: hex(&auction.surplus_capturing_jit_order_owners),
+        }
+    }
+}
+
+impl TryFrom<Line> for Auction {
+    type Error = anyhow::Error;
+
+    fn try_from(line: Line) -> Result<Self> {
+        Ok(Self {
+            id: line.id,
+            block: line.block,
+            deadline: line.deadline,
+            order_uids: bytes_vec(&line.order_uids).context("order_uids")?,
+            price_tokens: bytes_vec(&line.price_tokens).context("price_tokens")?,
+            price_values: line
+                .price_values
+                .iter()
+                .map(|value| value.parse())
+                .collect::<Result<_, _>>()
+                .context("price_values")?,
+            surplus_capturing_jit_order_owners: bytes_vec(&line.surplus_capturing_jit_order_owners)
+                .context("surplus_capturing_jit_order_owners")?,
+        })
+    }
+}
+
+fn bytes<const N: usize>(text: &str) -> Result<ByteArray<N>> {
+    let mut bytes = [0; N];
+    hex::decode_to_slice(text.strip_prefix("0x").unwrap_or(text), &mut bytes)
+        .with_context(|| format!("invalid bytes {text:?}"))?;
+    Ok(ByteArray(bytes))
+}
+
+fn bytes_vec<const N: usize>(values: &[String]) -> Result<Vec<ByteArray<N>>> {
+    values.iter().map(|value| bytes(value)).collect()
+}
+
+/// Writes a line per auction to `writer`.
+pub fn write(auctions: &[Auction], mut writer: impl Write) -> Result<()> {
+    for auction in auctions {
+        serde_json::to_writer(&mut writer, &Line::from(auction)).context("write auction")?;
+        writer.write_all(b"\n").context("write auction")?;
+    }
+    Ok(())
+}
+
+/// Reads the auctions of an export line by line.
+pub fn read(reader: impl BufRead) -> impl Iterator<Item = Result<Auction>> {
+    reader
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+        .map(|(number, line)| {
+            let parse = || -> Result<Auction> {
+                let line: Line = serde_json::from_str(&line?)?;
+                Auction::try_from(line)
+            };
+            parse().with_context(|| format!("line {}", number + 1))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, bigdecimal::BigDecimal, std::str::FromStr};
+
+    #[test]
+    fn lines() {
+        let auction = Auction {
+            id: 1,
+            block: 2,
+            deadline: 3,
+            order_uids: vec![ByteArray([0xab; 56])],
+            price_tokens: vec![ByteArray([1; 20])],
+            price_values: vec![BigDecimal::from_str("1000000000000000000000000").unwrap()],
+            surplus_capturing_jit_order_owners: vec![],
+        };
+        let mut output = Vec::new();
+        write(&[auction.clone(), auction.clone()], &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            format!(
+                r#"{{"id":1,"block":2,"deadline":3,"order_uids":["0x{}"],"price_tokens":["0x{}"],"price_values":["1000000000000000000000000"],"surplus_capturing_jit_order_owners":[]}}"#,
+                "ab".repeat(56),
+                "01".repeat(20)
+            )
+        );
+
+        let decoded: Vec<_> = read(output.as_bytes()).collect::<Result<_>>().unwrap();
+        assert_eq!(decoded, vec![auction.clone(), auction]);
+        assert!(read(r#"{"id":1}"#.as_bytes()).next().unwrap().is_err());
+    }
+}
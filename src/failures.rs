@@ -0,0 +1,198 @@
+//! Failures of a migration run flattened into one row per auction and
+//! reason, for triage outside the tool, e.g. in a spreadsheet.
+
+use {
+    crate::{database_failures::FailureContext, report::Report, report::SkipReason},
+    anyhow::{bail, Context, Result},
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeMap, io, str::FromStr},
+};
+
+/// Formats failures can be exported in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Failure {
+    pub auction_id: i64,
+    pub migration: String,
+    pub reason: SkipReason,
+    /// The panic, the mismatched fields or the likely causes of missing
+    /// orders, as far as the report knows.
+    pub error: String,
+    pub json_bytes: Option<i32>,
+    pub order_kinds: Option<String>,
+    /// Left empty, for whoever triages the failure to fill in with a
+    /// [`Resolution`].
+    pub resolution: String,
+}
+
+/// What to do with a triaged failure.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Resolution {
+    /// Written as `skip`: never retry the auction, a tombstone.
+    Skip,
+    /// Written as `retry` followed by the global arguments to retry with,
+    /// like `retry --auction-timeout-secs 600`.
+    Retry(Vec<String>),
+}
+
+impl FromStr for Resolution {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut words = s.split_whitespace();
+        match words.next() {
+            Some("skip") if words.next().is_none() => Ok(Self::Skip),
+            Some("retry") => Ok(Self::Retry(words.map(str::to_string).collect())),
+            _ => bail!("unknown resolution {s:?}, expected skip or retry [arguments]"),
+        }
+    }
+}
+
+/// Reads the failures written by [`write`] whose resolution column is filled
+/// in, with their resolutions.
+pub fn read_resolved(reader: impl io::Read) -> Result<Vec<(Failure, Resolution)>> {
+    let mut resolved = Vec::new();
+    for (line, failure) in csv::Reader::from_reader(reader)
+        .into_deserialize::<Failure>()
+        .enumerate()
+    {
+        // the header is line 1
+        let failure = failure.with_context(|| format!("read line {}", line + 2))?;
+        if failure.resolution.trim().is_empty() {
+            continue;
+        }
+        let resolution = failure
+            .resolution
+            .parse()
+            .with_context(|| format!("line {}", line + 2))?;
+        resolved.push((failure, resolution));
+    }
+    Ok(resolved)
+}
+
+/// The failures of `report`, ordered by auction, with the context fetched
+/// for them.
+pub fn from_report(report: &Report, contexts: &[FailureContext]) -> Vec<Failure> {
+    let contexts: BTreeMap<_, _> = contexts
+        .iter()
+        .map(|context| (context.auction_id, context))
+        .collect();
+    let mut failures: Vec<_> = report
+        .skipped
+        .iter()
+        .flat_map(|(reason, auctions)| auctions.iter().map(move |id| (*id, *reason)))
+        .map(|(auction_id, reason)| {
+            let context = contexts.get(&auction_id);
+            Failure {
+                auction_id,
+                migration: report.migration.clone(),
+                reason,
+                error: error(report, reason, auction_id),
+                json_bytes: context.and_then(|context| context.json_bytes),
+                order_kinds: context.and_then(|context| context.order_kinds.clone()),
+                resolution: String::new(),
+            }
+        })
+        .collect();
+    failures.sort_by_key(|failure| (failure.auction_id, failure.reason));
+    failures
+}
+
+fn error(report: &Report, reason: SkipReason, auction_id: i64) -> String {
+    match reason {
+        SkipReason::Panic => report.panics.get(&auction_id).cloned().unwrap_or_default(),
+        SkipReason::OrderNotFound => report
+            .order_not_found
+            .iter()
+            .filter(|(_, auctions)| auctions.contains(&auction_id))
+            .map(|(class, _)| class.as_str())
+            .collect::<Vec<_>>()
+            .join(","),
+        _ => report
+            .errors
+            .get(&auction_id)
+            .cloned()
+            .or_else(|| {
+                report
+                    .mismatches
+                    .get(&auction_id)
+                    .map(|fields| fields.join(","))
+            })
+            .unwrap_or_default(),
+    }
+}
+
+pub fn write(failures: &[Failure], format: ExportFormat, writer: impl io::Write) -> Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            for failure in failures {
+                writer.serialize(failure).context("write failure")?;
+            }
+            writer.flush().context("flush failures")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv() {
+        let mut report = Report::new("convert_executed_fee");
+        report.skipped.insert(SkipReason::Panic, [2].into());
+        report.skipped.insert(SkipReason::NullJson, [1].into());
+        report.panics.insert(2, "boom, \"twice\"".to_string());
+        let contexts = [FailureContext {
+            auction_id: 2,
+            json_bytes: Some(100),
+            order_kinds: Some("buy,sell".to_string()),
+        }];
+
+        let mut csv = Vec::new();
+        write(
+            &from_report(&report, &contexts),
+            ExportFormat::Csv,
+            &mut csv,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "auction_id,migration,reason,error,json_bytes,order_kinds,resolution\n\
+             1,convert_executed_fee,null_json,,,,\n\
+             2,convert_executed_fee,panic,\"boom, \"\"twice\"\"\",100,\"buy,sell\",\n"
+        );
+    }
+
+    #[test]
+    fn read_resolutions() {
+        let csv = "auction_id,migration,reason,error,json_bytes,order_kinds,resolution\n\
+                   1,convert_executed_fee,null_json,,,,skip\n\
+                   2,convert_executed_fee,panic,boom,100,sell,\n\
+                   3,convert_executed_fee,timeout,,100,sell,retry --auction-timeout-secs 60\n";
+        let resolutions: Vec<_> = read_resolved(csv.as_bytes())
+            .unwrap()
+            .into_iter()
+            .map(|(failure, resolution)| (failure.auction_id, resolution))
+            .collect();
+        assert_eq!(
+            resolutions,
+            [
+                (1, Resolution::Skip),
+                (
+                    3,
+                    Resolution::Retry(vec!["--auction-timeout-secs".to_string(), "60".to_string()])
+                ),
+            ]
+        );
+
+        let csv = "auction_id,migration,reason,error,json_bytes,order_kinds,resolution\n\
+                   1,convert_executed_fee,null_json,,,,ignore\n";
+        assert!(read_resolved(csv.as_bytes()).is_err());
+    }
+}
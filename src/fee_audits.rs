@@ -0,0 +1,180 @@
+//! CSV audit trail of the fees `convert-fees` changes, so the rewrite can be
+//! verified offline. Each line is an order execution with its fee and fee
+//! token before and after the change, and the rate the fee was converted with,
+//! 1 if only the missing fee token was filled in.
+//!
+//! Lines are added once the batch they belong to is committed, to a file that
+//! is appended to, so resumed runs continue the trail of the crashed one. Dry
+//! runs and shadow writes add the changes they would make, so a dry run
+//! against a replica computes a file `apply-from-file` applies later.
+
+use {
+    crate::{
+        conversions::u256_to_big_decimal, database_order_executions::OrderExecution, prices::Prices,
+    },
+    anyhow::{Context, Result},
+    bigdecimal::BigDecimal,
+    primitive_types::H160,
+    serde::{Deserialize, Serialize},
+    std::{
+        fs::{File, OpenOptions},
+        io,
+        path::Path,
+    },
+};
+
+/// Decimals of the conversion rate.
+const RATE_SCALE: i64 = 18;
+
+/// A line of the audit trail.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FeeChange {
+    pub order_uid: String,
+    pub auction_id: i64,
+    pub old_fee: String,
+    /// Empty if the fee token was NULL.
+    pub old_token: String,
+    pub new_fee: String,
+    pub new_token: String,
+    pub conversion_rate: String,
+}
+
+impl FeeChange {
+    /// The change from `old` to `new`, converted with `rate`.
+    pub fn new(old: &OrderExecution, new: &OrderExecution, rate: &BigDecimal) -> Self {
+        let token = |token: Option<_>| token.map(|token| format!("{token:?}")).unwrap_or_default();
+        Self {
+            order_uid: format!("{:?}", old.order_uid),
+            auction_id: old.auction_id,
+            old_fee: old.executed_fee.to_string(),
+            old_token: token(old.executed_fee_token),
+            new_fee: new.executed_fee.to_string(),
+            new_token: token(new.executed_fee_token),
+            conversion_rate: rate.to_string(),
+        }
+    }
+
+    /// The order execution before and after the change.
+    pub fn executions(&self) -> Result<(OrderExecution, OrderExecution)> {
+        let token = |token: &str| -> Result<_> {
+            match token {
+                "" => Ok(None),
+                token => Ok(Some(token.parse().context("invalid token")?)),
+            }
+        };
+        let old = OrderExecution {
+            order_uid: self.order_uid.parse().context("invalid order_uid")?,
+            auction_id: self.auction_id,
+            executed_fee: self.old_fee.parse().context("invalid old_fee")?,
+            executed_fee_token: token(&self.old_token)?,
+        };
+        let new = OrderExecution {
+            executed_fee: self.new_fee.parse().context("invalid new_fee")?,
+            executed_fee_token: token(&self.new_token)?,
+            ..old.clone()
+        };
+        Ok((old, new))
+    }
+}
+
+/// Reads the changes of an audit trail.
+pub fn read(reader: impl io::Read) -> impl Iterator<Item = Result<FeeChange>> {
+    csv::Reader::from_reader(reader)
+        .into_deserialize()
+        .enumerate()
+        // the header is line 1
+        .map(|(line, change)| change.with_context(|| format!("line {}", line + 2)))
+}
+
+/// How many units of `to` a unit of `from` converts into with `prices`,
+/// `None` if a price is missing.
+pub fn rate(prices: &Prices, from: H160, to: H160) -> Option<BigDecimal> {
+    let from = u256_to_big_decimal(&prices.get(from)?);
+    let to = u256_to_big_decimal(&prices.get(to)?);
+    Some((from / to).with_scale(RATE_SCALE))
+}
+
+/// The audit trail file.
+pub struct FeeAudit {
+    writer: csv::Writer<File>,
+}
+
+impl std::fmt::Debug for FeeAudit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FeeAudit").finish_non_exhaustive()
+    }
+}
+
+impl FeeAudit {
+    /// Opens the file at `path` to append to, writing the header if it is
+    /// new or empty.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .context("open fee audit file")?;
+        let empty = file.metadata().context("fee audit file metadata")?.len() == 0;
+        Ok(Self {
+            writer: csv::WriterBuilder::new()
+                .has_headers(empty)
+                .from_writer(file),
+        })
+    }
+
+    /// Appends `changes` and flushes them to the file.
+    pub fn write(&mut self, changes: &[FeeChange]) -> Result<()> {
+        for change in changes {
+            self.writer
+                .serialize(change)
+                .context("write fee audit line")?;
+        }
+        self.writer.flush().context("flush fee audit file")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::database_orders::ByteArray,
+        primitive_types::U256,
+        std::{collections::BTreeMap, str::FromStr},
+    };
+
+    #[test]
+    fn change() {
+        let (sell, buy) = (H160([1; 20]), H160([2; 20]));
+        let prices = Prices::from(BTreeMap::from([
+            (sell, U256::from(3)),
+            (buy, U256::from(2)),
+        ]));
+        let converted = rate(&prices, sell, buy).unwrap();
+        assert_eq!(converted, BigDecimal::from_str("1.5").unwrap());
+        assert!(rate(&prices, sell, H160([3; 20])).is_none());
+
+        let old = OrderExecution {
+            order_uid: ByteArray([4; 56]),
+            auction_id: 5,
+            executed_fee: BigDecimal::from(10),
+            executed_fee_token: None,
+        };
+        let new = OrderExecution {
+            executed_fee: BigDecimal::from(15),
+            executed_fee_token: Some(ByteArray(buy.0)),
+            ..old.clone()
+        };
+        let change = FeeChange::new(&old, &new, &converted);
+        assert_eq!(change.old_token, "");
+        assert_eq!(change.new_token, format!("0x{}", hex::encode([2; 20])));
+        assert_eq!(change.new_fee, "15");
+        assert_eq!(change.executions().unwrap(), (old, new));
+
+        let mut file = Vec::new();
+        let mut writer = csv::Writer::from_writer(&mut file);
+        writer.serialize(&change).unwrap();
+        drop(writer);
+        let changes: Vec<_> = read(&file[..]).collect::<Result<_>>().unwrap();
+        assert_eq!(changes, vec![change]);
+    }
+}
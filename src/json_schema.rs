@@ -0,0 +1,91 @@
+//! JSON Schema of the solver competition JSON written by the legacy
+//! `solver_competitions` writer. Validating stored blobs against it detects
+//! schema drift before it breaks a backfill.
+
+use {
+    crate::solver_competition_api::{SolverCompetitionAPI, SolverCompetitionDB},
+    anyhow::{anyhow, Result},
+    jsonschema::JSONSchema,
+    schemars::{schema::RootSchema, schema_for},
+    serde_json::Value,
+};
+
+/// Schema of the JSON stored in the `solver_competitions` table.
+pub fn solver_competition_db() -> RootSchema {
+    schema_for!(SolverCompetitionDB)
+}
+
+/// Schema of the JSON returned by the `/solver_competition` endpoint.
+pub fn solver_competition_api() -> RootSchema {
+    schema_for!(SolverCompetitionAPI)
+}
+
+pub struct Validator(JSONSchema);
+
+impl Validator {
+    /// Validator for the JSON stored in the `solver_competitions` table.
+    pub fn solver_competition_db() -> Result<Self> {
+        let schema = serde_json::to_value(solver_competition_db())?;
+        let schema =
+            JSONSchema::compile(&schema).map_err(|err| anyhow!("invalid schema: {err}"))?;
+        Ok(Self(schema))
+    }
+
+    /// Returns a description of every schema violation, empty if the
+    /// instance is valid.
+    pub fn validate(&self, instance: &Value) -> Vec<String> {
+        match self.0.validate(instance) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors
+                .map(|err| format!("{}: {}", err.instance_path, err))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, serde_json::json};
+
+    #[test]
+    fn validates_stored_json() {
+        let validator = Validator::solver_competition_db().unwrap();
+        let valid = json!({
+            "auctionStartBlock": 17000000,
+            "competitionSimulationBlock": 17000001,
+            "auction": {
+                "orders": [format!("0x{}", "11".repeat(56))],
+                "prices": {
+                    "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2": "1000000000000000000",
+                },
+            },
+            "solutions": [{
+                "solver": "quasimodo",
+                "solverAddress": "0x0000000000000000000000000000000000000001",
+                "score": "100",
+                "ranking": 1,
+                "clearingPrices": {
+                    "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2": "1",
+                },
+                "orders": [{
+                    "id": format!("0x{}", "11".repeat(56)),
+                    "sellAmount": "10",
+                    "buyAmount": "20",
+                }],
+            }],
+        });
+        assert_eq!(validator.validate(&valid), Vec::<String>::new());
+        serde_json::from_value::<SolverCompetitionDB>(valid).unwrap();
+
+        let invalid = json!({
+            "auctionStartBlock": "17000000",
+            "competitionSimulationBlock": 17000001,
+            "auction": {
+                "orders": ["0x11"],
+                "prices": {},
+            },
+            "solutions": [],
+        });
+        assert_eq!(validator.validate(&invalid).len(), 2);
+    }
+}
@@ -1,8 +1,39 @@
+pub mod anomalies;
+pub mod archives;
 pub mod arguments;
+pub mod clock;
+pub mod conversions;
 pub mod database;
+pub mod database_auction_status;
+pub mod database_audit;
+pub mod database_failures;
 pub mod database_order_executions;
 pub mod database_orders;
+pub mod database_privileges;
+pub mod database_proposed_solutions;
+pub mod database_runs;
+pub mod database_schema;
+pub mod database_settlements;
 pub mod database_solver_competition;
+pub mod database_tables;
+pub mod events;
+pub mod exports;
+pub mod failures;
+pub mod fee_audits;
+pub mod json_schema;
+pub mod metrics;
+pub mod numbers;
+pub mod order_snapshots;
+#[cfg(feature = "parquet")]
+pub mod parquet_export;
+pub mod prices;
+pub mod profiles;
+pub mod report;
 pub mod run;
 pub mod serialization;
+pub mod sinks;
 pub mod solver_competition_api;
+pub mod sources;
+pub mod throughput;
+#[cfg(feature = "object-storage")]
+pub mod uploads;
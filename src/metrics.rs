@@ -0,0 +1,207 @@
+//! Prometheus metrics of the running migrations, served in the text
+//! exposition format on `--metrics-address`.
+
+use {
+    anyhow::Result,
+    prometheus::{
+        exponential_buckets, CounterVec, Encoder, HistogramOpts, HistogramVec, IntCounterVec,
+        IntGaugeVec, Opts, Registry, TextEncoder,
+    },
+    std::{io, net::SocketAddr, sync::OnceLock, time::Duration},
+    tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    },
+    tracing::info,
+};
+
+pub struct Metrics {
+    registry: Registry,
+    /// Auctions or order executions skipped, by migration and reason.
+    pub skipped: IntCounterVec,
+    /// Order executions converted, by migration and the table the order was
+    /// found in.
+    pub converted: IntCounterVec,
+    /// The auction id a migration is currently processing.
+    pub current_auction_id: IntGaugeVec,
+    /// Size of the solver competition JSON of processed auctions.
+    pub auction_json_bytes: HistogramVec,
+    /// Time to process one auction, by JSON size bucket.
+    pub auction_processing_seconds: HistogramVec,
+    /// Time spent, by migration and stage, see [`crate::throughput::Stage`].
+    pub stage_seconds: CounterVec,
+    /// Size of the tables written by the migrations, including indexes.
+    pub table_bytes: IntGaugeVec,
+    /// Dead tuples in the tables written by the migrations, which a vacuum
+    /// would reclaim.
+    pub table_dead_tuples: IntGaugeVec,
+    /// Runs stopped because their batches modified an unexpected share of the
+    /// rows they scanned, by migration, see [`crate::anomalies`].
+    pub write_ratio_anomalies: IntCounterVec,
+}
+
+/// Upper bounds of the JSON size buckets used to label processing times.
+const SIZE_BUCKETS: &[(usize, &str)] = &[
+    (10_000, "10KB"),
+    (100_000, "100KB"),
+    (1_000_000, "1MB"),
+    (10_000_000, "10MB"),
+];
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("data_migration".to_string()), None).unwrap();
+        let skipped = IntCounterVec::new(
+            Opts::new("skipped", "Skipped auctions or order executions"),
+            &["migration", "reason"],
+        )
+        .unwrap();
+        let converted = IntCounterVec::new(
+            Opts::new("converted", "Converted order executions"),
+            &["migration", "source"],
+        )
+        .unwrap();
+        let current_auction_id = IntGaugeVec::new(
+            Opts::new("current_auction_id", "Auction id currently being processed"),
+            &["migration"],
+        )
+        .unwrap();
+        let auction_json_bytes = HistogramVec::new(
+            HistogramOpts::new("auction_json_bytes", "Size of the auction JSON in bytes")
+                .buckets(exponential_buckets(1_000., 4., 10).unwrap()),
+            &["migration"],
+        )
+        .unwrap();
+        let auction_processing_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "auction_processing_seconds",
+                "Time to process one auction by JSON size",
+            )
+            .buckets(exponential_buckets(0.001, 4., 10).unwrap()),
+            &["migration", "size"],
+        )
+        .unwrap();
+        let stage_seconds = CounterVec::new(
+            Opts::new("stage_seconds", "Time spent per stage of processing"),
+            &["migration", "stage"],
+        )
+        .unwrap();
+        registry.register(Box::new(skipped.clone())).unwrap();
+        registry.register(Box::new(converted.clone())).unwrap();
+        registry
+            .register(Box::new(current_auction_id.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(auction_json_bytes.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(auction_processing_seconds.clone()))
+            .unwrap();
+        let table_bytes = IntGaugeVec::new(
+            Opts::new("table_bytes", "Size of a written table in bytes"),
+            &["table"],
+        )
+        .unwrap();
+        let table_dead_tuples = IntGaugeVec::new(
+            Opts::new("table_dead_tuples", "Dead tuples of a written table"),
+            &["table"],
+        )
+        .unwrap();
+        let write_ratio_anomalies = IntCounterVec::new(
+            Opts::new(
+                "write_ratio_anomalies",
+                "Runs stopped for an unexpected share of modified rows",
+            ),
+            &["migration"],
+        )
+        .unwrap();
+        registry.register(Box::new(stage_seconds.clone())).unwrap();
+        registry.register(Box::new(table_bytes.clone())).unwrap();
+        registry
+            .register(Box::new(table_dead_tuples.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(write_ratio_anomalies.clone()))
+            .unwrap();
+        Self {
+            registry,
+            skipped,
+            converted,
+            current_auction_id,
+            auction_json_bytes,
+            auction_processing_seconds,
+            stage_seconds,
+            table_bytes,
+            table_dead_tuples,
+            write_ratio_anomalies,
+        }
+    }
+
+    /// Records the JSON size and processing time of one auction.
+    pub fn observe_auction(&self, migration: &str, json_bytes: usize, elapsed: Duration) {
+        let size = SIZE_BUCKETS
+            .iter()
+            .find(|(limit, _)| json_bytes < *limit)
+            .map_or("larger", |(_, label)| label);
+        self.auction_json_bytes
+            .with_label_values(&[migration])
+            .observe(json_bytes as f64);
+        self.auction_processing_seconds
+            .with_label_values(&[migration, size])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .unwrap();
+        buffer
+    }
+}
+
+/// Length of the serialized JSON without allocating it.
+pub fn json_size(value: &serde_json::Value) -> usize {
+    struct Counter(usize);
+    impl io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    // writing to the counter can't fail
+    let _ = serde_json::to_writer(&mut counter, value);
+    counter.0
+}
+
+pub fn get() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::new)
+}
+
+/// Serves the metrics to every HTTP request regardless of path.
+pub async fn serve(address: SocketAddr) -> Result<()> {
+    let listener = TcpListener::bind(address).await?;
+    info!("serving metrics on {address}");
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        tokio::spawn(async move {
+            // the request itself is irrelevant
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request).await;
+            let body = get().encode();
+            let header = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = stream.write_all(header.as_bytes()).await;
+            let _ = stream.write_all(&body).await;
+        });
+    }
+}
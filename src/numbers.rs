@@ -0,0 +1,109 @@
+//! Formatting of counts and token amounts in logs, with thousands separators
+//! or, with `--scientific-numbers`, in scientific notation, so that the
+//! output of runs over millions of auctions stays readable.
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+static SCIENTIFIC: AtomicBool = AtomicBool::new(false);
+
+/// Formats large numbers in scientific notation from now on.
+pub fn set_scientific(scientific: bool) {
+    SCIENTIFIC.store(scientific, Ordering::Relaxed);
+}
+
+/// A number formatted for logs, see [`big`].
+pub struct Big(String);
+
+/// Formats the decimal `value`, like a count, a `U256` or a `BigDecimal`,
+/// with thousands separators or in scientific notation.
+pub fn big(value: impl fmt::Display) -> Big {
+    Big(value.to_string())
+}
+
+impl fmt::Display for Big {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match SCIENTIFIC.load(Ordering::Relaxed) {
+            true => f.write_str(&scientific(&self.0)),
+            false => f.write_str(&thousands(&self.0)),
+        }
+    }
+}
+
+/// Inserts a `,` between every three digits of the integer part of the
+/// decimal `number`. Anything else is returned as it is.
+pub fn thousands(number: &str) -> String {
+    let (sign, digits) = split_sign(number);
+    let (integer, fraction) = digits
+        .split_once('.')
+        .map_or((digits, None), |(integer, fraction)| {
+            (integer, Some(fraction))
+        });
+    if integer.is_empty() || !integer.bytes().all(|byte| byte.is_ascii_digit()) {
+        return number.to_string();
+    }
+    let mut formatted = String::from(sign);
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        formatted.push('.');
+        formatted.push_str(fraction);
+    }
+    formatted
+}
+
+/// Writes the decimal `number` like `1.23e18`, truncated to three
+/// significant digits. Numbers below 1000 and anything else are returned as
+/// they are.
+pub fn scientific(number: &str) -> String {
+    let (sign, digits) = split_sign(number);
+    let integer = digits
+        .split_once('.')
+        .map_or(digits, |(integer, _)| integer);
+    if integer.len() < 4 || !integer.bytes().all(|byte| byte.is_ascii_digit()) {
+        return number.to_string();
+    }
+    let exponent = integer.len() - 1;
+    let mantissa = integer[1..3].trim_end_matches('0');
+    match mantissa.is_empty() {
+        true => format!("{sign}{}e{exponent}", &integer[..1]),
+        false => format!("{sign}{}.{mantissa}e{exponent}", &integer[..1]),
+    }
+}
+
+fn split_sign(number: &str) -> (&str, &str) {
+    number
+        .strip_prefix('-')
+        .map_or(("", number), |digits| ("-", digits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thousands_separators() {
+        assert_eq!(thousands("0"), "0");
+        assert_eq!(thousands("999"), "999");
+        assert_eq!(thousands("1000"), "1,000");
+        assert_eq!(thousands("-1234567"), "-1,234,567");
+        assert_eq!(thousands("1234567.891"), "1,234,567.891");
+        assert_eq!(thousands("n/a"), "n/a");
+    }
+
+    #[test]
+    fn scientific_notation() {
+        assert_eq!(scientific("999"), "999");
+        assert_eq!(scientific("1000"), "1e3");
+        assert_eq!(scientific("1234567"), "1.23e6");
+        assert_eq!(scientific("1205000000000000000"), "1.2e18");
+        assert_eq!(scientific("-4560000.5"), "-4.56e6");
+        assert_eq!(scientific("n/a"), "n/a");
+    }
+}
@@ -0,0 +1,135 @@
+//! Orders as captured at a fixed time, read from a CSV file instead of the
+//! `orders` and `jit_orders` tables, so reruns of the fee conversion see the
+//! same orders even if their rows were modified since.
+//!
+//! The file has the columns `uid`, `sell_token`, `buy_token`, `kind` and
+//! `source`, the table the order is from. Postgres writes it with
+//!
+//! ```sql
+//! \copy (SELECT uid, sell_token, buy_token, kind, 'orders' AS source FROM orders
+//!     UNION ALL SELECT uid, sell_token, buy_token, kind, 'jit_orders' FROM jit_orders)
+//!     TO 'orders.csv' CSV HEADER
+//! ```
+
+use {
+    crate::database_orders::{ByteArray, Order, OrderKind, OrderSource, OrderUid},
+    anyhow::{bail, Context, Result},
+    serde::Deserialize,
+    std::{collections::HashMap, io},
+};
+
+#[derive(Deserialize)]
+struct Row {
+    uid: String,
+    sell_token: String,
+    buy_token: String,
+    kind: String,
+    source: String,
+}
+
+/// The orders of a snapshot by uid.
+#[derive(Clone, Debug, Default)]
+pub struct OrderSnapshot {
+    orders: HashMap<OrderUid, (Order, OrderSource)>,
+}
+
+impl OrderSnapshot {
+    /// Reads the CSV written as described in the module documentation. An
+    /// order in both tables is taken from `orders`, like the tables are read.
+    pub fn read(reader: impl io::Read) -> Result<Self> {
+        let mut orders = HashMap::new();
+        for (line, row) in csv::Reader::from_reader(reader)
+            .into_deserialize::<Row>()
+            .enumerate()
+        {
+            // the header is line 1
+            let parse = || -> Result<(OrderUid, Order, OrderSource)> {
+                let row = row?;
+                let source = match row.source.as_str() {
+                    "orders" => OrderSource::Orders,
+                    "jit_orders" => OrderSource::JitOrders,
+                    source => bail!("unknown source {source:?}"),
+                };
+                let kind = match row.kind.as_str() {
+                    "sell" => OrderKind::Sell,
+                    "buy" => OrderKind::Buy,
+                    kind => bail!("unknown kind {kind:?}"),
+                };
+                let order = Order {
+                    sell_token: bytes(&row.sell_token).context("sell_token")?,
+                    buy_token: bytes(&row.buy_token).context("buy_token")?,
+                    kind,
+                };
+                Ok((bytes(&row.uid).context("uid")?, order, source))
+            };
+            let (uid, order, source) = parse().with_context(|| format!("line {}", line + 2))?;
+            match orders.get(&uid) {
+                Some((_, OrderSource::Orders)) => (),
+                _ => {
+                    orders.insert(uid, (order, source));
+                }
+            }
+        }
+        Ok(Self { orders })
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    /// Like [`crate::database_orders::fetch_many`], from the snapshot.
+    pub fn fetch_many(&self, order_uids: &[OrderUid]) -> HashMap<OrderUid, (Order, OrderSource)> {
+        order_uids
+            .iter()
+            .filter_map(|uid| Some((*uid, self.orders.get(uid)?.clone())))
+            .collect()
+    }
+}
+
+/// Parses bytes as Postgres writes them to CSV, `\x` prefixed hex, or `0x`
+/// prefixed like elsewhere.
+fn bytes<const N: usize>(text: &str) -> Result<ByteArray<N>> {
+    Ok(text.strip_prefix("\\x").unwrap_or(text).parse()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read() {
+        let uid = |byte: u8| format!("\\x{}", hex::encode([byte; 56]));
+        let token = |byte: u8| format!("0x{}", hex::encode([byte; 20]));
+        let csv = format!(
+            "uid,sell_token,buy_token,kind,source\n\
+             {},{},{},sell,jit_orders\n\
+             {},{},{},buy,orders\n\
+             {},{},{},sell,jit_orders\n",
+            uid(1),
+            token(2),
+            token(3),
+            uid(1),
+            token(4),
+            token(5),
+            uid(6),
+            token(7),
+            token(8),
+        );
+        let snapshot = OrderSnapshot::read(csv.as_bytes()).unwrap();
+        assert_eq!(snapshot.len(), 2);
+
+        let orders = snapshot.fetch_many(&[ByteArray([1; 56]), ByteArray([9; 56])]);
+        assert_eq!(orders.len(), 1);
+        let (order, source) = &orders[&ByteArray([1; 56])];
+        assert_eq!(*source, OrderSource::Orders);
+        assert_eq!(order.kind, OrderKind::Buy);
+        assert_eq!(order.sell_token, ByteArray([4; 20]));
+
+        let broken = "uid,sell_token,buy_token,kind,source\n\\x01,0x02,0x03,sell,orders\n";
+        assert!(OrderSnapshot::read(broken.as_bytes()).is_err());
+    }
+}
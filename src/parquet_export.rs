@@ -0,0 +1,324 @@
+//! Parquet files with the `competition_auctions` and `order_execution` rows a
+//! run writes, for analytics in tools like DuckDB or Spark. The columns are
+//! those of the Postgres tables, with `numeric` values as decimal strings
+//! since they don't fit Parquet decimals.
+//!
+//! Rows are added as their batch is written, before it commits, so the files
+//! of a run that stopped on an error may hold rows of the batch that was
+//! rolled back.
+
+use {
+    crate::{
+        database_order_executions::OrderExecution, database_solver_competition::Auction,
+        sinks::Sink,
+    },
+    anyhow::{Context, Result},
+    parquet::{
+        data_type::{ByteArray, ByteArrayType, Int64Type},
+        file::{properties::WriterProperties, writer::SerializedFileWriter},
+        schema::parser::parse_message_type,
+    },
+    std::{fmt, fs::File, path::Path, sync::Arc},
+    tracing::error,
+};
+
+const AUCTIONS_SCHEMA: &str = "
+    message competition_auctions {
+        required int64 id;
+        required int64 block;
+        required int64 deadline;
+        repeated binary order_uids;
+        repeated binary price_tokens;
+        repeated binary price_values (UTF8);
+        repeated binary surplus_capturing_jit_order_owners;
+    }";
+
+const ORDER_EXECUTIONS_SCHEMA: &str = "
+    message order_execution {
+        required binary order_uid;
+        required int64 auction_id;
+        required binary executed_fee (UTF8);
+        optional binary executed_fee_token;
+    }";
+
+/// Rows buffered before they are written as a row group.
+const ROW_GROUP_ROWS: usize = 10_000;
+
+/// The values of a column of a row group, in the order of the schema.
+#[derive(Debug, PartialEq)]
+enum Column {
+    Int64(Vec<i64>),
+    /// Definition and repetition levels are only kept for optional and
+    /// repeated columns.
+    Bytes {
+        values: Vec<ByteArray>,
+        definition: Vec<i16>,
+        repetition: Vec<i16>,
+    },
+}
+
+impl Column {
+    fn bytes() -> Self {
+        Self::Bytes {
+            values: Vec::new(),
+            definition: Vec::new(),
+            repetition: Vec::new(),
+        }
+    }
+
+    fn push_int64(&mut self, value: i64) {
+        if let Self::Int64(values) = self {
+            values.push(value);
+        }
+    }
+
+    fn push_required(&mut self, value: impl Into<ByteArray>) {
+        if let Self::Bytes { values, .. } = self {
+            values.push(value.into());
+        }
+    }
+
+    fn push_optional(&mut self, value: Option<impl Into<ByteArray>>) {
+        if let Self::Bytes {
+            values, definition, ..
+        } = self
+        {
+            definition.push(i16::from(value.is_some()));
+            values.extend(value.map(Into::into));
+        }
+    }
+
+    /// Adds the values of a repeated field of one row. A row without values
+    /// still needs a level to tell it apart from the next row.
+    fn push_repeated<T: Into<ByteArray>>(&mut self, row: impl IntoIterator<Item = T>) {
+        if let Self::Bytes {
+            values,
+            definition,
+            repetition,
+        } = self
+        {
+            let start = values.len();
+            for (i, value) in row.into_iter().enumerate() {
+                values.push(value.into());
+                definition.push(1);
+                repetition.push(i16::from(i > 0));
+            }
+            if values.len() == start {
+                definition.push(0);
+                repetition.push(0);
+            }
+        }
+    }
+}
+
+/// The levels of a column, `None` for columns that don't keep them.
+fn levels(levels: &[i16]) -> Option<&[i16]> {
+    (!levels.is_empty()).then_some(levels)
+}
+
+/// A file and the rows not written to it yet.
+struct Table {
+    writer: Option<SerializedFileWriter<File>>,
+    columns: Vec<Column>,
+    rows: usize,
+}
+
+impl Table {
+    fn create(path: &Path, schema: &str, columns: Vec<Column>) -> Result<Self> {
+        let schema = Arc::new(parse_message_type(schema).context("parse schema")?);
+        let properties = Arc::new(WriterProperties::builder().build());
+        let file = File::create(path).context("create parquet file")?;
+        Ok(Self {
+            writer: Some(SerializedFileWriter::new(file, schema, properties)?),
+            columns,
+            rows: 0,
+        })
+    }
+
+    fn added_row(&mut self) -> Result<()> {
+        self.rows += 1;
+        match self.rows >= ROW_GROUP_ROWS {
+            true => self.flush(),
+            false => Ok(()),
+        }
+    }
+
+    /// Writes the buffered rows as a row group.
+    fn flush(&mut self) -> Result<()> {
+        let Some(writer) = &mut self.writer else {
+            return Ok(());
+        };
+        if self.rows == 0 {
+            return Ok(());
+        }
+        let mut row_group = writer.next_row_group()?;
+        for column in &mut self.columns {
+            let mut column_writer = row_group
+                .next_column()?
+                .context("more columns than in the schema")?;
+            match column {
+                Column::Int64(values) => {
+                    column_writer
+                        .typed::<Int64Type>()
+                        .write_batch(values, None, None)?;
+                    values.clear();
+                }
+                Column::Bytes {
+                    values,
+                    definition,
+                    repetition,
+                } => {
+                    column_writer.typed::<ByteArrayType>().write_batch(
+                        values,
+                        levels(definition),
+                        levels(repetition),
+                    )?;
+                    values.clear();
+                    definition.clear();
+                    repetition.clear();
+                }
+            }
+            column_writer.close()?;
+        }
+        row_group.close()?;
+        self.rows = 0;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.flush()?;
+        if let Some(writer) = self.writer.take() {
+            writer.close()?;
+        }
+        Ok(())
+    }
+}
+
+/// The Parquet files of a run, a [`Sink`]. They are completed when the sink
+/// is dropped.
+pub struct ParquetSink {
+    auctions: Table,
+    order_executions: Table,
+}
+
+impl fmt::Debug for ParquetSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParquetSink").finish_non_exhaustive()
+    }
+}
+
+impl ParquetSink {
+    /// Creates `competition_auctions-<name>.parquet` and
+    /// `order_execution-<name>.parquet` in `dir`.
+    pub fn create(dir: &Path, name: &str) -> Result<Self> {
+        std::fs::create_dir_all(dir).context("create parquet directory")?;
+        Ok(Self {
+            auctions: Table::create(
+                &dir.join(format!("competition_auctions-{name}.parquet")),
+                AUCTIONS_SCHEMA,
+                vec![
+                    Column::Int64(Vec::new()),
+                    Column::Int64(Vec::new()),
+                    Column::Int64(Vec::new()),
+                    Column::bytes(),
+                    Column::bytes(),
+                    Column::bytes(),
+                    Column::bytes(),
+                ],
+            )?,
+            order_executions: Table::create(
+                &dir.join(format!("order_execution-{name}.parquet")),
+                ORDER_EXECUTIONS_SCHEMA,
+                vec![
+                    Column::bytes(),
+                    Column::Int64(Vec::new()),
+                    Column::bytes(),
+                    Column::bytes(),
+                ],
+            )?,
+        })
+    }
+}
+
+impl Sink for ParquetSink {
+    fn write_auctions(&mut self, auctions: &[Auction]) -> Result<()> {
+        for auction in auctions {
+            let columns = &mut self.auctions.columns;
+            columns[0].push_int64(auction.id);
+            columns[1].push_int64(auction.block);
+            columns[2].push_int64(auction.deadline);
+            columns[3].push_repeated(auction.order_uids.iter().map(|uid| uid.0.to_vec()));
+            columns[4].push_repeated(auction.price_tokens.iter().map(|token| token.0.to_vec()));
+            columns[5].push_repeated(
+                auction
+                    .price_values
+                    .iter()
+                    .map(|value| value.to_string().into_bytes()),
+            );
+            columns[6].push_repeated(
+                auction
+                    .surplus_capturing_jit_order_owners
+                    .iter()
+                    .map(|owner| owner.0.to_vec()),
+            );
+            self.auctions.added_row()?;
+        }
+        Ok(())
+    }
+
+    fn write_order_execution(&mut self, order_execution: &OrderExecution) -> Result<()> {
+        let columns = &mut self.order_executions.columns;
+        columns[0].push_required(order_execution.order_uid.0.to_vec());
+        columns[1].push_int64(order_execution.auction_id);
+        columns[2].push_required(order_execution.executed_fee.to_string().into_bytes());
+        columns[3].push_optional(
+            order_execution
+                .executed_fee_token
+                .map(|token| token.0.to_vec()),
+        );
+        self.order_executions.added_row()
+    }
+}
+
+impl Drop for ParquetSink {
+    fn drop(&mut self) {
+        for table in [&mut self.auctions, &mut self.order_executions] {
+            if let Err(err) = table.close() {
+                error!("failed to complete parquet file: {err:?}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_levels() {
+        let mut column = Column::bytes();
+        column.push_repeated(["a", "b"]);
+        column.push_repeated(Vec::<&str>::new());
+        column.push_repeated(["c"]);
+        assert_eq!(
+            column,
+            Column::Bytes {
+                values: vec!["a".into(), "b".into(), "c".into()],
+                definition: vec![1, 1, 0, 1],
+                repetition: vec![0, 1, 0, 0],
+            }
+        );
+
+        let mut column = Column::bytes();
+        column.push_optional(Some("a"));
+        column.push_optional(None::<&str>);
+        assert_eq!(
+            column,
+            Column::Bytes {
+                values: vec!["a".into()],
+                definition: vec![1, 0],
+                repetition: vec![],
+            }
+        );
+    }
+}
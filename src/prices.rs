@@ -0,0 +1,186 @@
+//! Price maps as stored in the solver competition JSON (uniform clearing
+//! prices of a solution, external native prices of an auction) and the one
+//! place where amounts get converted between tokens using them.
+
+use {
+    primitive_types::{H160, U256, U512},
+    std::{collections::BTreeMap, fmt},
+};
+
+/// Token prices sharing a common denomination. Only the ratio between two
+/// prices of the same map is meaningful.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Prices(BTreeMap<H160, U256>);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConversionError {
+    /// The price of the token is missing or zero.
+    MissingPrice(H160),
+    /// The converted amount does not fit into a `U256`.
+    Overflow,
+}
+
+/// How a converted amount that falls between two integers is rounded.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, clap::ValueEnum)]
+pub enum Rounding {
+    /// Towards zero, dropping the fraction.
+    #[default]
+    Floor,
+    /// Away from zero if there is a fraction.
+    Ceil,
+    /// To the nearest integer, away from zero at exactly half.
+    HalfUp,
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingPrice(token) => write!(f, "missing price for token {token:?}"),
+            Self::Overflow => f.write_str("converted amount overflows"),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+impl From<BTreeMap<H160, U256>> for Prices {
+    fn from(prices: BTreeMap<H160, U256>) -> Self {
+        Self(prices)
+    }
+}
+
+impl Prices {
+    /// Returns the price of `token`. Zero prices are treated like missing
+    /// ones because no amount can be converted with them.
+    pub fn get(&self, token: H160) -> Option<U256> {
+        self.0.get(&token).copied().filter(|price| !price.is_zero())
+    }
+
+    /// Converts `amount` of token `from` into the equivalent amount of token
+    /// `to`, rounding down.
+    ///
+    /// Converting a token into itself is the identity and does not require a
+    /// price. Fails if either price is missing or zero, or the result does not
+    /// fit into a `U256`. Intermediate results are computed with 512 bits so
+    /// `amount * price` can't overflow.
+    pub fn convert(&self, amount: U256, from: H160, to: H160) -> Result<U256, ConversionError> {
+        self.convert_rounded(amount, from, to, Rounding::Floor)
+    }
+
+    /// Like [`Self::convert`] but rounding up.
+    pub fn convert_up(&self, amount: U256, from: H160, to: H160) -> Result<U256, ConversionError> {
+        self.convert_rounded(amount, from, to, Rounding::Ceil)
+    }
+
+    /// Like [`Self::convert`] but rounding as given by `rounding`.
+    pub fn convert_rounded(
+        &self,
+        amount: U256,
+        from: H160,
+        to: H160,
+        rounding: Rounding,
+    ) -> Result<U256, ConversionError> {
+        if from == to {
+            return Ok(amount);
+        }
+        let from_price = self.get(from).ok_or(ConversionError::MissingPrice(from))?;
+        let to_price = self.get(to).ok_or(ConversionError::MissingPrice(to))?;
+        let to_price = U512::from(to_price);
+        let (mut result, remainder) = amount.full_mul(from_price).div_mod(to_price);
+        let round_up = match rounding {
+            Rounding::Floor => false,
+            Rounding::Ceil => !remainder.is_zero(),
+            // the remainder is below the price, doubling it can't overflow
+            Rounding::HalfUp => remainder * 2 >= to_price,
+        };
+        if round_up {
+            result += U512::one();
+        }
+        U256::try_from(result).map_err(|_| ConversionError::Overflow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(byte: u8) -> H160 {
+        H160([byte; 20])
+    }
+
+    #[test]
+    fn convert() {
+        let prices = Prices::from(BTreeMap::from([
+            (token(1), U256::from(2)),
+            (token(2), U256::from(3)),
+            (token(3), U256::zero()),
+        ]));
+
+        assert_eq!(
+            prices.convert(10.into(), token(1), token(2)).unwrap(),
+            6.into()
+        );
+        // rounds down
+        assert_eq!(
+            prices.convert(10.into(), token(2), token(1)).unwrap(),
+            15.into()
+        );
+        assert_eq!(
+            prices.convert_up(10.into(), token(1), token(2)).unwrap(),
+            7.into()
+        );
+        assert_eq!(
+            prices.convert_up(10.into(), token(2), token(1)).unwrap(),
+            15.into()
+        );
+        assert_eq!(
+            prices.convert(7.into(), token(4), token(4)).unwrap(),
+            7.into()
+        );
+        assert_eq!(
+            prices.convert(1.into(), token(1), token(3)),
+            Err(ConversionError::MissingPrice(token(3)))
+        );
+        assert_eq!(
+            prices.convert(1.into(), token(4), token(1)),
+            Err(ConversionError::MissingPrice(token(4)))
+        );
+    }
+
+    #[test]
+    fn convert_rounded() {
+        let prices = Prices::from(BTreeMap::from([
+            (token(1), U256::from(1)),
+            (token(2), U256::from(4)),
+        ]));
+        let convert = |amount: u64, rounding| {
+            prices
+                .convert_rounded(amount.into(), token(1), token(2), rounding)
+                .unwrap()
+        };
+
+        assert_eq!(convert(9, Rounding::Floor), 2.into());
+        assert_eq!(convert(9, Rounding::Ceil), 3.into());
+        assert_eq!(convert(9, Rounding::HalfUp), 2.into());
+        assert_eq!(convert(10, Rounding::HalfUp), 3.into());
+        assert_eq!(convert(11, Rounding::HalfUp), 3.into());
+        assert_eq!(convert(12, Rounding::Ceil), 3.into());
+        assert_eq!(convert(12, Rounding::HalfUp), 3.into());
+    }
+
+    #[test]
+    fn convert_large_amounts() {
+        let pow = |exp: usize| U256::one() << exp;
+        let prices = Prices::from(BTreeMap::from([(token(1), pow(200)), (token(2), pow(100))]));
+
+        // the intermediate product exceeds 256 bits but the result does not
+        assert_eq!(
+            prices.convert(pow(100), token(1), token(2)).unwrap(),
+            pow(200)
+        );
+        assert_eq!(
+            prices.convert(pow(200), token(1), token(2)),
+            Err(ConversionError::Overflow)
+        );
+    }
+}
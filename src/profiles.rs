@@ -0,0 +1,67 @@
+//! Profiles of the databases `run-all` runs against, one per chain.
+
+use {
+    anyhow::{Context, Result},
+    serde::Deserialize,
+    std::{collections::BTreeMap, path::Path},
+};
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub db_url: String,
+    /// Arguments for this profile only, like a different `--batch-size`.
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl Profile {
+    /// The full command line running the migration given by `args` against
+    /// this profile.
+    pub fn arguments(&self, args: &[String]) -> Vec<String> {
+        ["data-migration", "--db-url", &self.db_url]
+            .into_iter()
+            .map(String::from)
+            .chain(self.args.iter().cloned())
+            .chain(args.iter().cloned())
+            .collect()
+    }
+}
+
+/// Reads the profiles by name from a TOML file with a table per profile.
+pub fn read(path: &Path) -> Result<BTreeMap<String, Profile>> {
+    let content = std::fs::read_to_string(path).context("read profiles")?;
+    toml::from_str(&content).context("parse profiles")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arguments() {
+        let profiles: BTreeMap<String, Profile> = toml::from_str(
+            r#"
+            [mainnet]
+            db_url = "postgresql://mainnet"
+            args = ["--batch-size", "10"]
+
+            [gnosis]
+            db_url = "postgresql://gnosis"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            profiles["mainnet"].arguments(&["verify".to_string()]),
+            [
+                "data-migration",
+                "--db-url",
+                "postgresql://mainnet",
+                "--batch-size",
+                "10",
+                "verify"
+            ]
+        );
+        assert!(profiles["gnosis"].args.is_empty());
+    }
+}
@@ -0,0 +1,987 @@
+//! Summary of a migration run: which auctions were skipped, fully or for
+//! some of their order executions, and why.
+//!
+//! Reports can be written to a JSON file and compared with an earlier one to
+//! see which auctions got fixed or broken in between.
+
+use {
+    crate::{
+        database_orders::{OrderSource, OrderUid},
+        database_tables::TableStats,
+        events::{Action, Event},
+        numbers::big,
+    },
+    anyhow::{Context, Result},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        fmt,
+        ops::RangeInclusive,
+        path::Path,
+        time::{Duration, Instant},
+    },
+    tracing::{error, info, warn},
+};
+
+/// Why an auction or an order execution was skipped by a migration.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// The solver competition JSON is null.
+    NullJson,
+    /// The row or its JSON could not be decoded.
+    Deserialize,
+    /// A value does not fit into its target type.
+    Overflow,
+    /// A price needed for a conversion is missing or zero.
+    MissingPrice,
+    /// The order of an execution is in neither `orders` nor `jit_orders`.
+    OrderNotFound,
+    /// The auction has no solutions, so there is no winner whose prices could
+    /// be used for execution level backfills.
+    EmptySolutions,
+    /// Fees that have to be added up are in different tokens.
+    FeeTokenMismatch,
+    /// The auction is in a range owned by another writer, like the services.
+    Owned,
+    /// Processing the auction took longer than the configured timeout.
+    Timeout,
+    /// Processing the auction panicked.
+    Panic,
+    /// Writing the auction failed.
+    Save,
+    /// Processing the auction failed with an error, see [`OnError::Skip`].
+    Error,
+}
+
+/// How migrations react to failing to process an auction, see
+/// [`SkipReason::is_failure`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, clap::ValueEnum)]
+pub enum OnError {
+    /// Stop the run, without committing the batch of the auction.
+    Abort,
+    /// Skip the auction and go on.
+    #[default]
+    Skip,
+    /// Process the auction again a few times before skipping it. Only
+    /// populating retries single auctions, the other migrations skip them and
+    /// leave retries to `redrive`.
+    Retry,
+}
+
+impl SkipReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NullJson => "null_json",
+            Self::Deserialize => "deserialize",
+            Self::Overflow => "overflow",
+            Self::MissingPrice => "missing_price",
+            Self::OrderNotFound => "order_not_found",
+            Self::EmptySolutions => "empty_solutions",
+            Self::FeeTokenMismatch => "fee_token_mismatch",
+            Self::Owned => "owned",
+            Self::Timeout => "timeout",
+            Self::Panic => "panic",
+            Self::Save => "save",
+            Self::Error => "error",
+        }
+    }
+
+    /// Whether processing the auction failed, rather than the auction having
+    /// data a migration can't handle, so that retrying it may help.
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            Self::Deserialize
+                | Self::Overflow
+                | Self::Timeout
+                | Self::Panic
+                | Self::Save
+                | Self::Error
+        )
+    }
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How many rows per table a dry run logs.
+const DRY_RUN_SAMPLES: usize = 3;
+
+/// How many of the slowest auctions a report keeps.
+const SLOWEST: usize = 20;
+
+/// Time spent on one processed auction.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AuctionTime {
+    pub auction_id: i64,
+    pub wall_seconds: f64,
+    /// The part of `wall_seconds` spent waiting for database queries.
+    pub db_seconds: f64,
+}
+
+/// Size of a table written by a migration at the start and the end of a run,
+/// see [`crate::database_tables::TableStats`].
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct TableSize {
+    pub bytes_before: i64,
+    pub bytes_after: i64,
+    pub dead_tuples_before: i64,
+    pub dead_tuples_after: i64,
+    /// Size once the table has a row for every source row, at its current
+    /// bytes per row, if the number of rows it ends up with is known.
+    pub projected_bytes: Option<i64>,
+}
+
+impl TableSize {
+    /// The size between the statistics `before` and `after` a run, projected
+    /// to `final_rows` rows if known.
+    pub fn new(before: &TableStats, after: &TableStats, final_rows: Option<i64>) -> Self {
+        let projected_bytes = final_rows
+            .filter(|_| after.live_tuples > 0)
+            .map(|rows| (after.total_bytes as f64 / after.live_tuples as f64 * rows as f64) as i64);
+        Self {
+            bytes_before: before.total_bytes,
+            bytes_after: after.total_bytes,
+            dead_tuples_before: before.dead_tuples,
+            dead_tuples_after: after.dead_tuples,
+            projected_bytes,
+        }
+    }
+}
+
+/// Likely cause of an order being in neither `orders` nor `jit_orders`,
+/// guessed from the owner and validTo embedded in its uid.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotFoundClass {
+    /// Orders placed through the EthFlow contract have the maximal validTo.
+    EthFlow,
+    /// Solutions of the shadow competition may contain made up orders, which
+    /// have no owner or no validTo.
+    ShadowCompetition,
+    /// Orders valid only before `jit_orders` existed were most likely JIT
+    /// orders that had nowhere to be stored.
+    PreJitTable,
+    Unknown,
+}
+
+/// Approximately when `jit_orders` started being populated, 2024-11-01.
+const JIT_ORDERS_SINCE: u32 = 1_730_419_200;
+
+impl NotFoundClass {
+    pub fn of(order_uid: &OrderUid) -> Self {
+        let valid_to = order_uid.valid_to();
+        if valid_to == u32::MAX {
+            Self::EthFlow
+        } else if valid_to == 0 || order_uid.owner().0 == [0; 20] {
+            Self::ShadowCompetition
+        } else if valid_to < JIT_ORDERS_SINCE {
+            Self::PreJitTable
+        } else {
+            Self::Unknown
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::EthFlow => "eth_flow",
+            Self::ShadowCompetition => "shadow_competition",
+            Self::PreJitTable => "pre_jit_table",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Report {
+    pub migration: String,
+    /// Affected auction ids by reason.
+    pub skipped: BTreeMap<SkipReason, BTreeSet<i64>>,
+    /// Highest source auction id when the run started.
+    #[serde(default)]
+    pub snapshot_max_id: Option<i64>,
+    /// Time spent on all processed auctions together, and the part of it spent
+    /// waiting for the database.
+    #[serde(default)]
+    pub wall_seconds: f64,
+    #[serde(default)]
+    pub db_seconds: f64,
+    /// The slowest processed auctions, slowest first.
+    #[serde(default)]
+    pub slowest: Vec<AuctionTime>,
+    /// Auctions skipped for [`SkipReason::OrderNotFound`] by the likely cause.
+    #[serde(default)]
+    pub order_not_found: BTreeMap<NotFoundClass, BTreeSet<i64>>,
+    /// Auctions that were migrated but contain data that looks wrong.
+    #[serde(default)]
+    pub suspect: BTreeSet<i64>,
+    /// Rows a dry run would have written, by table.
+    #[serde(default)]
+    pub dry_run_rows: BTreeMap<String, usize>,
+    /// Message and backtrace of the panics of auctions skipped for
+    /// [`SkipReason::Panic`].
+    #[serde(default)]
+    pub panics: BTreeMap<i64, String>,
+    /// Sizes of the tables the migration writes, so vacuums and capacity can
+    /// be planned ahead of the remaining backfills.
+    #[serde(default)]
+    pub tables: BTreeMap<String, TableSize>,
+    /// Fields in which migrated auctions differ from the ones derived again
+    /// from their solver competitions, just `missing` for auctions that were
+    /// not migrated.
+    #[serde(default)]
+    pub mismatches: BTreeMap<i64, Vec<String>>,
+    /// The first error of auctions skipped because processing them failed,
+    /// see [`SkipReason::is_failure`].
+    #[serde(default)]
+    pub errors: BTreeMap<i64, String>,
+    /// Auctions whose JSON was taken from a fallback source because the
+    /// stored one is missing or doesn't decode, by source.
+    #[serde(default)]
+    pub fallbacks: BTreeMap<String, BTreeSet<i64>>,
+    /// Auctions written without their solutions because those don't decode,
+    /// see `--partial-writes`.
+    #[serde(default)]
+    pub partial: BTreeSet<i64>,
+    /// Auctions without solutions, so without winner, which execution level
+    /// backfills skip.
+    #[serde(default)]
+    pub no_winner: BTreeSet<i64>,
+    /// Auctions listing the same order uid more than once, with the number of
+    /// duplicate entries dropped from each.
+    #[serde(default)]
+    pub duplicate_orders: BTreeMap<i64, usize>,
+    /// Auctions processed or skipped since the last commit.
+    #[serde(skip)]
+    uncommitted: BTreeMap<i64, Action>,
+}
+
+impl Report {
+    pub fn new(migration: &str) -> Self {
+        Self {
+            migration: migration.to_string(),
+            skipped: Default::default(),
+            snapshot_max_id: None,
+            wall_seconds: 0.,
+            db_seconds: 0.,
+            slowest: Vec::new(),
+            order_not_found: Default::default(),
+            suspect: Default::default(),
+            dry_run_rows: Default::default(),
+            panics: Default::default(),
+            tables: Default::default(),
+            uncommitted: Default::default(),
+            mismatches: Default::default(),
+            errors: Default::default(),
+            fallbacks: Default::default(),
+            partial: Default::default(),
+            no_winner: Default::default(),
+            duplicate_orders: Default::default(),
+        }
+    }
+
+    /// Records a skip in `auction_id`, logging `message` with the details.
+    pub fn skip(&mut self, reason: SkipReason, auction_id: i64, message: impl fmt::Display) {
+        warn!("skipping ({reason}): {message}");
+        self.skipped.entry(reason).or_default().insert(auction_id);
+        self.uncommitted
+            .entry(auction_id)
+            .or_insert(Action::Skipped);
+        if reason.is_failure() {
+            self.errors
+                .entry(auction_id)
+                .or_insert_with(|| message.to_string());
+        }
+        crate::metrics::get()
+            .skipped
+            .with_label_values(&[&self.migration, reason.as_str()])
+            .inc();
+    }
+
+    /// Records a skip in `auction_id` because `order_uid` is in neither
+    /// `orders` nor `jit_orders`, classifying the likely cause.
+    pub fn order_not_found(&mut self, auction_id: i64, order_uid: &OrderUid) {
+        let class = NotFoundClass::of(order_uid);
+        self.skip(
+            SkipReason::OrderNotFound,
+            auction_id,
+            format_args!(
+                "order_uid: {:?}, owner: {:?}, valid_to: {}, auction_id: {}, likely cause: {}",
+                order_uid,
+                order_uid.owner(),
+                order_uid.valid_to(),
+                auction_id,
+                class.as_str()
+            ),
+        );
+        self.order_not_found
+            .entry(class)
+            .or_default()
+            .insert(auction_id);
+    }
+
+    /// Records suspect data in `auction_id`, logging `message` with the
+    /// details.
+    pub fn suspect(&mut self, auction_id: i64, message: impl fmt::Display) {
+        warn!("suspect: {message}");
+        self.suspect.insert(auction_id);
+    }
+
+    /// Records a skip in `auction_id` because processing it panicked with
+    /// `message`, keeping the `backtrace` of the panic.
+    pub fn panicked(&mut self, auction_id: i64, message: &str, backtrace: &str) {
+        self.skip(
+            SkipReason::Panic,
+            auction_id,
+            format_args!("auction: {auction_id}, panicked: {message}"),
+        );
+        self.panics
+            .insert(auction_id, format!("{message}\n{backtrace}"));
+    }
+
+    /// Records that `auction_id` differs from its solver competition in
+    /// `fields`.
+    pub fn mismatch(&mut self, auction_id: i64, fields: &[&str]) {
+        warn!(
+            "auction {auction_id} differs from its solver competition: {}",
+            fields.join(", ")
+        );
+        self.mismatches
+            .entry(auction_id)
+            .or_default()
+            .extend(fields.iter().map(|field| field.to_string()));
+    }
+
+    /// Whether processing `auction_id` failed, see [`SkipReason::is_failure`].
+    pub fn failed(&self, auction_id: i64) -> bool {
+        self.errors.contains_key(&auction_id)
+    }
+
+    /// Records that the JSON of `auction_id` was taken from the fallback
+    /// `source`.
+    pub fn fallback(&mut self, auction_id: i64, source: &str) {
+        info!("auction {auction_id}: using the JSON from the {source} fallback");
+        self.fallbacks
+            .entry(source.to_string())
+            .or_default()
+            .insert(auction_id);
+    }
+
+    /// Records that `auction_id` is written without its solutions, which
+    /// failed to decode with `error`.
+    pub fn partial(&mut self, auction_id: i64, error: impl fmt::Display) {
+        warn!("auction {auction_id}: writing it without its solutions, error: {error}");
+        self.partial.insert(auction_id);
+    }
+
+    /// Records that `auction_id` has no solutions, so no winner.
+    pub fn no_winner(&mut self, auction_id: i64) {
+        info!("no solutions, no winner, auction: {auction_id}");
+        self.no_winner.insert(auction_id);
+    }
+
+    /// Records that `duplicates` duplicate order uids were dropped from
+    /// `auction_id`.
+    pub fn duplicate_orders(&mut self, auction_id: i64, duplicates: usize) {
+        info!("dropped {duplicates} duplicate order uids, auction: {auction_id}");
+        self.duplicate_orders.insert(auction_id, duplicates);
+    }
+
+    /// Records that a dry run would have written `rows` rows to `table`,
+    /// logging `sample` of them for the first few calls per table.
+    pub fn would_write(&mut self, table: &str, rows: usize, sample: impl fmt::Display) {
+        let written = self.dry_run_rows.entry(table.to_string()).or_default();
+        if *written < DRY_RUN_SAMPLES {
+            info!("dry run, would write to {table}: {sample}");
+        }
+        *written += rows;
+    }
+
+    /// Records the conversion of an order execution whose order was found in
+    /// `source`, logging `message` with the details.
+    pub fn converted(&self, source: OrderSource, message: impl fmt::Display) {
+        info!("converted ({}): {message}", source.as_str());
+        crate::metrics::get()
+            .converted
+            .with_label_values(&[&self.migration, source.as_str()])
+            .inc();
+    }
+
+    /// Records the auction id the migration is currently processing.
+    pub fn progress(&self, auction_id: i64) {
+        crate::metrics::get()
+            .current_auction_id
+            .with_label_values(&[&self.migration])
+            .set(auction_id);
+    }
+
+    /// Records the processing of `auction_id` that started at `start` and
+    /// spent `db` of it waiting for the database.
+    pub fn processed(
+        &mut self,
+        auction_id: i64,
+        json: &serde_json::Value,
+        start: Instant,
+        db: Duration,
+    ) {
+        self.uncommitted.insert(auction_id, Action::Processed);
+        let elapsed = start.elapsed();
+        crate::metrics::get().observe_auction(
+            &self.migration,
+            crate::metrics::json_size(json),
+            elapsed,
+        );
+        let time = AuctionTime {
+            auction_id,
+            wall_seconds: elapsed.as_secs_f64(),
+            db_seconds: db.as_secs_f64(),
+        };
+        self.wall_seconds += time.wall_seconds;
+        self.db_seconds += time.db_seconds;
+        if self.slowest.len() < SLOWEST
+            || self
+                .slowest
+                .last()
+                .is_some_and(|slowest| slowest.wall_seconds < time.wall_seconds)
+        {
+            self.slowest.push(time);
+            self.slowest
+                .sort_by(|a, b| b.wall_seconds.total_cmp(&a.wall_seconds));
+            self.slowest.truncate(SLOWEST);
+        }
+    }
+
+    /// Adds the time `auction_ids`, processed before, took to be written
+    /// together, split evenly across them.
+    pub fn written(&mut self, auction_ids: &[i64], db: Duration) {
+        if auction_ids.is_empty() {
+            return;
+        }
+        let share = db.as_secs_f64() / auction_ids.len() as f64;
+        self.wall_seconds += db.as_secs_f64();
+        self.db_seconds += db.as_secs_f64();
+        for time in &mut self.slowest {
+            if auction_ids.contains(&time.auction_id) {
+                time.wall_seconds += share;
+                time.db_seconds += share;
+            }
+        }
+        self.slowest
+            .sort_by(|a, b| b.wall_seconds.total_cmp(&a.wall_seconds));
+    }
+
+    /// Publishes the events of the auctions processed or skipped since the
+    /// last call, now that their batch is committed.
+    pub fn committed(&mut self) {
+        let uncommitted = std::mem::take(&mut self.uncommitted);
+        crate::events::publish(uncommitted.into_iter().map(|(auction_id, action)| Event {
+            auction_id,
+            migration: self.migration.clone(),
+            action,
+        }));
+    }
+
+    /// Adds the report of another worker of the same run.
+    pub fn merge(&mut self, other: Self) {
+        for (reason, auctions) in other.skipped {
+            self.skipped.entry(reason).or_default().extend(auctions);
+        }
+        for (class, auctions) in other.order_not_found {
+            self.order_not_found
+                .entry(class)
+                .or_default()
+                .extend(auctions);
+        }
+        for (table, rows) in other.dry_run_rows {
+            *self.dry_run_rows.entry(table).or_default() += rows;
+        }
+        self.suspect.extend(other.suspect);
+        self.panics.extend(other.panics);
+        self.mismatches.extend(other.mismatches);
+        self.errors.extend(other.errors);
+        self.uncommitted.extend(other.uncommitted);
+        self.partial.extend(other.partial);
+        self.no_winner.extend(other.no_winner);
+        self.duplicate_orders.extend(other.duplicate_orders);
+        for (source, auctions) in other.fallbacks {
+            self.fallbacks.entry(source).or_default().extend(auctions);
+        }
+        self.wall_seconds += other.wall_seconds;
+        self.db_seconds += other.db_seconds;
+        self.slowest.extend(other.slowest);
+        self.slowest
+            .sort_by(|a, b| b.wall_seconds.total_cmp(&a.wall_seconds));
+        self.slowest.truncate(SLOWEST);
+    }
+
+    pub fn print(&self) {
+        if self.skipped.is_empty() {
+            info!("{}: nothing was skipped", self.migration);
+        }
+        for (reason, auctions) in &self.skipped {
+            info!(
+                "{}: skipped {reason}: {} auctions",
+                self.migration,
+                big(auctions.len())
+            );
+        }
+        for (table, rows) in &self.dry_run_rows {
+            info!(
+                "{}: dry run, would have written {} rows to {table}",
+                self.migration,
+                big(rows)
+            );
+        }
+        if !self.suspect.is_empty() {
+            info!(
+                "{}: suspect data in {} auctions",
+                self.migration,
+                big(self.suspect.len())
+            );
+        }
+        if !self.mismatches.is_empty() {
+            info!(
+                "{}: {} auctions differ from their solver competitions",
+                self.migration,
+                big(self.mismatches.len())
+            );
+        }
+        if !self.partial.is_empty() {
+            info!(
+                "{}: wrote {} auctions without their solutions",
+                self.migration,
+                big(self.partial.len())
+            );
+        }
+        if !self.no_winner.is_empty() {
+            info!(
+                "{}: {} auctions without winner",
+                self.migration,
+                big(self.no_winner.len())
+            );
+        }
+        if !self.duplicate_orders.is_empty() {
+            info!(
+                "{}: {} auctions with duplicate order uids, {} duplicates dropped",
+                self.migration,
+                big(self.duplicate_orders.len()),
+                big(self.duplicate_orders.values().sum::<usize>())
+            );
+        }
+        for (source, auctions) in &self.fallbacks {
+            info!(
+                "{}: took the JSON of {} auctions from the {source} fallback",
+                self.migration,
+                big(auctions.len())
+            );
+        }
+        for (class, auctions) in &self.order_not_found {
+            info!(
+                "{}: order not found, likely cause {}: {} auctions",
+                self.migration,
+                class.as_str(),
+                big(auctions.len())
+            );
+        }
+        if self.slowest.is_empty() {
+            return;
+        }
+        info!(
+            "{}: {:.1}s spent on auctions, {:.1}s of it in the database",
+            self.migration, self.wall_seconds, self.db_seconds
+        );
+        info!("slowest auctions:");
+        for time in &self.slowest {
+            info!(
+                "    {}: {:.3}s, {:.3}s in the database",
+                time.auction_id, time.wall_seconds, time.db_seconds
+            );
+        }
+    }
+
+    /// Prints how the tables written by the migration grew during the run.
+    pub fn print_tables(&self) {
+        const MB: f64 = 1024. * 1024.;
+        for (table, size) in &self.tables {
+            info!(
+                "{table}: {:.1}MB ({:+.1}MB), {} dead tuples ({}{})",
+                size.bytes_after as f64 / MB,
+                (size.bytes_after - size.bytes_before) as f64 / MB,
+                big(size.dead_tuples_after),
+                match size.dead_tuples_after >= size.dead_tuples_before {
+                    true => "+",
+                    false => "",
+                },
+                big(size.dead_tuples_after - size.dead_tuples_before)
+            );
+            if let Some(projected) = size.projected_bytes {
+                info!("    projected final size: {:.1}MB", projected as f64 / MB);
+            }
+        }
+    }
+
+    /// Whether `auction_id` has at least one skip.
+    pub fn is_skipped(&self, auction_id: i64) -> bool {
+        self.skipped
+            .values()
+            .any(|auctions| auctions.contains(&auction_id))
+    }
+
+    /// All auctions with at least one skip.
+    pub fn affected_auctions(&self) -> BTreeSet<i64> {
+        self.skipped.values().flatten().copied().collect()
+    }
+
+    /// What is left for a follow-up run given the highest source auction id
+    /// now.
+    pub fn follow_up(&self, current_max_id: Option<i64>) -> FollowUp {
+        let arrived = match (self.snapshot_max_id, current_max_id) {
+            (Some(snapshot), Some(current)) if current > snapshot => Some(snapshot + 1..=current),
+            (None, Some(current)) => Some(0..=current),
+            _ => None,
+        };
+        let mut skipped: Vec<RangeInclusive<i64>> = Vec::new();
+        for auction in self.affected_auctions() {
+            match skipped.last_mut() {
+                Some(range) if *range.end() + 1 == auction => *range = *range.start()..=auction,
+                _ => skipped.push(auction..=auction),
+            }
+        }
+        FollowUp { arrived, skipped }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let file = std::fs::File::create(path).context("create report file")?;
+        serde_json::to_writer_pretty(file, self).context("write report")
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path).context("open report file")?;
+        serde_json::from_reader(std::io::BufReader::new(file)).context("read report")
+    }
+}
+
+/// Auctions a finished run did not cover.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct FollowUp {
+    /// Auctions that arrived after the snapshot of the run.
+    pub arrived: Option<RangeInclusive<i64>>,
+    /// Auctions with at least one skip, as contiguous ranges.
+    pub skipped: Vec<RangeInclusive<i64>>,
+}
+
+impl FollowUp {
+    /// Prints the uncovered auctions and `command`, the invocation that
+    /// covers them, if there is one.
+    pub fn print(&self, command: Option<&str>) {
+        if self.arrived.is_none() && self.skipped.is_empty() {
+            info!("nothing left for a follow-up run");
+            return;
+        }
+        if let Some(arrived) = &self.arrived {
+            info!(
+                "auctions {}..={} arrived after the snapshot",
+                arrived.start(),
+                arrived.end()
+            );
+        }
+        if !self.skipped.is_empty() {
+            let ranges: Vec<_> = self
+                .skipped
+                .iter()
+                .map(|range| match range.start() == range.end() {
+                    true => range.start().to_string(),
+                    false => format!("{}..={}", range.start(), range.end()),
+                })
+                .collect();
+            info!(
+                "auctions skipped (fix them before the follow-up run): {}",
+                ranges.join(", ")
+            );
+        }
+        match command {
+            Some(command) => info!("follow up with:\n    {command}"),
+            None => info!("this migration can't be started from the command line"),
+        }
+    }
+}
+
+/// The reports of a `run-all` invocation, by profile.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ProfileReports {
+    pub reports: BTreeMap<String, Report>,
+    /// Why the run against a profile failed.
+    pub failed: BTreeMap<String, String>,
+}
+
+impl ProfileReports {
+    /// Prints a line per profile, so the chains can be compared at a glance.
+    pub fn print(&self) {
+        for (profile, report) in &self.reports {
+            let skipped: usize = report.skipped.values().map(BTreeSet::len).sum();
+            info!(
+                "{profile}: {}: {skipped} auctions skipped, {} suspect, {} mismatches, {} panics",
+                report.migration,
+                report.suspect.len(),
+                report.mismatches.len(),
+                report.panics.len()
+            );
+        }
+        for (profile, reason) in &self.failed {
+            error!("{profile}: failed: {reason}");
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let file = std::fs::File::create(path).context("create report file")?;
+        serde_json::to_writer_pretty(file, self).context("write report")
+    }
+}
+
+/// The steps of a `rehearse` invocation in the order they ran, with how long
+/// each took, to estimate the maintenance window of the real cutover.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Rehearsal {
+    pub steps: Vec<RehearsalStep>,
+    pub total_seconds: f64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RehearsalStep {
+    pub name: String,
+    pub seconds: f64,
+    pub report: Report,
+}
+
+impl RehearsalStep {
+    /// A step passes if no auction panicked, failed or mismatches.
+    pub fn passed(&self) -> bool {
+        self.report.panics.is_empty()
+            && self.report.mismatches.is_empty()
+            && !self.report.skipped.keys().any(SkipReason::is_failure)
+    }
+}
+
+impl Rehearsal {
+    /// Records that the step `name` took `duration` and reported `report`.
+    pub fn step(&mut self, name: &str, duration: Duration, report: Report) {
+        self.steps.push(RehearsalStep {
+            name: name.to_string(),
+            seconds: duration.as_secs_f64(),
+            report,
+        });
+    }
+
+    /// Whether every step passed, which the cutover can go ahead on.
+    pub fn passed(&self) -> bool {
+        self.steps.iter().all(RehearsalStep::passed)
+    }
+
+    /// Prints a line per step and whether the rehearsal passed.
+    pub fn print(&self) {
+        for step in &self.steps {
+            let skipped: usize = step.report.skipped.values().map(BTreeSet::len).sum();
+            info!(
+                "{}: {:.0}s, {skipped} auctions skipped, {} mismatches, {} panics{}",
+                step.name,
+                step.seconds,
+                step.report.mismatches.len(),
+                step.report.panics.len(),
+                if step.passed() { "" } else { ", failed" }
+            );
+        }
+        match self.passed() {
+            true => info!("rehearsal passed in {:.0}s", self.total_seconds),
+            false => error!("rehearsal failed after {:.0}s", self.total_seconds),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let file = std::fs::File::create(path).context("create report file")?;
+        serde_json::to_writer_pretty(file, self).context("write report")
+    }
+}
+
+/// Difference between two reports of the same migration.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Comparison {
+    /// Affected in the old report but not in the new one.
+    pub fixed: BTreeSet<i64>,
+    /// Affected in the new report but not in the old one.
+    pub broken: BTreeSet<i64>,
+    /// Affected in both, but for different reasons.
+    pub changed: BTreeSet<i64>,
+}
+
+impl Comparison {
+    pub fn new(old: &Report, new: &Report) -> Self {
+        let (old_auctions, new_auctions) = (old.affected_auctions(), new.affected_auctions());
+        let reasons = |report: &Report, auction: i64| -> BTreeSet<SkipReason> {
+            report
+                .skipped
+                .iter()
+                .filter(|(_, auctions)| auctions.contains(&auction))
+                .map(|(reason, _)| *reason)
+                .collect()
+        };
+        Self {
+            fixed: old_auctions.difference(&new_auctions).copied().collect(),
+            broken: new_auctions.difference(&old_auctions).copied().collect(),
+            changed: old_auctions
+                .intersection(&new_auctions)
+                .filter(|auction| reasons(old, **auction) != reasons(new, **auction))
+                .copied()
+                .collect(),
+        }
+    }
+
+    pub fn print(&self) {
+        let print = |name: &str, auctions: &BTreeSet<i64>| {
+            info!("{name}: {} auctions", big(auctions.len()));
+            for auction in auctions {
+                info!("    {auction}");
+            }
+        };
+        print("newly fixed", &self.fixed);
+        print("newly broken", &self.broken);
+        print("different reason", &self.changed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare() {
+        let report = |skipped: &[(SkipReason, &[i64])]| Report {
+            migration: "test".to_string(),
+            skipped: skipped
+                .iter()
+                .map(|(reason, auctions)| (*reason, auctions.iter().copied().collect()))
+                .collect(),
+            ..Default::default()
+        };
+        let old = report(&[
+            (SkipReason::NullJson, &[1, 2]),
+            (SkipReason::MissingPrice, &[3, 4]),
+        ]);
+        let new = report(&[
+            (SkipReason::NullJson, &[1]),
+            (SkipReason::MissingPrice, &[3, 5]),
+            (SkipReason::OrderNotFound, &[1]),
+        ]);
+
+        assert_eq!(
+            Comparison::new(&old, &new),
+            Comparison {
+                fixed: [2, 4].into(),
+                broken: [5].into(),
+                changed: [1].into(),
+            }
+        );
+    }
+
+    #[test]
+    fn follow_up() {
+        let mut report = Report::new("test");
+        report.snapshot_max_id = Some(10);
+        for auction in [1, 2, 3, 5, 8, 9] {
+            report.skip(SkipReason::NullJson, auction, "");
+        }
+        report.skip(SkipReason::MissingPrice, 4, "");
+
+        assert_eq!(
+            report.follow_up(Some(12)),
+            FollowUp {
+                arrived: Some(11..=12),
+                skipped: vec![1..=5, 8..=9],
+            }
+        );
+        assert_eq!(report.follow_up(Some(10)).arrived, None);
+    }
+
+    #[test]
+    fn rehearsal() {
+        let mut rehearsal = Rehearsal::default();
+        let mut report = Report::new("test");
+        report.skip(SkipReason::NullJson, 1, "");
+        rehearsal.step("populate", Duration::from_secs(5), report);
+        assert!(rehearsal.passed());
+
+        let mut report = Report::new("test");
+        report.skip(SkipReason::Deserialize, 2, "");
+        rehearsal.step("convert", Duration::from_secs(5), report);
+        assert!(!rehearsal.passed());
+    }
+
+    #[test]
+    fn duplicate_orders() {
+        let mut report = Report::new("test");
+        report.duplicate_orders(1, 2);
+        let mut other = Report::new("test");
+        other.duplicate_orders(3, 1);
+        report.merge(other);
+
+        assert_eq!(report.duplicate_orders, [(1, 2), (3, 1)].into());
+        assert_eq!(
+            serde_json::to_value(&report).unwrap()["duplicate_orders"],
+            serde_json::json!({ "1": 2, "3": 1 })
+        );
+    }
+
+    #[test]
+    fn classify_order_not_found() {
+        let uid = |owner: u8, valid_to: u32| {
+            let mut uid = [1; 56];
+            uid[32..52].fill(owner);
+            uid[52..].copy_from_slice(&valid_to.to_be_bytes());
+            crate::database_orders::ByteArray(uid)
+        };
+
+        assert_eq!(NotFoundClass::of(&uid(2, u32::MAX)), NotFoundClass::EthFlow);
+        assert_eq!(
+            NotFoundClass::of(&uid(0, 1_700_000_000)),
+            NotFoundClass::ShadowCompetition
+        );
+        assert_eq!(
+            NotFoundClass::of(&uid(2, 0)),
+            NotFoundClass::ShadowCompetition
+        );
+        assert_eq!(
+            NotFoundClass::of(&uid(2, 1_700_000_000)),
+            NotFoundClass::PreJitTable
+        );
+        assert_eq!(
+            NotFoundClass::of(&uid(2, 1_800_000_000)),
+            NotFoundClass::Unknown
+        );
+    }
+
+    #[test]
+    fn slowest() {
+        let mut report = Report::new("test");
+        let json = serde_json::json!({});
+        for auction in 0..SLOWEST as i64 + 5 {
+            // earlier auctions started earlier, so took longer
+            let start = Instant::now() - Duration::from_secs(100 - auction as u64);
+            report.processed(auction, &json, start, Duration::from_secs(1));
+        }
+
+        let slowest: Vec<_> = report.slowest.iter().map(|time| time.auction_id).collect();
+        assert_eq!(slowest, (0..SLOWEST as i64).collect::<Vec<_>>());
+        assert_eq!(report.db_seconds, (SLOWEST + 5) as f64);
+
+        report.written(
+            &[SLOWEST as i64 - 1, SLOWEST as i64 + 1],
+            Duration::from_secs(400),
+        );
+        assert_eq!(report.slowest[0].auction_id, SLOWEST as i64 - 1);
+        assert!(report.slowest[0].db_seconds > 200.);
+        assert_eq!(report.db_seconds, (SLOWEST + 405) as f64);
+    }
+}
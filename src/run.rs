@@ -1,376 +1,4918 @@
 use crate::{
-    database::Postgres,
+    arguments::{Arguments, Command},
+    conversions::u256_to_big_decimal,
+    database::{Config, IdRange, PoolOptions, Postgres},
+    database_privileges::Grant,
+    database_runs::{FrontierKind, Ownership, WorkerProgress},
     database_solver_competition::{
-        big_decimal_to_u256, fetch_batch, fetch_competition_order_execution, Auction, ByteArray,
+        fetch_batch, fetch_batch_metadata_first, fetch_batch_missing,
+        fetch_competition_order_execution, fetch_existing, fetch_frontier, fetch_max_id,
+        fetch_min_id, fetch_without_solutions, Auction, AuctionColumn, ByteArray, Direction,
+        RichSolverCompetition,
     },
-    solver_competition_api::SolverCompetitionDB,
+    database_tables::TableStats,
+    numbers::big,
+    report::{Comparison, OnError, ProfileReports, Report, SkipReason, TableSize},
+    solver_competition_api::{decode_without_solutions, skipped_orders, SolverCompetitionDB},
+    throughput::{self, Stage, Timings},
 };
-use anyhow::{Context, Result};
+#[cfg(feature = "destructive")]
+use crate::{
+    conversions::big_decimal_to_u256,
+    prices::ConversionError,
+    solver_competition_api::{select_winning_solutions, Order},
+};
+use anyhow::{bail, Context, Result};
 use clap::Parser;
-use primitive_types::H160;
-use std::{num::NonZero, ops::DerefMut};
+use futures::{FutureExt, StreamExt};
+use primitive_types::{H160, H256};
+use sqlx::{Connection, PgConnection};
+use std::{
+    backtrace::Backtrace,
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    num::NonZero,
+    ops::DerefMut,
+    panic::AssertUnwindSafe,
+    time::{Duration, Instant},
+};
+use tracing::{error, info, instrument, warn, Instrument};
+
+pub async fn start(args: impl Iterator<Item = String>) {
+    let args = Arguments::parse_from(args);
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::new(&args.log_filter))
+        .init();
+    install_panic_hook();
+    crate::numbers::set_scientific(args.scientific_numbers);
+
+    match &args.command {
+        Some(Command::JsonSchema { api }) => {
+            let schema = if *api {
+                crate::json_schema::solver_competition_api()
+            } else {
+                crate::json_schema::solver_competition_db()
+            };
+            println!("{}", serde_json::to_string_pretty(&schema).unwrap());
+            return;
+        }
+        Some(Command::CompareReports { old, new }) => {
+            let old = Report::read(old).unwrap();
+            let new = Report::read(new).unwrap();
+            Comparison::new(&old, &new).print();
+            return;
+        }
+        _ => (),
+    }
+
+    if let Some(address) = args.metrics_address {
+        tokio::spawn(async move {
+            if let Err(err) = crate::metrics::serve(address).await {
+                error!("failed to serve metrics: {:?}", err);
+            }
+        });
+    }
+
+    #[cfg(feature = "events")]
+    if let Some(url) = &args.events_url {
+        if args.dry_run {
+            info!("not publishing events in a dry run");
+        } else {
+            let publisher = crate::events::connect(url).await.unwrap();
+            crate::events::start(publisher).unwrap();
+            info!("publishing events to {url}");
+        }
+    }
+
+    let report_file = args.report_file.clone();
+    match args.command {
+        Some(Command::RunAll {
+            config,
+            profiles,
+            concurrency,
+            args: migration_args,
+        }) => {
+            let reports = run_all(&config, &profiles, concurrency.get(), &migration_args)
+                .await
+                .unwrap();
+            reports.print();
+            if let Some(path) = &report_file {
+                reports.write(path).unwrap();
+            }
+        }
+        _ => {
+            if let (Some(report), Some(path)) = (migrate(args).await, &report_file) {
+                report.write(path).unwrap();
+            }
+        }
+    }
+
+    // sleep for 10 minutes
+    tokio::time::sleep(Duration::from_secs(600)).await;
+}
+
+/// Runs the command of `args` against the database of `args` and returns its
+/// report, if it has one.
+async fn migrate(args: Arguments) -> Option<Report> {
+    if let (Some(start), Some(end)) = (args.start_auction_id, args.end_auction_id) {
+        if start > end {
+            error!("--start-auction-id {start} is after --end-auction-id {end}");
+            return None;
+        }
+    }
+
+    // every worker holds a connection, leave the usual ones to the rest
+    let max_connections = 10 + args.workers.get() as u32;
+    // the writes of the run are recorded for the migrations it runs
+    let migrations = locked_migrations(args.command.as_ref());
+    let mut db = Postgres::connect(
+        args.db_url.as_str(),
+        NonZero::new(500).unwrap(),
+        PoolOptions {
+            max_connections,
+            migration: (!migrations.is_empty()).then(|| migrations.join(",")),
+            tcp_keepalive_idle: args.tcp_keepalive_idle_secs.map(Duration::from_secs),
+            tcp_keepalive_interval: args.tcp_keepalive_interval_secs.map(Duration::from_secs),
+            tcp_keepalive_count: args.tcp_keepalive_count,
+            ping_interval: args.ping_interval_secs.map(Duration::from_secs),
+        },
+    )
+    .await
+    .unwrap();
+    db.config.dry_run = args.dry_run;
+    db.config.shadow_writes = args.shadow_writes;
+    #[cfg(feature = "destructive")]
+    {
+        db.config.overwrite = args.overwrite;
+    }
+    db.config.on_error = args.on_error;
+    db.config.partial_writes = args.partial_writes;
+    db.config.max_updates = args.max_updates;
+    db.config.max_write_ratio_deviation = args.max_write_ratio_deviation;
+    db.config.fee_rounding = args.fee_rounding;
+    db.config.fallbacks = crate::sources::Fallbacks::from_arguments(&args).unwrap();
+    if let Some(path) = &args.orders_snapshot {
+        let file = std::fs::File::open(path)
+            .context("open orders snapshot")
+            .unwrap();
+        let snapshot =
+            crate::order_snapshots::OrderSnapshot::read(std::io::BufReader::new(file)).unwrap();
+        info!(
+            "read {} orders from the snapshot {}",
+            big(snapshot.len()),
+            path.display()
+        );
+        db.config.orders_snapshot = Some(std::sync::Arc::new(snapshot));
+    }
+    if let Some(path) = &args.fee_audit_file {
+        let audit = crate::fee_audits::FeeAudit::open(path).unwrap();
+        db.config.fee_audit = Some(std::sync::Arc::new(std::sync::Mutex::new(audit)));
+    }
+    #[cfg(feature = "parquet")]
+    if let Some(dir) = &args.parquet_dir {
+        if args.dry_run {
+            info!("not writing parquet files in a dry run");
+        } else {
+            let name = time::OffsetDateTime::now_utc().unix_timestamp().to_string();
+            let sink = crate::parquet_export::ParquetSink::create(dir, &name).unwrap();
+            info!("writing parquet files to {}", dir.display());
+            db.config
+                .sinks
+                .push(std::sync::Arc::new(std::sync::Mutex::new(sink)));
+        }
+    }
+    let missing = crate::database_privileges::missing(
+        &mut db.pool.acquire().await.unwrap(),
+        &required_grants(args.command.as_ref()),
+    )
+    .await
+    .unwrap();
+    if !missing.is_empty() {
+        error!("the connected role is missing privileges, grant them with:");
+        for statement in missing {
+            error!("    {statement}");
+        }
+        // fail jobs that run without them instead of reporting success
+        std::process::exit(1);
+    }
+    // held until `locks` is dropped, closing their connections
+    let mut locks = Vec::new();
+    if !db.config.dry_run {
+        for migration in locked_migrations(args.command.as_ref()) {
+            let mut lock = db.pool.acquire().await.unwrap().detach();
+            if !crate::database_runs::try_lock(&mut lock, migration)
+                .await
+                .unwrap()
+            {
+                error!("another run of {migration} is in progress, refusing to run concurrently");
+                return None;
+            }
+            locks.push(lock);
+        }
+    }
+    let written = written_tables(args.command.as_ref());
+    if !is_ready(&db, written, args.min_schema_version.as_ref())
+        .await
+        .unwrap()
+    {
+        if !args.force {
+            error!(
+                "refusing to write to a database that is not ready, pass --force to write anyway"
+            );
+            return None;
+        }
+        warn!("writing to a database that is not ready, as forced");
+    }
+    crate::database_runs::create_tables(&mut db.pool.acquire().await.unwrap())
+        .await
+        .unwrap();
+    // recording the chain is how the database gets one to check against
+    if !matches!(args.command, Some(Command::SetChainId { .. }))
+        && !is_expected_database(
+            &db,
+            args.expected_db_name.as_deref(),
+            args.expected_chain_id,
+        )
+        .await
+        .unwrap()
+    {
+        error!("refusing to run against an unexpected database");
+        return None;
+    }
+    let schema = crate::database_schema::fetch(&mut db.pool.acquire().await.unwrap())
+        .await
+        .unwrap();
+    info!("detected schema: {schema}");
+    db.config.schema_variant = Some(schema.to_string());
+
+    let range = AuctionRange {
+        start: args.start_auction_id,
+        end: args.end_auction_id,
+    };
+    let tables_before = fetch_table_stats(&db, written).await.unwrap();
+    let options = PopulateOptions::from_arguments(&args);
+    let mut report = match args.command {
+        None | Some(Command::MigrateAuctions | Command::FixMissingAuctions) => {
+            db.config.auction_columns = existing_auction_columns(&args.auction_columns, &schema);
+            if let Some(Command::FixMissingAuctions) = args.command {
+                Some(fix_missing_historic_auctions(&db, options).await.unwrap())
+            } else {
+                let prioritized = match &args.priority_file {
+                    Some(path) => read_auction_ids(path).unwrap(),
+                    None => Vec::new(),
+                };
+                Some(
+                    populate_historic_auctions(&db, options, &prioritized)
+                        .await
+                        .unwrap(),
+                )
+            }
+        }
+        #[cfg(feature = "destructive")]
+        Some(Command::ConvertFees) => Some(
+            convert_executed_fee(&db, range, args.batch_size, options.throttle, options.limit)
+                .await
+                .unwrap(),
+        ),
+        #[cfg(feature = "destructive")]
+        Some(Command::BackfillExecutedFeeToken) => Some(
+            backfill_executed_fee_token(&db, options.throttle)
+                .await
+                .unwrap(),
+        ),
+        #[cfg(feature = "destructive")]
+        Some(Command::ConsolidateSurplusFee) => Some(
+            consolidate_surplus_fee(&db, options.throttle)
+                .await
+                .unwrap(),
+        ),
+        #[cfg(feature = "destructive")]
+        Some(Command::RecomputeExecutedAmounts) => Some(
+            recompute_executed_amounts(&db, options.throttle)
+                .await
+                .unwrap(),
+        ),
+        #[cfg(feature = "destructive")]
+        Some(Command::ApplyProposals) => {
+            apply_proposals(&db, args.batch_size, options.throttle)
+                .await
+                .unwrap();
+            return None;
+        }
+        #[cfg(feature = "destructive")]
+        Some(Command::Rollback { migration }) => {
+            rollback(&db, migration, args.batch_size, options.throttle)
+                .await
+                .unwrap();
+            return None;
+        }
+        #[cfg(feature = "destructive")]
+        Some(Command::ApplyFromFile { file }) => {
+            db.config.auction_columns = existing_auction_columns(&args.auction_columns, &schema);
+            apply_from_file(&db, &file, args.batch_size).await.unwrap();
+            return None;
+        }
+        #[cfg(feature = "destructive")]
+        Some(Command::Rehearse) => {
+            db.config.auction_columns = existing_auction_columns(&args.auction_columns, &schema);
+            let rehearsal = rehearse(&db, options, args.giant_auction_bytes)
+                .await
+                .unwrap();
+            rehearsal.print();
+            if let Some(path) = &args.report_file {
+                rehearsal.write(path).unwrap();
+            }
+            if !rehearsal.passed() {
+                error!("the rehearsal failed, see the report");
+                std::process::exit(1);
+            }
+            return None;
+        }
+        Some(Command::ValidateJson) => {
+            validate_json(&db).await.unwrap();
+            None
+        }
+        Some(Command::Redrive) => {
+            db.config.auction_columns = existing_auction_columns(&args.auction_columns, &schema);
+            redrive(&db, options).await.unwrap();
+            return None;
+        }
+        Some(Command::ImportResolutions { file }) => {
+            import_resolutions(&db, &file).await.unwrap();
+            return None;
+        }
+        Some(Command::Verify) => {
+            let report = verify(&db, range, args.giant_auction_bytes).await.unwrap();
+            report.print();
+            return Some(report);
+        }
+        Some(Command::CheckTrades { limit }) => {
+            check_trades(&db, range, limit).await.unwrap();
+            return None;
+        }
+        Some(Command::ReportRemaining { top }) => {
+            report_remaining(&db, top).await.unwrap();
+            return None;
+        }
+        Some(Command::Scope { bucket_size, top }) => {
+            scope(&db, bucket_size, top).await.unwrap();
+            return None;
+        }
+        Some(Command::MarkOwned { start, end, owner }) => {
+            crate::database_runs::mark_owned(
+                &mut db.pool.acquire().await.unwrap(),
+                start,
+                end,
+                &owner,
+            )
+            .await
+            .unwrap();
+            info!("auctions {start}..={end} are owned by {owner}");
+            return None;
+        }
+        Some(Command::Annotate {
+            run_id,
+            auction_id,
+            note,
+        }) => {
+            annotate(&db, run_id, auction_id, note).await.unwrap();
+            return None;
+        }
+        Some(Command::SetChainId { chain_id }) => {
+            crate::database_runs::save_setting(
+                &mut db.pool.acquire().await.unwrap(),
+                crate::database_runs::CHAIN_ID,
+                &chain_id.to_string(),
+            )
+            .await
+            .unwrap();
+            info!("recorded chain id {chain_id}");
+            return None;
+        }
+        Some(Command::AuctionStatus { auction_id }) => {
+            auction_status(&db, auction_id).await.unwrap();
+            return None;
+        }
+        #[cfg(feature = "export")]
+        Some(Command::ExportFailures {
+            report,
+            format,
+            output,
+        }) => {
+            export_failures(&db, &report, format, &output)
+                .await
+                .unwrap();
+            return None;
+        }
+        #[cfg(feature = "export")]
+        Some(Command::Export { output }) => {
+            export(&db, range, args.batch_size, output.as_deref())
+                .await
+                .unwrap();
+            return None;
+        }
+        #[cfg(feature = "export")]
+        Some(Command::Archive) => {
+            let dir = args
+                .archive_dir
+                .context("archive needs --archive-dir")
+                .unwrap();
+            archive(&db, range, args.batch_size, &dir).await.unwrap();
+            return None;
+        }
+        #[cfg(feature = "export")]
+        Some(Command::VerifyArchive) => {
+            let dir = args
+                .archive_dir
+                .context("verify-archive needs --archive-dir")
+                .unwrap();
+            verify_archive(&db, &dir).await.unwrap();
+            return None;
+        }
+        #[cfg(feature = "object-storage")]
+        Some(Command::Upload {
+            file,
+            url,
+            object,
+            token,
+            chunk_bytes,
+        }) => {
+            upload(
+                &db,
+                &file,
+                &url,
+                object.as_deref(),
+                token.as_deref(),
+                chunk_bytes.get(),
+            )
+            .await
+            .unwrap();
+            return None;
+        }
+        Some(
+            Command::JsonSchema { .. } | Command::CompareReports { .. } | Command::RunAll { .. },
+        ) => unreachable!(),
+    };
+    if let Some(report) = &mut report {
+        record_table_sizes(&db, report, written, &tables_before)
+            .await
+            .unwrap();
+        if !db.config.dry_run {
+            record_failures(&db, report).await.unwrap();
+            // the migrations `redrive` can limit to single auctions
+            let retries = matches!(
+                report.migration.as_str(),
+                POPULATE_HISTORIC_AUCTIONS | FIX_MISSING_HISTORIC_AUCTIONS | CONVERT_EXECUTED_FEE
+            );
+            if retries && args.auto_retry_attempts > 0 {
+                retry_due_failures(&db, options, &report.migration, args.auto_retry_attempts)
+                    .await
+                    .unwrap();
+            }
+        }
+    }
+    if let Some(report) = &report {
+        // auctions arriving later are outside of a bounded range
+        let current_max_id = match range.end {
+            Some(_) => None,
+            None => fetch_max_id(&mut db.pool.acquire().await.unwrap())
+                .await
+                .unwrap(),
+        };
+        report
+            .follow_up(current_max_id)
+            .print(follow_up_command(&report.migration, range).as_deref());
+    }
+    report
+}
+
+/// Runs the steps of the cutover one after the other, timing each: populating
+/// `competition_auctions`, verifying it, converting the fees and verifying
+/// again. Meant for a staging copy of the database, as it writes like the
+/// real migrations do.
+#[cfg(feature = "destructive")]
+pub async fn rehearse(
+    db: &Postgres,
+    options: PopulateOptions,
+    giant_auction_bytes: i32,
+) -> Result<crate::report::Rehearsal> {
+    info!("starting rehearsal of the cutover");
+    let mut rehearsal = crate::report::Rehearsal::default();
+    let run_start = Instant::now();
+
+    let start = Instant::now();
+    let report = populate_historic_auctions(db, options, &[]).await?;
+    rehearsal.step("populate", start.elapsed(), report);
+
+    let start = Instant::now();
+    let report = verify(db, options.range, giant_auction_bytes).await?;
+    rehearsal.step("verify populated", start.elapsed(), report);
+
+    let start = Instant::now();
+    let report = convert_executed_fee(
+        db,
+        options.range,
+        options.batch_size,
+        options.throttle,
+        options.limit,
+    )
+    .await?;
+    rehearsal.step("convert fees", start.elapsed(), report);
+
+    let start = Instant::now();
+    let report = verify(db, options.range, giant_auction_bytes).await?;
+    rehearsal.step("verify converted", start.elapsed(), report);
+
+    rehearsal.total_seconds = run_start.elapsed().as_secs_f64();
+    Ok(rehearsal)
+}
+
+/// Runs the migration given by `args`, the arguments after `--` of `run-all`,
+/// against every profile of the `config` file, or only against `selected` if
+/// not empty, in that order. Up to `concurrency` profiles run at the same
+/// time. A profile whose run fails or panics doesn't stop the others.
+pub async fn run_all(
+    config: &std::path::Path,
+    selected: &[String],
+    concurrency: usize,
+    args: &[String],
+) -> Result<ProfileReports> {
+    let mut profiles = crate::profiles::read(config)?;
+    if !selected.is_empty() {
+        for name in selected {
+            anyhow::ensure!(profiles.contains_key(name), "unknown profile {name}");
+        }
+        profiles.retain(|name, _| selected.contains(name));
+    }
+    let runs = profiles
+        .into_iter()
+        .map(|(name, profile)| {
+            let args = profile.arguments(args);
+            (name, args)
+        })
+        .collect::<Vec<_>>();
+    // invalid arguments fail before anything runs
+    let mut parsed = Vec::new();
+    for (name, args) in runs {
+        let args = Arguments::try_parse_from(args)
+            .with_context(|| format!("arguments of profile {name}"))?;
+        if let Some(
+            Command::JsonSchema { .. } | Command::CompareReports { .. } | Command::RunAll { .. },
+        ) = args.command
+        {
+            anyhow::bail!("run-all only runs commands that use the database");
+        }
+        parsed.push((name, args));
+    }
+
+    info!(
+        "running against {} profiles, {concurrency} at a time",
+        parsed.len()
+    );
+    let results: Vec<_> = futures::stream::iter(parsed)
+        .map(|(name, args)| async move {
+            let span = tracing::info_span!("profile", name);
+            let result = AssertUnwindSafe(migrate(args).instrument(span))
+                .catch_unwind()
+                .await;
+            (name, result)
+        })
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+
+    let mut reports = ProfileReports::default();
+    for (name, result) in results {
+        match result {
+            Ok(Some(report)) => {
+                reports.reports.insert(name, report);
+            }
+            Ok(None) => info!("profile {name} has no report"),
+            Err(panic) => {
+                let message = panic_message(&*panic);
+                error!("profile {name} panicked: {message}");
+                reports.failed.insert(name, message.to_string());
+            }
+        }
+    }
+    Ok(reports)
+}
+
+const POPULATE_HISTORIC_AUCTIONS: &str = "populate_historic_auctions";
+const FIX_MISSING_HISTORIC_AUCTIONS: &str = "fix_missing_historic_auctions";
+const CONVERT_EXECUTED_FEE: &str = "convert_executed_fee";
+#[cfg(feature = "destructive")]
+const BACKFILL_EXECUTED_FEE_TOKEN: &str = "backfill_executed_fee_token";
+#[cfg(feature = "destructive")]
+const CONSOLIDATE_SURPLUS_FEE: &str = "consolidate_surplus_fee";
+#[cfg(feature = "destructive")]
+const RECOMPUTE_EXECUTED_AMOUNTS: &str = "recompute_executed_amounts";
+const VERIFY: &str = "verify";
+#[cfg(feature = "export")]
+const ARCHIVE: &str = "archive";
+#[cfg(feature = "object-storage")]
+const UPLOAD: &str = "upload";
+
+/// The columns of `configured` that `schema` has, warning about the others.
+fn existing_auction_columns(
+    configured: &[AuctionColumn],
+    schema: &crate::database_schema::Schema,
+) -> Vec<AuctionColumn> {
+    configured
+        .iter()
+        .copied()
+        .filter(|column| {
+            let exists = schema.auction_columns.contains(column);
+            if !exists {
+                warn!("competition_auctions has no column {column}, not writing it");
+            }
+            exists
+        })
+        .collect()
+}
+
+/// The privileges `command` needs, see [`crate::database_privileges`].
+fn required_grants(command: Option<&Command>) -> Vec<Grant> {
+    let table = |table, privilege| Grant::Table { table, privilege };
+    // `database_runs::create_tables` runs for every command
+    let mut grants = vec![
+        Grant::Schema {
+            privilege: "CREATE",
+        },
+        Grant::Owner {
+            table: "migration_runs",
+        },
+    ];
+    for tool_table in [
+        "migration_runs",
+        "migration_annotations",
+        "migration_ownership",
+        "migration_frontier",
+        "migration_progress",
+        "migration_failures",
+        "migration_auction_status",
+        "migration_settings",
+        "migration_uploads",
+        "migration_archives",
+        "order_execution_fee_proposals",
+        "fee_conversion_backup",
+        "migration_audit_log",
+    ] {
+        grants.extend([table(tool_table, "SELECT"), table(tool_table, "INSERT")]);
+    }
+    grants.push(table("migration_runs", "UPDATE"));
+    grants.push(table("migration_frontier", "UPDATE"));
+    grants.push(table("migration_progress", "UPDATE"));
+    grants.push(table("migration_failures", "UPDATE"));
+    grants.push(table("migration_auction_status", "UPDATE"));
+    grants.push(table("migration_settings", "UPDATE"));
+    grants.push(table("migration_uploads", "UPDATE"));
+    grants.push(table("migration_archives", "UPDATE"));
+    grants.push(table("order_execution_fee_proposals", "UPDATE"));
+    grants.push(table("fee_conversion_backup", "UPDATE"));
+
+    let orders = [table("orders", "SELECT"), table("jit_orders", "SELECT")];
+    let solver_competitions = [
+        table("solver_competitions", "SELECT"),
+        table("settlement_scores", "SELECT"),
+        table("surplus_capturing_jit_order_owners", "SELECT"),
+    ];
+    match command {
+        None | Some(Command::MigrateAuctions | Command::FixMissingAuctions) => {
+            grants.extend(solver_competitions);
+            grants.extend([
+                table("competition_auctions", "SELECT"),
+                table("competition_auctions", "INSERT"),
+            ]);
+        }
+        #[cfg(feature = "destructive")]
+        Some(
+            Command::ConvertFees
+            | Command::BackfillExecutedFeeToken
+            | Command::ConsolidateSurplusFee,
+        ) => {
+            grants.extend(orders);
+            grants.extend([
+                table("solver_competitions", "SELECT"),
+                table("order_execution", "SELECT"),
+                table("order_execution", "UPDATE"),
+            ]);
+            if let Some(Command::ConvertFees) = command {
+                grants.push(table("settlements", "SELECT"));
+            }
+        }
+        #[cfg(feature = "destructive")]
+        Some(Command::RecomputeExecutedAmounts) => {
+            grants.extend(orders);
+            grants.extend(solver_competitions);
+            grants.extend([
+                table("proposed_trade_executions", "SELECT"),
+                table("proposed_trade_executions", "INSERT"),
+                table("proposed_trade_executions", "UPDATE"),
+                table("settlements", "SELECT"),
+                table("trades", "SELECT"),
+            ]);
+        }
+        #[cfg(feature = "destructive")]
+        Some(Command::ApplyProposals | Command::Rollback { .. }) => grants.extend([
+            table("order_execution", "SELECT"),
+            table("order_execution", "UPDATE"),
+        ]),
+        #[cfg(feature = "destructive")]
+        Some(Command::ApplyFromFile { .. }) => grants.extend([
+            table("competition_auctions", "SELECT"),
+            table("competition_auctions", "INSERT"),
+            table("order_execution", "SELECT"),
+            table("order_execution", "UPDATE"),
+        ]),
+        #[cfg(feature = "destructive")]
+        Some(Command::Rehearse) => {
+            grants.extend(orders);
+            grants.extend(solver_competitions);
+            grants.extend([
+                table("competition_auctions", "SELECT"),
+                table("competition_auctions", "INSERT"),
+                table("order_execution", "SELECT"),
+                table("order_execution", "UPDATE"),
+                table("settlements", "SELECT"),
+            ]);
+        }
+        Some(Command::ValidateJson) => grants.extend(solver_competitions),
+        Some(Command::Redrive) => {
+            grants.extend(orders);
+            grants.extend(solver_competitions);
+            grants.extend([
+                table("competition_auctions", "SELECT"),
+                table("competition_auctions", "INSERT"),
+                table("order_execution", "SELECT"),
+                table("order_execution", "UPDATE"),
+                table("settlements", "SELECT"),
+            ]);
+        }
+        Some(Command::Verify) => {
+            grants.extend(orders);
+            grants.extend(solver_competitions);
+            grants.extend([
+                table("competition_auctions", "SELECT"),
+                table("order_execution", "SELECT"),
+                table("settlements", "SELECT"),
+            ]);
+        }
+        Some(Command::CheckTrades { .. }) => grants.extend([
+            table("order_execution", "SELECT"),
+            table("trades", "SELECT"),
+            table("settlements", "SELECT"),
+        ]),
+        #[cfg(feature = "export")]
+        Some(Command::Archive) => grants.extend(solver_competitions),
+        #[cfg(feature = "export")]
+        Some(Command::Export { .. }) => grants.push(table("competition_auctions", "SELECT")),
+        #[cfg(feature = "export")]
+        Some(Command::VerifyArchive) => (),
+        #[cfg(feature = "object-storage")]
+        Some(Command::Upload { .. }) => (),
+        #[cfg(feature = "export")]
+        Some(Command::ExportFailures { .. }) => {
+            grants.extend(orders);
+            grants.extend([
+                table("solver_competitions", "SELECT"),
+                table("order_execution", "SELECT"),
+            ]);
+        }
+        Some(Command::Scope { .. }) => {
+            grants.extend(orders);
+            grants.push(table("order_execution", "SELECT"));
+        }
+        Some(Command::ReportRemaining { .. }) => {
+            grants.extend(orders);
+            grants.extend([
+                table("solver_competitions", "SELECT"),
+                table("competition_auctions", "SELECT"),
+                table("order_execution", "SELECT"),
+            ]);
+        }
+        Some(
+            Command::MarkOwned { .. }
+            | Command::Annotate { .. }
+            | Command::AuctionStatus { .. }
+            | Command::SetChainId { .. }
+            | Command::ImportResolutions { .. }
+            | Command::JsonSchema { .. }
+            | Command::CompareReports { .. }
+            | Command::RunAll { .. },
+        ) => (),
+    }
+    grants
+}
+
+/// The tables `command` writes, each with the source table it ends up having
+/// a row per row of, if there is one.
+fn written_tables(command: Option<&Command>) -> &'static [(&'static str, Option<&'static str>)] {
+    match command {
+        None | Some(Command::MigrateAuctions | Command::FixMissingAuctions) => {
+            &[("competition_auctions", Some("solver_competitions"))]
+        }
+        Some(Command::Redrive) => &[
+            ("competition_auctions", Some("solver_competitions")),
+            ("order_execution", None),
+        ],
+        #[cfg(feature = "destructive")]
+        Some(Command::Rehearse) => &[
+            ("competition_auctions", Some("solver_competitions")),
+            ("order_execution", None),
+        ],
+        #[cfg(feature = "destructive")]
+        Some(
+            Command::ConvertFees
+            | Command::BackfillExecutedFeeToken
+            | Command::ConsolidateSurplusFee,
+        ) => &[("order_execution", None)],
+        #[cfg(feature = "destructive")]
+        Some(Command::RecomputeExecutedAmounts) => &[("proposed_trade_executions", None)],
+        #[cfg(feature = "destructive")]
+        Some(Command::ApplyProposals | Command::Rollback { .. }) => &[("order_execution", None)],
+        #[cfg(feature = "destructive")]
+        Some(Command::ApplyFromFile { .. }) => &[
+            ("competition_auctions", Some("solver_competitions")),
+            ("order_execution", None),
+        ],
+        _ => &[],
+    }
+}
+
+/// The migrations `command` runs, whose advisory locks it takes so that no
+/// other instance runs them at the same time.
+fn locked_migrations(command: Option<&Command>) -> &'static [&'static str] {
+    match command {
+        None | Some(Command::MigrateAuctions) => &[POPULATE_HISTORIC_AUCTIONS],
+        Some(Command::FixMissingAuctions) => &[FIX_MISSING_HISTORIC_AUCTIONS],
+        Some(Command::Redrive) => &[
+            POPULATE_HISTORIC_AUCTIONS,
+            FIX_MISSING_HISTORIC_AUCTIONS,
+            CONVERT_EXECUTED_FEE,
+        ],
+        #[cfg(feature = "destructive")]
+        Some(Command::Rehearse | Command::ApplyFromFile { .. }) => {
+            &[POPULATE_HISTORIC_AUCTIONS, CONVERT_EXECUTED_FEE]
+        }
+        #[cfg(feature = "destructive")]
+        Some(Command::ConvertFees | Command::ApplyProposals | Command::Rollback { .. }) => {
+            &[CONVERT_EXECUTED_FEE]
+        }
+        #[cfg(feature = "destructive")]
+        Some(Command::BackfillExecutedFeeToken) => &[BACKFILL_EXECUTED_FEE_TOKEN],
+        #[cfg(feature = "destructive")]
+        Some(Command::ConsolidateSurplusFee) => &[CONSOLIDATE_SURPLUS_FEE],
+        #[cfg(feature = "destructive")]
+        Some(Command::RecomputeExecutedAmounts) => &[RECOMPUTE_EXECUTED_AMOUNTS],
+        _ => &[],
+    }
+}
+
+/// Whether the database is ready for the `written` tables to be written: they
+/// exist, and the services schema is at least `min_schema_version` if given,
+/// so that the services reading them are deployed. Logs why it is not.
+async fn is_ready(
+    db: &Postgres,
+    written: &[(&str, Option<&str>)],
+    min_schema_version: Option<&crate::database_schema::Version>,
+) -> Result<bool> {
+    if written.is_empty() {
+        return Ok(true);
+    }
+    let mut ex = db.pool.acquire().await?;
+    let tables: Vec<&str> = written.iter().map(|(table, _)| *table).collect();
+    let missing = crate::database_schema::fetch_missing_tables(&mut ex, &tables)
+        .await
+        .context("fetch missing tables")?;
+    for table in &missing {
+        error!("table {table} does not exist");
+    }
+    let Some(min_schema_version) = min_schema_version else {
+        return Ok(missing.is_empty());
+    };
+    let version = crate::database_schema::fetch_services_version(&mut ex)
+        .await
+        .context("fetch services schema version")?;
+    let outdated = match &version {
+        Some(version) if version >= min_schema_version => false,
+        Some(version) => {
+            error!("services schema version {version} is older than {min_schema_version}");
+            true
+        }
+        None => {
+            error!("flyway_schema_history has no services schema version");
+            true
+        }
+    };
+    Ok(missing.is_empty() && !outdated)
+}
+
+/// Whether the connected database has the `expected_db_name` and belongs to
+/// the chain `expected_chain_id`, as far as they are given. Logs why it
+/// doesn't.
+async fn is_expected_database(
+    db: &Postgres,
+    expected_db_name: Option<&str>,
+    expected_chain_id: Option<u64>,
+) -> Result<bool> {
+    let mut ex = db.pool.acquire().await?;
+    let mut expected = true;
+    if let Some(expected_db_name) = expected_db_name {
+        let name = crate::database_schema::fetch_database_name(&mut ex)
+            .await
+            .context("fetch database name")?;
+        if name != expected_db_name {
+            error!("connected to database {name}, expected {expected_db_name}");
+            expected = false;
+        }
+    }
+    if let Some(expected_chain_id) = expected_chain_id {
+        let chain_id = crate::database_runs::fetch_setting(&mut ex, crate::database_runs::CHAIN_ID)
+            .await
+            .context("fetch chain id")?;
+        match chain_id {
+            Some(chain_id) if chain_id == expected_chain_id.to_string() => (),
+            Some(chain_id) => {
+                error!("the database belongs to chain {chain_id}, expected {expected_chain_id}");
+                expected = false;
+            }
+            None => {
+                error!("the database has no chain id, record it with set-chain-id");
+                expected = false;
+            }
+        }
+    }
+    Ok(expected)
+}
+
+/// Fetches the statistics of the `written` tables and their source tables,
+/// updating the table metrics.
+async fn fetch_table_stats(
+    db: &Postgres,
+    written: &[(&str, Option<&str>)],
+) -> Result<Vec<TableStats>> {
+    let tables: Vec<&str> = written
+        .iter()
+        .flat_map(|(table, source)| std::iter::once(*table).chain(*source))
+        .collect();
+    let stats = crate::database_tables::fetch_stats(&mut *db.pool.acquire().await?, &tables)
+        .await
+        .context("fetch table statistics")?;
+    let metrics = crate::metrics::get();
+    for table in &stats {
+        metrics
+            .table_bytes
+            .with_label_values(&[&table.table])
+            .set(table.total_bytes);
+        metrics
+            .table_dead_tuples
+            .with_label_values(&[&table.table])
+            .set(table.dead_tuples);
+    }
+    Ok(stats)
+}
+
+/// Records in `report` how the `written` tables grew since `before` and how
+/// large they will get, and prints it.
+async fn record_table_sizes(
+    db: &Postgres,
+    report: &mut Report,
+    written: &[(&str, Option<&str>)],
+    before: &[TableStats],
+) -> Result<()> {
+    let after = fetch_table_stats(db, written).await?;
+    let find = |stats: &[TableStats], table: &str| {
+        stats.iter().find(|stats| stats.table == table).cloned()
+    };
+    for (table, source) in written {
+        let (Some(before), Some(after_table)) = (find(before, table), find(&after, table)) else {
+            continue;
+        };
+        let final_rows = source
+            .and_then(|source| find(&after, source))
+            .map(|source| source.live_tuples);
+        report.tables.insert(
+            table.to_string(),
+            TableSize::new(&before, &after_table, final_rows),
+        );
+    }
+    report.print_tables();
+    Ok(())
+}
+
+/// The owner of the ranges this tool writes, see
+/// [`crate::database_runs::Ownership`].
+const OWNER: &str = "data-migration";
+
+/// Skips `auction_id` if it is in one of the ranges owned by other writers.
+fn skip_foreign(report: &mut Report, foreign: &[Ownership], auction_id: i64) -> bool {
+    let Some(owner) = crate::database_runs::owner_of(foreign, auction_id) else {
+        return false;
+    };
+    report.skip(
+        SkipReason::Owned,
+        auction_id,
+        format_args!("auction {} is owned by {}", auction_id, owner),
+    );
+    true
+}
+
+/// The invocation that runs `migration` again.
+fn follow_up_command(migration: &str, range: AuctionRange) -> Option<String> {
+    let command = match migration {
+        POPULATE_HISTORIC_AUCTIONS => "migrate-auctions",
+        FIX_MISSING_HISTORIC_AUCTIONS => "fix-missing-auctions",
+        #[cfg(feature = "destructive")]
+        CONVERT_EXECUTED_FEE => "convert-fees",
+        #[cfg(feature = "destructive")]
+        BACKFILL_EXECUTED_FEE_TOKEN => "backfill-executed-fee-token",
+        #[cfg(feature = "destructive")]
+        CONSOLIDATE_SURPLUS_FEE => "consolidate-surplus-fee",
+        #[cfg(feature = "destructive")]
+        RECOMPUTE_EXECUTED_AMOUNTS => "recompute-executed-amounts",
+        _ => return None,
+    };
+    let mut invocation = "data-migration".to_string();
+    if let Some(start) = range.start {
+        invocation.push_str(&format!(" --start-auction-id {start}"));
+    }
+    if let Some(end) = range.end {
+        invocation.push_str(&format!(" --end-auction-id {end}"));
+    }
+    Some(format!("{invocation} {command}"))
+}
+
+/// Adds a note to a run or auction, or prints the existing notes.
+pub async fn annotate(
+    db: &Postgres,
+    run_id: Option<i64>,
+    auction_id: Option<i64>,
+    note: Option<String>,
+) -> Result<()> {
+    let mut ex = db.pool.acquire().await?;
+    match note {
+        Some(note) => {
+            crate::database_runs::annotate(&mut ex, run_id, auction_id, &note).await?;
+            info!("annotation saved");
+        }
+        None => {
+            let annotations =
+                crate::database_runs::fetch_annotations(&mut ex, run_id, auction_id).await?;
+            for annotation in annotations {
+                info!(
+                    "{} run: {:?}, auction: {:?}: {}",
+                    annotation.created_at,
+                    annotation.run_id,
+                    annotation.auction_id,
+                    annotation.note
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Logs which parts of `auction_id` are written and which are missing.
+pub async fn auction_status(db: &Postgres, auction_id: i64) -> Result<()> {
+    use crate::database_auction_status::{complete, names, NO_WINNER};
+
+    let mut ex = db.pool.acquire().await?;
+    let status = crate::database_auction_status::fetch(&mut ex, auction_id)
+        .await?
+        .unwrap_or_default();
+    if status & NO_WINNER != 0 {
+        info!("auction {auction_id} has no winner, so no executions or settlements");
+    }
+    let complete = complete(status);
+    match status & complete == complete {
+        true => info!("auction {auction_id} is fully normalized"),
+        false => info!(
+            "auction {auction_id} has {:?} written, {:?} missing",
+            names(status),
+            names(complete & !status)
+        ),
+    }
+    Ok(())
+}
+
+/// Records the auctions `report` failed to process in `migration_failures`,
+/// see [`SkipReason::is_failure`].
+async fn record_failures(db: &Postgres, report: &Report) -> Result<()> {
+    let failures: Vec<(i64, &str, &str)> = report
+        .skipped
+        .iter()
+        .filter(|(reason, _)| reason.is_failure())
+        .flat_map(|(reason, auctions)| {
+            auctions.iter().map(|auction_id| {
+                let error = report.errors.get(auction_id).map_or("", String::as_str);
+                (*auction_id, reason.as_str(), error)
+            })
+        })
+        .collect();
+    crate::database_failures::record(&mut *db.pool.acquire().await?, &report.migration, &failures)
+        .await
+        .context("record failures")?;
+    if !failures.is_empty() {
+        info!(
+            "recorded {} failures, retry them with redrive",
+            big(failures.len())
+        );
+    }
+    Ok(())
+}
+
+/// Runs the migrations again over only the auctions they failed to process,
+/// as recorded in `migration_failures`, and resolves the failures of those
+/// that aren't skipped this time. Failures with retry arguments, see
+/// [`import_resolutions`], are retried with the options of those instead of
+/// `options`. Migrations that can't be limited to a range of auctions are
+/// left to be run again as a whole.
+pub async fn redrive(db: &Postgres, options: PopulateOptions) -> Result<()> {
+    let failures =
+        crate::database_failures::fetch_unresolved(&mut *db.pool.acquire().await?).await?;
+    if failures.is_empty() {
+        info!("no unresolved failures to redrive");
+    }
+    redrive_failures(db, options, failures).await
+}
+
+/// Redrives the failures of `migration` that are due for an automatic retry,
+/// see [`crate::database_failures::is_due`].
+async fn retry_due_failures(
+    db: &Postgres,
+    options: PopulateOptions,
+    migration: &str,
+    max_attempts: i32,
+) -> Result<()> {
+    let pending =
+        crate::database_failures::fetch_pending(&mut *db.pool.acquire().await?, migration)
+            .await
+            .context("fetch pending failures")?;
+    let due: Vec<_> = pending
+        .into_iter()
+        .filter(|pending| {
+            crate::database_failures::is_due(
+                pending.failure.attempts,
+                pending.runs_since,
+                max_attempts,
+            )
+        })
+        .map(|pending| pending.failure)
+        .collect();
+    if due.is_empty() {
+        return Ok(());
+    }
+    info!(
+        "retrying {} failures of earlier runs that are due",
+        big(due.len())
+    );
+    redrive_failures(db, options, due).await
+}
+
+/// Redrives `failures`, see [`redrive`].
+async fn redrive_failures(
+    db: &Postgres,
+    options: PopulateOptions,
+    failures: Vec<crate::database_failures::Failure>,
+) -> Result<()> {
+    let mut failed: BTreeMap<(String, Option<String>), BTreeSet<i64>> = BTreeMap::new();
+    for failure in failures {
+        failed
+            .entry((failure.migration, failure.retry_args))
+            .or_default()
+            .insert(failure.auction_id);
+    }
+
+    'migrations: for ((migration, retry_args), auction_ids) in failed {
+        let mut db = db.clone();
+        let options = match &retry_args {
+            Some(retry_args) => {
+                info!(
+                    "redriving {} auctions of {migration} with {retry_args}",
+                    big(auction_ids.len())
+                );
+                let args = retry_arguments(retry_args)?;
+                db.config.fallbacks = crate::sources::Fallbacks::from_arguments(&args)?;
+                db.config.partial_writes |= args.partial_writes;
+                PopulateOptions::from_arguments(&args)
+            }
+            None => {
+                info!(
+                    "redriving {} auctions of {migration}",
+                    big(auction_ids.len())
+                );
+                options
+            }
+        };
+        let db = &db;
+        let mut ranges: Vec<(i64, i64)> = Vec::new();
+        for &auction_id in &auction_ids {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == auction_id => *end = auction_id,
+                _ => ranges.push((auction_id, auction_id)),
+            }
+        }
+        let mut report = Report::new(&migration);
+        for (start, end) in ranges {
+            let range = AuctionRange {
+                start: Some(start),
+                end: Some(end),
+            };
+            let redriven = match migration.as_str() {
+                POPULATE_HISTORIC_AUCTIONS | FIX_MISSING_HISTORIC_AUCTIONS => {
+                    fix_missing_historic_auctions(db, PopulateOptions { range, ..options }).await?
+                }
+                #[cfg(feature = "destructive")]
+                CONVERT_EXECUTED_FEE => {
+                    convert_executed_fee(db, range, options.batch_size, options.throttle, None)
+                        .await?
+                }
+                _ => {
+                    warn!(
+                        "{migration} can't be redriven for single auctions, run it again instead"
+                    );
+                    continue 'migrations;
+                }
+            };
+            report.merge(redriven);
+        }
+
+        if db.config.dry_run {
+            report.print();
+            continue;
+        }
+        record_failures(db, &report).await?;
+        let skipped: BTreeSet<i64> = report.skipped.values().flatten().copied().collect();
+        let still_failing: BTreeSet<i64> = auction_ids.intersection(&skipped).copied().collect();
+        let resolved: Vec<i64> = auction_ids.difference(&still_failing).copied().collect();
+        let resolved = crate::database_failures::resolve(
+            &mut *db.pool.acquire().await?,
+            &migration,
+            &resolved,
+        )
+        .await
+        .context("resolve failures")?;
+        info!(
+            "{migration}: resolved {} failures, {} auctions still fail",
+            big(resolved),
+            big(still_failing.len())
+        );
+        report.print();
+    }
+    Ok(())
+}
+
+/// Parses the global arguments a failure is retried with, space separated.
+fn retry_arguments(retry_args: &str) -> Result<Arguments> {
+    Arguments::try_parse_from(
+        std::iter::once("data-migration").chain(retry_args.split_whitespace()),
+    )
+    .with_context(|| format!("invalid retry arguments {retry_args:?}"))
+}
+
+/// Applies the resolutions filled into a file written by `export-failures`:
+/// tombstones the failures resolved with `skip` and queues those resolved
+/// with `retry` for the next redrive, with the arguments given.
+pub async fn import_resolutions(db: &Postgres, path: &std::path::Path) -> Result<()> {
+    let file = std::fs::File::open(path).context("open resolutions file")?;
+    let resolved = crate::failures::read_resolved(file)?;
+    // refuse the whole file before changing anything if an argument is wrong
+    for (_, resolution) in &resolved {
+        if let crate::failures::Resolution::Retry(args) = resolution {
+            retry_arguments(&args.join(" "))?;
+        }
+    }
+
+    let mut ex = db.pool.begin().await?;
+    let (mut tombstoned, mut retried) = (0, 0);
+    for (failure, resolution) in resolved {
+        match resolution {
+            crate::failures::Resolution::Skip => {
+                crate::database_failures::tombstone(
+                    &mut ex,
+                    &failure.migration,
+                    failure.auction_id,
+                    failure.reason.as_str(),
+                    &failure.error,
+                )
+                .await
+                .context("tombstone failure")?;
+                tombstoned += 1;
+            }
+            crate::failures::Resolution::Retry(args) => {
+                let args = Some(args.join(" ")).filter(|args| !args.is_empty());
+                crate::database_failures::retry(
+                    &mut ex,
+                    &failure.migration,
+                    failure.auction_id,
+                    failure.reason.as_str(),
+                    &failure.error,
+                    args.as_deref(),
+                )
+                .await
+                .context("queue failure for retry")?;
+                retried += 1;
+            }
+        }
+    }
+    ex.commit().await?;
+    info!("tombstoned {tombstoned} failures, queued {retried} for the next redrive");
+    Ok(())
+}
+
+/// Writes the auctions skipped in the report at `report_path` with their
+/// context to `output`.
+#[cfg(feature = "export")]
+pub async fn export_failures(
+    db: &Postgres,
+    report_path: &std::path::Path,
+    format: crate::failures::ExportFormat,
+    output: &std::path::Path,
+) -> Result<()> {
+    let report = Report::read(report_path)?;
+    let auction_ids: Vec<i64> = report
+        .skipped
+        .values()
+        .flatten()
+        .copied()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let contexts =
+        crate::database_failures::fetch_contexts(&mut *db.pool.acquire().await?, &auction_ids)
+            .await
+            .context("fetch failure contexts")?;
+    let failures = crate::failures::from_report(&report, &contexts);
+    let file = std::fs::File::create(output).context("create failures file")?;
+    crate::failures::write(&failures, format, file)?;
+    info!(
+        "exported {} failures to {}",
+        big(failures.len()),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Writes the auctions in `range` from `competition_auctions` as JSON Lines to
+/// `output`, or to stdout, fetching `batch_size` at a time, see
+/// [`crate::exports`].
+#[cfg(feature = "export")]
+pub async fn export(
+    db: &Postgres,
+    range: AuctionRange,
+    batch_size: i64,
+    output: Option<&std::path::Path>,
+) -> Result<()> {
+    use std::io::Write;
+
+    let writer: Box<dyn Write + Send> = match output {
+        Some(path) => Box::new(std::fs::File::create(path).context("create export file")?),
+        None => Box::new(std::io::stdout()),
+    };
+    let mut writer = std::io::BufWriter::new(writer);
+
+    let mut ex = db.pool.acquire().await?;
+    let mut after = range.start.map_or(i64::MIN, |start| start - 1);
+    let before = range.end.map_or(i64::MAX, |end| end + 1);
+    let mut exported = 0;
+    loop {
+        let auctions =
+            crate::database_solver_competition::fetch_page(&mut ex, after, before, batch_size)
+                .await
+                .context("fetch auctions")?;
+        let Some(last) = auctions.last() else {
+            break;
+        };
+        after = last.id;
+        crate::exports::write(&auctions, &mut writer)?;
+        exported += auctions.len();
+    }
+    writer.flush().context("write export")?;
+    info!("exported {} auctions", big(exported));
+    Ok(())
+}
+
+/// Writes the solver competition JSON of the auctions in `range` to `dir`, in
+/// objects of `batch_size` auctions with a manifest each, see
+/// [`crate::archives`].
+#[cfg(feature = "export")]
+pub async fn archive(
+    db: &Postgres,
+    range: AuctionRange,
+    batch_size: i64,
+    dir: &std::path::Path,
+) -> Result<()> {
+    info!(
+        "starting archive of solver competitions to {}",
+        dir.display()
+    );
+    std::fs::create_dir_all(dir).context("create archive directory")?;
+
+    let mut ex = db.pool.acquire().await?;
+    let run_id =
+        crate::database_runs::start(&mut ex, ARCHIVE, None, db.config.schema_variant.as_deref())
+            .await?;
+    info!("run id: {run_id}");
+
+    let mut after = range.start.map_or(i64::MIN, |start| start - 1);
+    let before = range.end.map_or(i64::MAX, |end| end + 1);
+    let (mut objects, mut rows) = (0, 0);
+    loop {
+        // archives hold the complete JSON, however large
+        let batch = fetch_batch(
+            &mut ex,
+            after,
+            before,
+            Direction::Ascending,
+            batch_size,
+            i32::MAX,
+        )
+        .await
+        .context("fetch solver competitions")?;
+        let competitions: Vec<_> = batch
+            .into_iter()
+            .map(|competition| (competition.id, competition.json))
+            .collect();
+        let Some((bytes, manifest)) = crate::archives::object(&competitions)? else {
+            break;
+        };
+        after = manifest.end_auction_id;
+
+        std::fs::write(dir.join(&manifest.object), bytes).context("write archive object")?;
+        let file =
+            std::fs::File::create(dir.join(crate::archives::manifest_name(&manifest.object)))
+                .context("create manifest")?;
+        serde_json::to_writer_pretty(file, &manifest).context("write manifest")?;
+        crate::database_runs::save_archive(&mut ex, run_id, &manifest)
+            .await
+            .context("save archive")?;
+        info!(
+            "archived auctions {}..={} to {}",
+            manifest.start_auction_id, manifest.end_auction_id, manifest.object
+        );
+        objects += 1;
+        rows += manifest.rows;
+    }
+    info!(
+        "archived {} auctions in {} objects",
+        big(rows),
+        big(objects)
+    );
+
+    crate::database_runs::finish(&mut ex, run_id).await?;
+    Ok(())
+}
+
+/// Checks the objects in `dir` against their manifests and records the result
+/// of each in `migration_archives`.
+#[cfg(feature = "export")]
+pub async fn verify_archive(db: &Postgres, dir: &std::path::Path) -> Result<()> {
+    info!("starting verification of the archive in {}", dir.display());
+
+    let manifests = crate::archives::read_manifests(dir)?;
+    let mut ex = db.pool.acquire().await?;
+    let mut broken = 0;
+    for manifest in &manifests {
+        let problems = match std::fs::read(dir.join(&manifest.object)) {
+            Ok(bytes) => manifest.check(&bytes),
+            Err(err) => vec![format!("can't be read: {err}")],
+        };
+        for problem in &problems {
+            warn!("{}: {problem}", manifest.object);
+        }
+        if !problems.is_empty() {
+            broken += 1;
+        }
+        crate::database_runs::save_archive_verification(&mut ex, manifest, problems.is_empty())
+            .await
+            .context("save archive verification")?;
+    }
+    info!(
+        "verified {} archive objects, {} broken",
+        big(manifests.len()),
+        big(broken)
+    );
+    Ok(())
+}
+
+/// Uploads `file` to `object`, its file name by default, in the bucket with
+/// the upload endpoint `url`, continuing the last upload of it if it is
+/// unfinished, see [`crate::uploads`].
+#[cfg(feature = "object-storage")]
+pub async fn upload(
+    db: &Postgres,
+    file: &std::path::Path,
+    url: &url::Url,
+    object: Option<&str>,
+    token: Option<&str>,
+    chunk_bytes: usize,
+) -> Result<()> {
+    use {
+        crate::uploads::{Progress, CHUNK_ALIGNMENT},
+        tokio::io::{AsyncReadExt, AsyncSeekExt},
+    };
+
+    let object = match object {
+        Some(object) => object.to_string(),
+        None => file
+            .file_name()
+            .context("file has no name")?
+            .to_string_lossy()
+            .into_owned(),
+    };
+    let chunk_bytes = (chunk_bytes / CHUNK_ALIGNMENT).max(1) * CHUNK_ALIGNMENT;
+    let mut reader = tokio::fs::File::open(file)
+        .await
+        .context("open upload file")?;
+    let size = reader.metadata().await.context("read file size")?.len();
+    let client = crate::uploads::client()?;
+    let mut ex = db.pool.acquire().await?;
+    let run_id =
+        crate::database_runs::start(&mut ex, UPLOAD, None, db.config.schema_variant.as_deref())
+            .await?;
+    info!("run id: {run_id}");
+
+    let previous = crate::database_runs::fetch_upload(&mut ex, &object)
+        .await
+        .context("fetch upload")?
+        .filter(|upload| upload.size == size as i64);
+    let resumed = match &previous {
+        Some(upload) if upload.completed => {
+            info!("{object} is already uploaded");
+            crate::database_runs::finish(&mut ex, run_id).await?;
+            return Ok(());
+        }
+        Some(upload) => crate::uploads::query(&client, &upload.session_url, size)
+            .await
+            .context("query upload")?
+            .map(|progress| (upload.session_url.clone(), progress)),
+        None => None,
+    };
+    let (session, mut progress) = match resumed {
+        Some((session, progress)) => {
+            info!("resuming the upload of {object}");
+            (session, progress)
+        }
+        None => {
+            let session = crate::uploads::start(&client, url, &object, token, size)
+                .await
+                .context("start upload")?;
+            crate::database_runs::start_upload(&mut ex, &object, run_id, size as i64, &session)
+                .await
+                .context("save upload")?;
+            (session, Progress::Incomplete(0))
+        }
+    };
+
+    while let Progress::Incomplete(offset) = progress {
+        info!("uploaded {} of {} bytes", big(offset), big(size));
+        let len = chunk_bytes.min(size.saturating_sub(offset) as usize);
+        let mut chunk = vec![0; len];
+        reader
+            .seek(std::io::SeekFrom::Start(offset))
+            .await
+            .context("seek upload file")?;
+        reader
+            .read_exact(&mut chunk)
+            .await
+            .context("read upload file")?;
+        progress = crate::uploads::send(&client, &session, offset, chunk, size)
+            .await
+            .with_context(|| format!("upload bytes from {offset}"))?;
+        if let Progress::Incomplete(uploaded) = progress {
+            crate::database_runs::save_upload_progress(
+                &mut ex,
+                &object,
+                run_id,
+                uploaded as i64,
+                false,
+            )
+            .await
+            .context("save upload progress")?;
+        }
+    }
+    crate::database_runs::save_upload_progress(&mut ex, &object, run_id, size as i64, true)
+        .await
+        .context("save upload progress")?;
+    info!("uploaded {object}, {} bytes", big(size));
+
+    crate::database_runs::finish(&mut ex, run_id).await?;
+    Ok(())
+}
+
+/// Prints the largest ranges of auctions not yet processed by each migration.
+pub async fn report_remaining(db: &Postgres, top: i64) -> Result<()> {
+    let mut ex = db.pool.acquire().await?;
+    let print = |migration: &str, ranges: Vec<IdRange>| {
+        info!("{migration}: {} largest unprocessed ranges", ranges.len());
+        for range in ranges {
+            info!(
+                "    {}..={} ({} auctions)",
+                range.start, range.end, range.count
+            );
+        }
+    };
+
+    let ranges = crate::database_solver_competition::fetch_unmigrated_ranges(&mut ex, top)
+        .await
+        .context("fetch unmigrated ranges")?;
+    print(POPULATE_HISTORIC_AUCTIONS, ranges);
+
+    let ranges = crate::database_order_executions::fetch_unconverted_ranges(&mut ex, top)
+        .await
+        .context("fetch unconverted ranges")?;
+    print(CONVERT_EXECUTED_FEE, ranges);
+
+    Ok(())
+}
+
+/// Prints how many order executions the fee conversion still has to convert
+/// per bucket of `bucket_size` auction ids, up to `top` buckets with the most
+/// first.
+pub async fn scope(db: &Postgres, bucket_size: i64, top: Option<i64>) -> Result<()> {
+    let buckets = crate::database_order_executions::count_unconverted_buckets(
+        &mut *db.pool.acquire().await?,
+        bucket_size,
+        top.unwrap_or(i64::MAX),
+    )
+    .await
+    .context("count unconverted order executions")?;
+    info!(
+        "{}: {} order executions to convert in {} buckets of {} auctions",
+        CONVERT_EXECUTED_FEE,
+        big(buckets.iter().map(|bucket| bucket.total()).sum::<i64>()),
+        big(buckets.len()),
+        big(bucket_size)
+    );
+    for bucket in buckets {
+        info!(
+            "    {}..={}: {} ({} sell, {} buy, {} without fee token)",
+            bucket.start,
+            bucket.end,
+            big(bucket.total()),
+            big(bucket.sell),
+            big(bucket.buy),
+            big(bucket.without_token)
+        );
+    }
+    Ok(())
+}
+
+/// Reads auction ids from a file with one id per line, ignoring empty lines
+/// and lines starting with `#`.
+fn read_auction_ids(path: &std::path::Path) -> Result<Vec<i64>> {
+    let content = std::fs::read_to_string(path).context("read auction ids")?;
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            line.parse()
+                .with_context(|| format!("invalid auction id {line:?}"))
+        })
+        .collect()
+}
+
+/// Auction ids `start..=end` a migration is limited to, unbounded on the
+/// sides that are `None`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AuctionRange {
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+}
+
+/// Options of [`populate_historic_auctions`].
+#[derive(Clone, Copy, Debug)]
+pub struct PopulateOptions {
+    /// Don't read the JSON of auctions that get skipped anyway, see
+    /// [`fetch_batch_metadata_first`].
+    pub metadata_first: bool,
+    /// Auctions whose stored JSON is larger are processed alone, in their own
+    /// transaction, and without reading their solutions.
+    pub giant_auction_bytes: i32,
+    /// Only fetch auctions missing from `competition_auctions`, see
+    /// [`fetch_batch_missing`].
+    pub only_missing: bool,
+    /// Start at the first gap in `competition_auctions` going down from the
+    /// snapshot, see [`fetch_frontier`].
+    pub resume_from_frontier: bool,
+    /// Also go up from the first gap above the oldest auction, until meeting
+    /// the descending pass. Implies `resume_from_frontier`.
+    pub bidirectional: bool,
+    /// Only populate these auctions.
+    pub range: AuctionRange,
+    /// Auctions fetched and committed together.
+    pub batch_size: i64,
+    /// Split the auctions into this many ranges populated concurrently, see
+    /// [`populate_in_workers`].
+    pub workers: usize,
+    /// Skip auctions that take longer to populate.
+    pub auction_timeout: Option<Duration>,
+    /// Pause between batches.
+    pub throttle: Duration,
+    /// Stop after processing this many auctions.
+    pub limit: Option<usize>,
+}
+
+impl PopulateOptions {
+    pub fn from_arguments(args: &Arguments) -> Self {
+        Self {
+            metadata_first: args.metadata_first,
+            giant_auction_bytes: args.giant_auction_bytes,
+            only_missing: args.only_missing,
+            resume_from_frontier: args.resume_from_frontier,
+            bidirectional: args.bidirectional,
+            range: AuctionRange {
+                start: args.start_auction_id,
+                end: args.end_auction_id,
+            },
+            batch_size: args.batch_size,
+            workers: args.workers.get(),
+            auction_timeout: args.auction_timeout_secs.map(Duration::from_secs),
+            throttle: Duration::from_millis(args.throttle_ms),
+            limit: args.limit.map(NonZero::get),
+        }
+    }
+
+    /// The options for the next batch after `processed` auctions, with a
+    /// batch size that doesn't go beyond the `limit`. `None` once the limit
+    /// is reached.
+    fn within_limit(self, processed: usize) -> Option<Self> {
+        let Some(limit) = self.limit else {
+            return Some(self);
+        };
+        let remaining = limit
+            .checked_sub(processed)
+            .filter(|remaining| *remaining > 0)?;
+        Some(Self {
+            batch_size: self.batch_size.min(remaining as i64),
+            ..self
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct PopulateStats {
+    auctions_without_winner: usize,
+    /// Auctions that were written or failed to be written.
+    auctions: usize,
+    timings: Timings,
+}
+
+impl PopulateStats {
+    /// Adds the stats of another worker of the same run.
+    fn merge(&mut self, other: Self) {
+        self.auctions_without_winner += other.auctions_without_winner;
+        self.auctions += other.auctions;
+        self.timings.merge(other.timings);
+    }
+}
+
+/// Populates `competition_auctions`, first the `prioritized` auctions in the
+/// given order and then all others.
+#[instrument(skip_all)]
+pub async fn populate_historic_auctions(
+    db: &Postgres,
+    options: PopulateOptions,
+    prioritized: &[i64],
+) -> Result<Report> {
+    info!("starting data migration for auction data");
+    let mut report = Report::new(POPULATE_HISTORIC_AUCTIONS);
+
+    let mut ex = db.pool.begin().await?;
+
+    let snapshot_max_id = fetch_max_id(&mut ex)
+        .await
+        .context("fetch highest auction id")?;
+    let run_id = crate::database_runs::start(
+        &mut *db.pool.acquire().await?,
+        POPULATE_HISTORIC_AUCTIONS,
+        snapshot_max_id,
+        db.config.schema_variant.as_deref(),
+    )
+    .await?;
+    info!("run id: {run_id}, snapshot upper bound: {snapshot_max_id:?}");
+    report.snapshot_max_id = snapshot_max_id;
+
+    let Some(snapshot_max_id) = snapshot_max_id else {
+        info!("solver_competitions is empty, nothing to process");
+        return Ok(report);
+    };
+    let range = options.range;
+    let max_id = range
+        .end
+        .map_or(snapshot_max_id, |end| end.min(snapshot_max_id));
+    // exclusive lower bound of all batches
+    let floor = range.start.map_or(i64::MIN, |start| start - 1);
+
+    let from_frontier =
+        options.resume_from_frontier || options.bidirectional || !prioritized.is_empty();
+    // only the default pass down from the oldest auction is checkpointed, the
+    // others find their start from the migrated auctions
+    let checkpointed = !from_frontier && range.end.is_none();
+    let checkpoint = match checkpointed {
+        true => crate::database_runs::fetch_progress(&mut ex, POPULATE_HISTORIC_AUCTIONS)
+            .await
+            .context("fetch checkpoint")?,
+        false => None,
+    };
+    // a crashed run with workers is resumed with the ranges it split the
+    // auctions into, whatever the number of workers now
+    let worker_checkpoints = match checkpointed {
+        true => crate::database_runs::fetch_worker_progress(&mut ex, POPULATE_HISTORIC_AUCTIONS)
+            .await
+            .context("fetch worker checkpoints")?,
+        false => Vec::new(),
+    };
+    if !worker_checkpoints.is_empty() {
+        info!(
+            "resuming the {} workers of a crashed run",
+            worker_checkpoints.len()
+        );
+        ex.commit().await?;
+        return populate_in_workers(
+            db,
+            options,
+            run_id,
+            worker_checkpoints,
+            checkpointed,
+            report,
+        )
+        .await;
+    }
+    let descending_start = match from_frontier {
+        true => fetch_frontier(&mut ex, max_id, Direction::Descending)
+            .await
+            .context("fetch frontier")?
+            .map(|frontier| frontier + 1),
+        // reprocess the whole range
+        false if range.end.is_some() => Some(max_id + 1),
+        false if checkpoint.is_some() => {
+            let checkpoint = checkpoint.unwrap();
+            info!("resuming from the checkpoint of a crashed run at auction {checkpoint}");
+            Some(checkpoint)
+        }
+        // continue below the lowest auction the earlier passes down reached,
+        // ignoring the ranges completed otherwise, which may have gaps above them
+        false => crate::database_runs::fetch_lowest_covered(&mut ex, POPULATE_HISTORIC_AUCTIONS)
+            .await
+            .context("fetch lowest covered auction id")?,
+    };
+    let Some(descending_start) = descending_start else {
+        match from_frontier {
+            true => info!("competition_auctions covers all auctions, nothing to process"),
+            false => info!("competition_auctions is empty, nothing to process"),
+        }
+        return Ok(report);
+    };
+    let descending_start = descending_start.min(max_id + 1);
+    if options.workers > 1 {
+        let lowest = fetch_min_id(&mut ex)
+            .await
+            .context("fetch lowest auction id")?
+            .unwrap_or(snapshot_max_id);
+        let workers = partition(
+            (floor + 1).max(lowest),
+            descending_start - 1,
+            options.workers,
+        );
+        info!(
+            "populating auctions below {descending_start} with {} workers",
+            workers.len()
+        );
+        ex.commit().await?;
+        return populate_in_workers(db, options, run_id, workers, checkpointed, report).await;
+    }
+    let mut cursors = vec![Cursor::new(Direction::Descending, descending_start, range)];
+
+    if options.bidirectional {
+        let ascending_start = fetch_frontier(&mut ex, max_id, Direction::Ascending)
+            .await
+            .context("fetch frontier")?
+            // there is a gap, since there is a descending frontier
+            .context("no ascending frontier")?
+            - 1;
+        let ascending_start = ascending_start.max(floor);
+        info!(
+            "resuming descending from auction {descending_start} and ascending from auction {ascending_start}"
+        );
+        cursors.push(Cursor::new(Direction::Ascending, ascending_start, range));
+    } else if from_frontier {
+        info!("resuming from the frontier at auction {descending_start}");
+    }
+    ex.commit().await?;
+
+    let mut stats = PopulateStats::default();
+    let run_start = Instant::now();
+
+    if checkpoint.is_some() {
+        cursors[0].position = verify_checkpoint(
+            db,
+            &mut report,
+            &mut stats,
+            options,
+            floor,
+            descending_start,
+            max_id + 1,
+        )
+        .await?;
+    }
+
+    // prioritized auctions that are not populated yet, skipped by the cursors
+    // once done
+    let mut queue = Vec::new();
+    for &auction_id in prioritized {
+        if auction_id > snapshot_max_id {
+            info!("prioritized auction {auction_id} is after the snapshot, leaving it");
+        } else if auction_id > max_id || auction_id <= floor {
+            info!("prioritized auction {auction_id} is outside of the range, leaving it");
+        } else if !queue.contains(&auction_id) {
+            queue.push(auction_id);
+        }
+    }
+    let existing = fetch_existing(&mut *db.pool.acquire().await?, &queue)
+        .await
+        .context("fetch existing prioritized auctions")?;
+    queue.retain(|auction_id| !existing.contains(auction_id));
+    info!("populating {} prioritized auctions first", big(queue.len()));
+    let mut done = BTreeSet::new();
+    for auction_id in queue {
+        if options.within_limit(stats.auctions).is_none() {
+            break;
+        }
+        info!("populating prioritized auction {auction_id}");
+        let batch = Batch {
+            direction: Direction::Descending,
+            after: auction_id - 1,
+            before: auction_id + 1,
+        };
+        match populate_batch(db, &mut report, &mut stats, options, batch, &done).await? {
+            Some(_) if db.config.dry_run => (),
+            Some(_) => {
+                let start = Instant::now();
+                crate::database_runs::insert_frontier(
+                    &mut *db.pool.acquire().await?,
+                    run_id,
+                    POPULATE_HISTORIC_AUCTIONS,
+                    auction_id,
+                    auction_id,
+                    FrontierKind::Prioritized,
+                )
+                .await
+                .context("publish frontier")?;
+                stats
+                    .timings
+                    .record(POPULATE_HISTORIC_AUCTIONS, Stage::Write, start);
+            }
+            None => info!("prioritized auction {auction_id} does not exist"),
+        }
+        done.insert(auction_id);
+
+        let start = Instant::now();
+        db.config.clock.sleep(options.throttle).await;
+        stats
+            .timings
+            .record(POPULATE_HISTORIC_AUCTIONS, Stage::Sleep, start);
+    }
+
+    'cursors: while cursors.iter().any(|cursor| !cursor.done) {
+        print_progress(&cursors, range.start.unwrap_or(0));
+        for i in 0..cursors.len() {
+            if cursors[i].done {
+                continue;
+            }
+            let Some(options) = options.within_limit(stats.auctions) else {
+                info!(
+                    "processed {} auctions, stopping at the limit",
+                    big(stats.auctions)
+                );
+                break 'cursors;
+            };
+            // the cursors stop where they meet
+            let batch = match cursors[i].direction {
+                Direction::Descending => Batch {
+                    direction: Direction::Descending,
+                    after: cursors.get(1).map_or(floor, |other| other.position),
+                    before: cursors[i].position,
+                },
+                Direction::Ascending => Batch {
+                    direction: Direction::Ascending,
+                    after: cursors[i].position,
+                    before: cursors[0].position,
+                },
+            };
+            let cursor = &mut cursors[i];
+            if cursor.direction == Direction::Descending {
+                report.progress(cursor.position);
+            }
+            match populate_batch(db, &mut report, &mut stats, options, batch, &done).await? {
+                Some(position) => {
+                    cursor.position = position;
+                    let start = Instant::now();
+                    if !db.config.dry_run {
+                        cursor.publish(db, run_id).await?;
+                        if checkpointed {
+                            crate::database_runs::save_progress(
+                                &mut *db.pool.acquire().await?,
+                                POPULATE_HISTORIC_AUCTIONS,
+                                run_id,
+                                position,
+                            )
+                            .await
+                            .context("save checkpoint")?;
+                        }
+                    }
+                    stats
+                        .timings
+                        .record(POPULATE_HISTORIC_AUCTIONS, Stage::Write, start);
+                }
+                None => {
+                    match cursor.direction {
+                        Direction::Descending => info!("no more auctions to process"),
+                        Direction::Ascending => {
+                            info!("ascending cursor met the descending one")
+                        }
+                    }
+                    cursor.done = true;
+                }
+            }
+        }
+
+        let start = Instant::now();
+        db.config.clock.sleep(options.throttle).await;
+        stats
+            .timings
+            .record(POPULATE_HISTORIC_AUCTIONS, Stage::Sleep, start);
+    }
+
+    finish_populate(db, options, run_id, report, stats, run_start).await
+}
+
+/// Populates only the auctions in `options.range` that have a JSON in
+/// `solver_competitions` but are missing from `competition_auctions`, each in
+/// its own transaction, going from the highest one down. Unlike
+/// [`populate_historic_auctions`] it doesn't scan the covered parts of the
+/// range again, so it is cheap for closing the few gaps left by skips,
+/// crashes, or runs over other ranges.
+#[instrument(skip_all)]
+pub async fn fix_missing_historic_auctions(
+    db: &Postgres,
+    options: PopulateOptions,
+) -> Result<Report> {
+    info!("starting data migration fix for missing auctions");
+    let mut report = Report::new(FIX_MISSING_HISTORIC_AUCTIONS);
+
+    // how many missing auctions are looked up at once
+    const MISSING_BATCH_SIZE: i64 = 1000;
+
+    let mut ex = db.pool.acquire().await?;
+    // auctions arriving after this point are left to the regular population
+    let snapshot_max_id = fetch_max_id(&mut ex)
+        .await
+        .context("fetch highest auction id")?;
+    let run_id = crate::database_runs::start(
+        &mut ex,
+        FIX_MISSING_HISTORIC_AUCTIONS,
+        snapshot_max_id,
+        db.config.schema_variant.as_deref(),
+    )
+    .await?;
+    info!("run id: {run_id}, snapshot upper bound: {snapshot_max_id:?}");
+    report.snapshot_max_id = snapshot_max_id;
+    let Some(snapshot_max_id) = snapshot_max_id else {
+        info!("solver_competitions is empty, nothing to process");
+        return Ok(report);
+    };
+
+    let after = options.range.start.map_or(i64::MIN, |start| start - 1);
+    let mut before = options
+        .range
+        .end
+        .map_or(snapshot_max_id, |end| end.min(snapshot_max_id))
+        + 1;
+    let mut stats = PopulateStats::default();
+    let run_start = Instant::now();
+    let none_done = BTreeSet::new();
+    loop {
+        let missing = crate::database_solver_competition::fetch_missing(
+            &mut ex,
+            after,
+            before,
+            MISSING_BATCH_SIZE,
+        )
+        .await
+        .context("fetch missing auctions")?;
+        let Some(&last) = missing.last() else {
+            break;
+        };
+        info!(
+            "found {} missing auctions, from {} down to {last}",
+            big(missing.len()),
+            missing[0]
+        );
+        // auctions that are still missing after this, like skipped ones, are
+        // not looked at again
+        before = last;
+
+        for auction_id in missing {
+            report.progress(auction_id);
+            let batch = Batch {
+                direction: Direction::Descending,
+                after: auction_id - 1,
+                before: auction_id + 1,
+            };
+            populate_batch(db, &mut report, &mut stats, options, batch, &none_done).await?;
+
+            let start = Instant::now();
+            db.config.clock.sleep(options.throttle).await;
+            stats
+                .timings
+                .record(POPULATE_HISTORIC_AUCTIONS, Stage::Sleep, start);
+        }
+    }
+    drop(ex);
+
+    finish_populate(db, options, run_id, report, stats, run_start).await
+}
+
+/// Prints the summary of a run of [`populate_historic_auctions`] and records
+/// that it finished.
+async fn finish_populate(
+    db: &Postgres,
+    options: PopulateOptions,
+    run_id: i64,
+    report: Report,
+    stats: PopulateStats,
+    run_start: Instant,
+) -> Result<Report> {
+    info!("auctions without winner: {}", stats.auctions_without_winner);
+    report.print();
+    stats.timings.print(
+        run_start.elapsed(),
+        stats.auctions,
+        throughput::Config {
+            batch_size: options.batch_size,
+            parallelism: options.workers,
+            throttle: options.throttle,
+        },
+    );
+
+    crate::database_runs::finish(&mut *db.pool.acquire().await?, run_id).await?;
+    Ok(report)
+}
+
+/// Splits the auctions `start..=end` into up to `workers` disjoint ranges of
+/// about the same size, highest first, with nothing populated yet.
+fn partition(start: i64, end: i64, workers: usize) -> Vec<WorkerProgress> {
+    let len = end - start + 1;
+    if len <= 0 {
+        return Vec::new();
+    }
+    let workers = (workers as i64).min(len);
+    (0..workers)
+        .rev()
+        .map(|i| {
+            let end_id = start + len * (i + 1) / workers - 1;
+            WorkerProgress {
+                start_id: start + len * i / workers,
+                end_id,
+                last_auction_id: end_id + 1,
+            }
+        })
+        .collect()
+}
+
+/// Populates the ranges of `workers` concurrently, each going down from its
+/// checkpoint on its own task and connection, and finishes the run with the
+/// merged reports of all workers.
+async fn populate_in_workers(
+    db: &Postgres,
+    options: PopulateOptions,
+    run_id: i64,
+    workers: Vec<WorkerProgress>,
+    checkpointed: bool,
+    mut report: Report,
+) -> Result<Report> {
+    let run_start = Instant::now();
+    if checkpointed && !db.config.dry_run {
+        // a range is resumed even if its worker crashes before committing
+        // anything
+        for worker in &workers {
+            crate::database_runs::save_worker_progress(
+                &mut *db.pool.acquire().await?,
+                POPULATE_HISTORIC_AUCTIONS,
+                run_id,
+                *worker,
+            )
+            .await
+            .context("save worker checkpoint")?;
+        }
+    }
+
+    let tasks: Vec<_> = workers
+        .into_iter()
+        .map(|worker| {
+            tokio::spawn(populate_worker(
+                db.clone(),
+                options,
+                run_id,
+                worker,
+                checkpointed,
+            ))
+        })
+        .collect();
+    let mut stats = PopulateStats::default();
+    for task in tasks {
+        let (worker_report, worker_stats) = task.await.context("worker panicked")??;
+        report.merge(worker_report);
+        stats.merge(worker_stats);
+    }
+
+    finish_populate(db, options, run_id, report, stats, run_start).await
+}
+
+/// Populates the auctions of `worker` going down from its checkpoint.
+#[instrument(skip_all, fields(start_id = worker.start_id, end_id = worker.end_id))]
+async fn populate_worker(
+    db: Postgres,
+    options: PopulateOptions,
+    run_id: i64,
+    mut worker: WorkerProgress,
+    checkpointed: bool,
+) -> Result<(Report, PopulateStats)> {
+    let mut report = Report::new(POPULATE_HISTORIC_AUCTIONS);
+    let mut stats = PopulateStats::default();
+    let mut cursor = Cursor::new(Direction::Descending, worker.end_id + 1, options.range);
+    cursor.position = worker.last_auction_id;
+    if checkpointed {
+        cursor.position = verify_checkpoint(
+            &db,
+            &mut report,
+            &mut stats,
+            options,
+            worker.start_id - 1,
+            worker.last_auction_id,
+            worker.end_id + 1,
+        )
+        .await?;
+    }
+    loop {
+        info!(
+            "worker of auctions {}..={} populating from auction {}",
+            worker.start_id, worker.end_id, cursor.position
+        );
+        let batch = Batch {
+            direction: Direction::Descending,
+            after: worker.start_id - 1,
+            before: cursor.position,
+        };
+        let done = BTreeSet::new();
+        let Some(position) =
+            populate_batch(&db, &mut report, &mut stats, options, batch, &done).await?
+        else {
+            break;
+        };
+        cursor.position = position;
+        let start = Instant::now();
+        if !db.config.dry_run {
+            cursor.publish(&db, run_id).await?;
+            if checkpointed {
+                worker.last_auction_id = position;
+                crate::database_runs::save_worker_progress(
+                    &mut *db.pool.acquire().await?,
+                    POPULATE_HISTORIC_AUCTIONS,
+                    run_id,
+                    worker,
+                )
+                .await
+                .context("save worker checkpoint")?;
+            }
+        }
+        stats
+            .timings
+            .record(POPULATE_HISTORIC_AUCTIONS, Stage::Write, start);
+
+        let start = Instant::now();
+        db.config.clock.sleep(options.throttle).await;
+        stats
+            .timings
+            .record(POPULATE_HISTORIC_AUCTIONS, Stage::Sleep, start);
+    }
+    info!(
+        "worker of auctions {}..={} is done",
+        worker.start_id, worker.end_id
+    );
+    Ok((report, stats))
+}
+
+/// One pass of [`populate_historic_auctions`] through the auction ids.
+#[derive(Clone, Copy, Debug)]
+struct Cursor {
+    direction: Direction,
+    /// Exclusive bound of the next batch, the last auction the cursor moved
+    /// past.
+    position: i64,
+    start: i64,
+    done: bool,
+    /// The range in `migration_frontier` of the auctions covered so far.
+    frontier_id: Option<i64>,
+    kind: FrontierKind,
+}
+
+impl Cursor {
+    /// A pass in `direction` from `start` through the auctions of `range`.
+    fn new(direction: Direction, start: i64, range: AuctionRange) -> Self {
+        Self {
+            direction,
+            position: start,
+            start,
+            done: false,
+            frontier_id: None,
+            kind: frontier_kind(direction, range),
+        }
+    }
+
+    /// Publishes the auctions covered so far to `migration_frontier`.
+    async fn publish(&mut self, db: &Postgres, run_id: i64) -> Result<()> {
+        let (start_id, end_id) = match self.direction {
+            Direction::Descending => (self.position, self.start - 1),
+            Direction::Ascending => (self.start + 1, self.position),
+        };
+        let mut ex = db.pool.acquire().await?;
+        match self.frontier_id {
+            Some(id) => crate::database_runs::update_frontier(&mut ex, id, start_id, end_id)
+                .await
+                .context("publish frontier")?,
+            None => {
+                let id = crate::database_runs::insert_frontier(
+                    &mut ex,
+                    run_id,
+                    POPULATE_HISTORIC_AUCTIONS,
+                    start_id,
+                    end_id,
+                    self.kind,
+                )
+                .await
+                .context("publish frontier")?;
+                self.frontier_id = Some(id);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The kind of the ranges a pass in `direction` through `range` completes.
+/// Only passes down without upper bound continue the passes down before them,
+/// which default runs start below of.
+fn frontier_kind(direction: Direction, range: AuctionRange) -> FrontierKind {
+    match direction {
+        Direction::Ascending => FrontierKind::Ascending,
+        Direction::Descending if range.end.is_some() => FrontierKind::Bounded,
+        Direction::Descending => FrontierKind::Descending,
+    }
+}
+
+/// Prints the positions of the cursors and how much of the auctions between
+/// their starts they covered together. A single descending cursor covers the
+/// auctions down to `lowest`.
+fn print_progress(cursors: &[Cursor], lowest: i64) {
+    let (descending, ascending) = (&cursors[0], cursors.get(1));
+    let lowest = ascending.map_or(lowest, |ascending| ascending.start);
+    let remaining = descending.position - ascending.map_or(lowest, |ascending| ascending.position);
+    let total = descending.start - lowest;
+    let percent = (total - remaining) as f64 / total as f64 * 100.0;
+    match ascending {
+        None => info!(
+            "populating historic auctions from auction {}, executed in percent: {percent}",
+            descending.position
+        ),
+        Some(ascending) => info!(
+            "populating historic auctions from auction {} down and from auction {} up, executed in percent: {percent}",
+            descending.position, ascending.position
+        ),
+    }
+}
+
+/// Auctions strictly between `after` and `before`, gone through in
+/// `direction`.
+#[derive(Clone, Copy, Debug)]
+struct Batch {
+    direction: Direction,
+    after: i64,
+    before: i64,
+}
+
+/// Processes the next batch of auctions in `batch`, except the ones in
+/// `done`, in its own transaction. Returns the last auction id of the batch,
+/// or `None` if there are no auctions left.
+async fn populate_batch(
+    db: &Postgres,
+    report: &mut Report,
+    stats: &mut PopulateStats,
+    options: PopulateOptions,
+    batch: Batch,
+    done: &BTreeSet<i64>,
+) -> Result<Option<i64>> {
+    let start = Instant::now();
+    let mut ex = db.pool.begin().await?;
+    let foreign = crate::database_runs::fetch_foreign_ownership(&mut ex, OWNER)
+        .await
+        .context("fetch ownership")?;
+
+    // fetch the next batch of auctions
+    let competitions = match options.metadata_first {
+        true => {
+            fetch_batch_metadata_first(
+                &mut ex,
+                batch.after,
+                batch.before,
+                batch.direction,
+                options.batch_size,
+                |metadata| {
+                    crate::database_runs::owner_of(&foreign, metadata.id).is_none()
+                        && metadata.json_bytes <= Some(options.giant_auction_bytes)
+                },
+            )
+            .await
+        }
+        false if options.only_missing => {
+            fetch_batch_missing(
+                &mut ex,
+                batch.after,
+                batch.before,
+                batch.direction,
+                options.batch_size,
+                options.giant_auction_bytes,
+            )
+            .await
+        }
+        false => {
+            fetch_batch(
+                &mut ex,
+                batch.after,
+                batch.before,
+                batch.direction,
+                options.batch_size,
+                options.giant_auction_bytes,
+            )
+            .await
+        }
+    };
+    stats
+        .timings
+        .record(POPULATE_HISTORIC_AUCTIONS, Stage::Fetch, start);
+    let Ok(competitions) = competitions else {
+        let next = match batch.direction {
+            Direction::Descending => batch.before - 1,
+            Direction::Ascending => batch.after + 1,
+        };
+        report.skip(
+            SkipReason::Deserialize,
+            next,
+            format_args!("failed to fetch auction {next}"),
+        );
+        return Ok(Some(next));
+    };
+
+    let Some(last) = competitions.last() else {
+        return Ok(None);
+    };
+
+    info!("processing {} auctions", big(competitions.len()));
+
+    let mut giants = Vec::new();
+    let mut pending = Vec::new();
+    for solver_competition in &competitions {
+        if done.contains(&solver_competition.id) {
+            continue;
+        }
+        if skip_foreign(report, &foreign, solver_competition.id) {
+            continue;
+        }
+        if solver_competition.json.is_none()
+            && solver_competition.json_bytes > Some(options.giant_auction_bytes)
+        {
+            giants.push(solver_competition.id);
+            continue;
+        }
+        populate_auction_guarded(
+            &mut ex,
+            report,
+            stats,
+            solver_competition,
+            &db.config,
+            options.auction_timeout,
+            Instant::now(),
+            &mut pending,
+        )
+        .await?;
+    }
+    save_auctions(&mut ex, report, stats, &db.config, pending).await;
+
+    // commit each batch separately
+    let start = Instant::now();
+    db.config.check_max_updates()?;
+    ex.commit().await?;
+    report.committed();
+    stats
+        .timings
+        .record(POPULATE_HISTORIC_AUCTIONS, Stage::Write, start);
+
+    for auction_id in giants {
+        info!("processing giant auction {auction_id} separately");
+        let start = Instant::now();
+        let mut ex = db.pool.begin().await?;
+        // the fetch of a giant auction counts towards its timeout, like its
+        // processing
+        crate::database::set_statement_timeout(&mut ex, options.auction_timeout).await?;
+        let fetched = fetch_without_solutions(&mut ex, auction_id).await;
+        stats
+            .timings
+            .record(POPULATE_HISTORIC_AUCTIONS, Stage::Fetch, start);
+        let solver_competition = match fetched {
+            Err(err) if crate::database::is_statement_timeout(&err) => {
+                report_timeout(report, auction_id);
+                ex.rollback().await?;
+                continue;
+            }
+            fetched => fetched.context("fetch giant auction")?,
+        };
+        crate::database::set_statement_timeout(&mut ex, None).await?;
+        if let Some(solver_competition) = solver_competition {
+            let mut pending = Vec::new();
+            populate_auction_guarded(
+                &mut ex,
+                report,
+                stats,
+                &solver_competition,
+                &db.config,
+                options.auction_timeout,
+                start,
+                &mut pending,
+            )
+            .await?;
+            save_auctions(&mut ex, report, stats, &db.config, pending).await;
+        }
+        let start = Instant::now();
+        db.config.check_max_updates()?;
+        ex.commit().await?;
+        report.committed();
+        stats
+            .timings
+            .record(POPULATE_HISTORIC_AUCTIONS, Stage::Write, start);
+    }
+
+    Ok(Some(last.id))
+}
+
+/// How long before the estimated time of its auction the validTo of an
+/// executed order has to be to be suspect, two years.
+#[cfg(feature = "destructive")]
+const SUSPECT_VALID_TO_AGE: u32 = 2 * 365 * 24 * 60 * 60;
+
+/// Flags `order_uid` as suspect if it expired years before the auction it was
+/// executed in, whose time is estimated by `auction_valid_to`, see
+/// [`crate::solver_competition_api::CompetitionAuction::median_valid_to`].
+#[cfg(feature = "destructive")]
+fn check_valid_to(
+    report: &mut Report,
+    auction_id: i64,
+    auction_valid_to: Option<u32>,
+    order_uid: &crate::database_orders::OrderUid,
+) {
+    let Some(auction_valid_to) = auction_valid_to else {
+        return;
+    };
+    if order_uid.valid_to() < auction_valid_to.saturating_sub(SUSPECT_VALID_TO_AGE) {
+        report.suspect(
+            auction_id,
+            format_args!(
+                "order_uid: {:?} expired at {}, years before the orders of auction {} at {}",
+                order_uid,
+                order_uid.valid_to(),
+                auction_id,
+                auction_valid_to
+            ),
+        );
+    }
+}
+
+thread_local! {
+    /// Backtrace of the last panic on this thread, see [`install_panic_hook`].
+    static PANIC_BACKTRACE: RefCell<Option<Backtrace>> = const { RefCell::new(None) };
+}
+
+/// Keeps the backtrace of every panic for [`populate_auction_guarded`] to
+/// report, besides printing the panic as usual.
+fn install_panic_hook() {
+    let print = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        PANIC_BACKTRACE
+            .with(|backtrace| *backtrace.borrow_mut() = Some(Backtrace::force_capture()));
+        print(info);
+    }));
+}
+
+/// The message a panic was raised with.
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or(panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// Runs [`populate_auction`] and adds the auction to `pending`, for the
+/// caller to write together with the rest of its batch.
+///
+/// With a `timeout` the auction is instead written right away, in a savepoint,
+/// and given up on once `timeout` passed since `started`, when the caller
+/// began to fetch it. A timed out auction is skipped as
+/// [`SkipReason::Timeout`] and rolled back to the savepoint, so the rest of
+/// its batch still commits. Retries get the full `timeout` again.
+///
+/// An auction whose processing panics is skipped as [`SkipReason::Panic`]
+/// with the backtrace of the panic, one whose processing fails with an error
+/// as [`SkipReason::Error`]. Nothing of it is written either, since it is
+/// written in a single statement after its processing.
+///
+/// A failed auction is processed again up to [`RETRY_ATTEMPTS`] times, or
+/// fails the batch instead of being skipped, as `config.on_error` says.
+#[instrument(skip_all, fields(auction_id = solver_competition.id))]
+#[allow(clippy::too_many_arguments)]
+async fn populate_auction_guarded(
+    ex: &mut PgConnection,
+    report: &mut Report,
+    stats: &mut PopulateStats,
+    solver_competition: &RichSolverCompetition,
+    config: &Config,
+    timeout: Option<Duration>,
+    started: Instant,
+    pending: &mut Vec<Auction>,
+) -> Result<()> {
+    let auction_id = solver_competition.id;
+    let mut deadline = timeout.map(|timeout| started + timeout);
+    let recovered = match recover_json(ex, report, config, solver_competition, deadline).await {
+        Err(err) if err.is::<tokio::time::error::Elapsed>() => {
+            report_timeout(report, auction_id);
+            return Ok(());
+        }
+        recovered => recovered?,
+    };
+    let solver_competition = recovered.as_ref().unwrap_or(solver_competition);
+    let mut attempt = 1;
+    loop {
+        // only the final attempt counts
+        let mut attempt_report = Report::new(&report.migration);
+        let mut attempt_pending = Vec::new();
+        populate_auction_once(
+            ex,
+            &mut attempt_report,
+            stats,
+            solver_competition,
+            config,
+            deadline,
+            &mut attempt_pending,
+        )
+        .await?;
+        let failed = attempt_report.failed(auction_id);
+        match config.on_error {
+            OnError::Abort if failed => bail!(
+                "auction {auction_id} failed, aborting: {}",
+                attempt_report.errors[&auction_id]
+            ),
+            OnError::Retry if failed && attempt < RETRY_ATTEMPTS => {
+                warn!(
+                    "auction {auction_id} failed, retrying, attempt {attempt} of {RETRY_ATTEMPTS}"
+                );
+                back_off(&config.clock, attempt).await;
+                attempt += 1;
+                deadline = timeout.map(|timeout| Instant::now() + timeout);
+            }
+            _ => {
+                if attempt_report.partial.contains(&auction_id) && !config.dry_run {
+                    crate::database_runs::annotate(
+                        ex,
+                        None,
+                        Some(auction_id),
+                        "written without its solutions, which don't decode",
+                    )
+                    .await
+                    .context("annotate partial write")?;
+                }
+                report.merge(attempt_report);
+                pending.extend(attempt_pending);
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// How often [`populate_auction_guarded`] processes a failing auction with
+/// `--on-error retry`, and how much longer it waits before each further
+/// attempt.
+const RETRY_ATTEMPTS: u32 = 3;
+const RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Waits before the attempt after `attempt`.
+async fn back_off(clock: &crate::clock::Clock, attempt: u32) {
+    clock.sleep(RETRY_BACKOFF * attempt).await;
+}
+
+/// Skips `auction_id` for taking longer than `--auction-timeout-secs`.
+fn report_timeout(report: &mut Report, auction_id: i64) {
+    report.skip(
+        SkipReason::Timeout,
+        auction_id,
+        format_args!("auction: {auction_id}, took longer than --auction-timeout-secs"),
+    );
+}
+
+/// One attempt of [`populate_auction_guarded`], with the `deadline` of the
+/// attempt if it has a timeout.
+async fn populate_auction_once(
+    ex: &mut PgConnection,
+    report: &mut Report,
+    stats: &mut PopulateStats,
+    solver_competition: &RichSolverCompetition,
+    config: &Config,
+    deadline: Option<Instant>,
+    pending: &mut Vec<Auction>,
+) -> Result<()> {
+    // `None` if the auction timed out
+    let result = match deadline {
+        Some(deadline) => {
+            let mut savepoint = ex.begin().await?;
+            let result = populate_until(
+                &mut savepoint,
+                report,
+                stats,
+                solver_competition,
+                config,
+                deadline,
+            )
+            .await?;
+            match result {
+                Some(Ok(Ok(()))) => {
+                    crate::database::set_statement_timeout(&mut savepoint, None).await?;
+                    savepoint.commit().await?
+                }
+                _ => savepoint.rollback().await?,
+            }
+            result
+        }
+        None => Some(std::panic::catch_unwind(AssertUnwindSafe(|| {
+            let auction =
+                populate_auction(report, stats, solver_competition, config.partial_writes)?;
+            pending.extend(auction);
+            anyhow::Ok(())
+        }))),
+    };
+    match result {
+        Some(Ok(Ok(()))) => (),
+        Some(Ok(Err(err))) => report.skip(
+            SkipReason::Error,
+            solver_competition.id,
+            format_args!("auction: {}, error: {:?}", solver_competition.id, err),
+        ),
+        Some(Err(panic)) => {
+            let message = panic_message(&*panic);
+            let backtrace = PANIC_BACKTRACE
+                .with(|backtrace| backtrace.borrow_mut().take())
+                .map_or_else(String::new, |backtrace| backtrace.to_string());
+            report.panicked(solver_competition.id, message, &backtrace);
+        }
+        None => report_timeout(report, solver_competition.id),
+    }
+    Ok(())
+}
+
+/// Processes and writes one auction in `ex` before `deadline`, `None` if that
+/// is missed. Fails only if `ex` is unusable.
+///
+/// Nothing in flight is cancelled on the client: decoding the JSON, which
+/// can't be interrupted, runs on a blocking thread that is left to finish on
+/// its own, and the write is bounded by a `statement_timeout`, so the server
+/// cancels it and `ex` can still be rolled back.
+async fn populate_until(
+    ex: &mut PgConnection,
+    report: &mut Report,
+    stats: &mut PopulateStats,
+    solver_competition: &RichSolverCompetition,
+    config: &Config,
+    deadline: Instant,
+) -> Result<Option<std::thread::Result<Result<()>>>> {
+    let start = Instant::now();
+    let decoded = match solver_competition.json.clone() {
+        Some(json) => {
+            let decode = tokio::task::spawn_blocking(move || decode_competition(&json));
+            match tokio::time::timeout_at(deadline.into(), decode).await {
+                Ok(Ok(decoded)) => Some(decoded),
+                Ok(Err(err)) if err.is_panic() => return Ok(Some(Err(err.into_panic()))),
+                Ok(Err(err)) => return Ok(Some(Ok(Err(err.into())))),
+                Err(_) => return Ok(None),
+            }
+        }
+        None => None,
+    };
+    let auction = match std::panic::catch_unwind(AssertUnwindSafe(|| {
+        populate_decoded(
+            report,
+            stats,
+            solver_competition,
+            config.partial_writes,
+            start,
+            decoded,
+        )
+    })) {
+        Ok(Ok(Some(auction))) => auction,
+        Ok(Ok(None)) => return Ok(Some(Ok(Ok(())))),
+        Ok(Err(err)) => return Ok(Some(Ok(Err(err)))),
+        Err(panic) => return Ok(Some(Err(panic))),
+    };
+    let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+        return Ok(None);
+    };
+    if config.dry_run {
+        save_auctions(ex, report, stats, config, vec![auction]).await;
+        return Ok(Some(Ok(Ok(()))));
+    }
+    crate::database::set_statement_timeout(ex, Some(remaining)).await?;
+    let start = Instant::now();
+    let saved = save_in_savepoint(ex, config, vec![auction], &report.no_winner).await;
+    stats
+        .timings
+        .record(POPULATE_HISTORIC_AUCTIONS, Stage::Write, start);
+    report.written(&[solver_competition.id], start.elapsed());
+    match saved {
+        Ok(()) => Ok(Some(Ok(Ok(())))),
+        Err(err) if crate::database::is_statement_timeout(err.as_ref()) => Ok(None),
+        Err(err) => {
+            report.skip(
+                SkipReason::Save,
+                solver_competition.id,
+                format_args!(
+                    "auction: {}, failed to save: {err:?}",
+                    solver_competition.id
+                ),
+            );
+            Ok(Some(Ok(Ok(()))))
+        }
+    }
+}
+
+/// Records a batch of `migration` that modified `modified` of the `scanned`
+/// rows, and fails the run before it is committed if the share of modified
+/// rows deviates too much from the batches before, see
+/// [`crate::anomalies::WriteRatio`].
+#[cfg(feature = "destructive")]
+fn check_write_ratio(
+    write_ratio: &mut crate::anomalies::WriteRatio,
+    migration: &str,
+    scanned: u64,
+    modified: u64,
+) -> Result<()> {
+    let Err(anomaly) = write_ratio.observe(scanned, modified) else {
+        return Ok(());
+    };
+    crate::metrics::get()
+        .write_ratio_anomalies
+        .with_label_values(&[migration])
+        .inc();
+    error!(
+        "{migration}: recent batches modified {:.1}% of the rows they scanned, earlier ones {:.1}%",
+        anomaly.actual * 100.,
+        anomaly.expected * 100.
+    );
+    bail!(
+        "stopped on an unexpected share of modified rows, rolled back the current batch; check \
+         the run and invoke it again, with a higher --max-write-ratio-deviation or without it if \
+         the change is expected"
+    )
+}
+
+/// Fails the run if `on_error` is to abort and auctions failed since `report`
+/// had `failures_before` failures. Migrations that can't retry single
+/// auctions call this before committing each batch.
+#[cfg(feature = "destructive")]
+fn abort_on_failure(report: &Report, on_error: OnError, failures_before: usize) -> Result<()> {
+    let failures = report.errors.len() - failures_before;
+    if on_error == OnError::Abort && failures > 0 {
+        bail!("{failures} auctions of the batch failed, aborting");
+    }
+    Ok(())
+}
+
+/// Takes the JSON of an auction whose stored one is missing or doesn't decode
+/// from the first fallback source that has one that does, if any are
+/// configured, and notes the source in the annotations of the auction.
+/// Returns `None` if the stored JSON is used, and a
+/// [`tokio::time::error::Elapsed`] error if the sources don't answer before
+/// `deadline`.
+async fn recover_json(
+    ex: &mut PgConnection,
+    report: &mut Report,
+    config: &Config,
+    solver_competition: &RichSolverCompetition,
+    deadline: Option<Instant>,
+) -> Result<Option<RichSolverCompetition>> {
+    // giant auctions are fetched without their solutions on purpose
+    if config.fallbacks.is_empty()
+        || solver_competition.solution_count.is_some()
+        || solver_competition
+            .json
+            .as_ref()
+            .is_some_and(crate::sources::decodes)
+    {
+        return Ok(None);
+    }
+    let fetch = config.fallbacks.fetch(solver_competition.id);
+    let fetched = match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline.into(), fetch).await?,
+        None => fetch.await,
+    };
+    let Some((json, source)) = fetched else {
+        return Ok(None);
+    };
+    report.fallback(solver_competition.id, source);
+    if !config.dry_run {
+        crate::database_runs::annotate(
+            ex,
+            None,
+            Some(solver_competition.id),
+            &format!("JSON taken from the {source} fallback"),
+        )
+        .await
+        .context("annotate fallback")?;
+    }
+    Ok(Some(RichSolverCompetition {
+        json: Some(json),
+        json_bytes: None,
+        ..solver_competition.clone()
+    }))
+}
+
+/// Writes `auctions` to `competition_auctions` in as few statements as
+/// `insert_batch_size` allows, or only records them in a dry run. A failed
+/// write skips the auctions, it is not returned.
+///
+/// The write is in a savepoint, so a failed statement doesn't abort the
+/// transaction of the batch. If it fails the auctions are written again one
+/// by one, each in its own savepoint, and only the ones that fail on their
+/// own are skipped.
+async fn save_auctions(
+    ex: &mut PgConnection,
+    report: &mut Report,
+    stats: &mut PopulateStats,
+    config: &Config,
+    auctions: Vec<Auction>,
+) {
+    if auctions.is_empty() {
+        return;
+    }
+    let ids: Vec<i64> = auctions.iter().map(|auction| auction.id).collect();
+    let start = Instant::now();
+    match config.dry_run {
+        true => {
+            for auction in &auctions {
+                report.would_write("competition_auctions", 1, format_args!("{auction:?}"));
+            }
+        }
+        false => {
+            // the auctions left to save one by one
+            let saved = match auctions.len() {
+                1 => Err(auctions),
+                len => match save_in_savepoint(ex, config, auctions.clone(), &report.no_winner)
+                    .await
+                {
+                    Ok(()) => Ok(()),
+                    Err(err) => {
+                        warn!("failed to save {len} auctions at once, saving one by one: {err:?}");
+                        Err(auctions)
+                    }
+                },
+            };
+            if let Err(auctions) = saved {
+                for auction in auctions {
+                    let auction_id = auction.id;
+                    if let Err(err) =
+                        save_in_savepoint(ex, config, vec![auction], &report.no_winner).await
+                    {
+                        report.skip(
+                            SkipReason::Save,
+                            auction_id,
+                            format_args!("auction: {auction_id}, failed to save: {err:?}"),
+                        );
+                    }
+                }
+            }
+            report.written(&ids, start.elapsed());
+        }
+    }
+    stats
+        .timings
+        .record(POPULATE_HISTORIC_AUCTIONS, Stage::Write, start);
+}
+
+/// Writes `auctions` and marks them written in `migration_auction_status`,
+/// the ones in `no_winner` without winner, in a savepoint that is rolled back
+/// if either fails.
+async fn save_in_savepoint(
+    ex: &mut PgConnection,
+    config: &Config,
+    auctions: Vec<Auction>,
+    no_winner: &BTreeSet<i64>,
+) -> Result<()> {
+    let ids: Vec<i64> = auctions.iter().map(|auction| auction.id).collect();
+    let without_winner: Vec<i64> = ids
+        .iter()
+        .copied()
+        .filter(|id| no_winner.contains(id))
+        .collect();
+    let mut parts = crate::database_auction_status::AUCTION;
+    if config.auction_columns.contains(&AuctionColumn::Prices) {
+        parts |= crate::database_auction_status::PRICES;
+    }
+
+    let copied = (!config.sinks.is_empty()).then(|| auctions.clone());
+
+    let mut savepoint = ex.begin().await?;
+    let saved = async {
+        let rows = crate::database_solver_competition::save_batch(
+            &mut savepoint,
+            auctions,
+            &config.auction_columns,
+            config.insert_batch_size,
+            config.overwrite,
+        )
+        .await?;
+        crate::database_auction_status::mark(&mut savepoint, &ids, parts)
+            .await
+            .context("mark auction status")?;
+        crate::database_auction_status::mark(
+            &mut savepoint,
+            &without_winner,
+            crate::database_auction_status::NO_WINNER,
+        )
+        .await
+        .context("mark auctions without winner")?;
+        anyhow::Ok(rows)
+    }
+    .await;
+    match saved {
+        Ok(rows) => {
+            savepoint.commit().await?;
+            config.count_updates(rows);
+            if let Some(auctions) = copied {
+                crate::sinks::write_auctions(&config.sinks, &auctions)?;
+            }
+            Ok(())
+        }
+        Err(err) => {
+            savepoint.rollback().await?;
+            Err(err)
+        }
+    }
+}
+
+/// Derives the `competition_auctions` row of one auction, with canonical
+/// prices. Returns `None` if the auction is skipped.
+fn populate_auction(
+    report: &mut Report,
+    stats: &mut PopulateStats,
+    solver_competition: &RichSolverCompetition,
+    partial_writes: bool,
+) -> Result<Option<Auction>> {
+    let start = Instant::now();
+    let decoded = solver_competition.json.as_ref().map(decode_competition);
+    populate_decoded(
+        report,
+        stats,
+        solver_competition,
+        partial_writes,
+        start,
+        decoded,
+    )
+}
+
+/// Decodes the JSON of a solver competition, the slow part of processing a
+/// huge one.
+fn decode_competition(json: &serde_json::Value) -> serde_json::Result<SolverCompetitionDB> {
+    serde_json::from_value(json.clone())
+}
+
+/// Like [`populate_auction`], with the JSON of the auction already decoded
+/// into `decoded`, starting at `start`.
+fn populate_decoded(
+    report: &mut Report,
+    stats: &mut PopulateStats,
+    solver_competition: &RichSolverCompetition,
+    partial_writes: bool,
+    start: Instant,
+    decoded: Option<serde_json::Result<SolverCompetitionDB>>,
+) -> Result<Option<Auction>> {
+    let (Some(json), Some(decoded)) = (&solver_competition.json, decoded) else {
+        report.skip(
+            SkipReason::NullJson,
+            solver_competition.id,
+            format_args!("auction: {}", solver_competition.id),
+        );
+        return Ok(None);
+    };
+    let competition = match decoded {
+        Ok(competition) => competition,
+        Err(err) => match decode_without_solutions(json).filter(|_| partial_writes) {
+            Some(competition) => {
+                report.partial(solver_competition.id, err);
+                competition
+            }
+            None => {
+                report.skip(
+                    SkipReason::Deserialize,
+                    solver_competition.id,
+                    format_args!("auction: {}, error: {err}", solver_competition.id),
+                );
+                return Ok(None);
+            }
+        },
+    };
+    let skipped = skipped_orders(json, &competition);
+    if skipped > 0 {
+        warn!(
+            "skipped {} malformed orders, auction: {}",
+            skipped, solver_competition.id
+        );
+    }
+
+    // the solutions of a partial write are counted even though they don't
+    // decode
+    let solutions = solver_competition.solution_count.map_or(
+        json["solutions"]
+            .as_array()
+            .map_or(competition.solutions.len(), Vec::len),
+        |count| count as usize,
+    );
+    if solutions == 0 {
+        report.no_winner(solver_competition.id);
+        stats.auctions_without_winner += 1;
+    }
+
+    let (auction, duplicates) = match derive_auction(solver_competition, &competition) {
+        Ok(derived) => derived,
+        Err(err) => {
+            report.skip(
+                SkipReason::Overflow,
+                solver_competition.id,
+                format_args!("{err:#}, auction: {}", solver_competition.id),
+            );
+            return Ok(None);
+        }
+    };
+    if duplicates > 0 {
+        report.duplicate_orders(solver_competition.id, duplicates);
+    }
+
+    stats
+        .timings
+        .record(POPULATE_HISTORIC_AUCTIONS, Stage::Parse, start);
+
+    stats.auctions += 1;
+    // the auction is written with the rest of its batch, whose time is added
+    // by `save_auctions`
+    report.processed(solver_competition.id, json, start, Duration::ZERO);
+    let mut auction = auction;
+    if let Err(err) = auction.canonicalize_prices() {
+        error!(
+            "failed to save auction: {:?}, auction: {}",
+            err, solver_competition.id
+        );
+        return Ok(None);
+    }
+    Ok(Some(auction))
+}
+
+/// Derives the `competition_auctions` row of `solver_competition`, whose JSON
+/// decoded to `competition`, and returns it with the number of duplicate
+/// order uids dropped from it. Fails if the start block overflows.
+fn derive_auction(
+    solver_competition: &RichSolverCompetition,
+    competition: &SolverCompetitionDB,
+) -> Result<(Auction, usize)> {
+    let (mut order_uids, duplicates) = competition.auction.unique_orders();
+    // sorted so the stored array does not depend on the order of the source
+    order_uids.sort();
+
+    let block = i64::try_from(competition.auction_start_block)
+        .map_err(|_| anyhow::anyhow!("block {} overflows", competition.auction_start_block))?;
+
+    let auction = Auction {
+        id: solver_competition.id,
+        block,
+        deadline: solver_competition.deadline,
+        order_uids: order_uids.iter().map(|order| ByteArray(order.0)).collect(),
+        price_tokens: competition
+            .auction
+            .prices
+            .keys()
+            .map(|token| ByteArray(token.0))
+            .collect(),
+        price_values: competition
+            .auction
+            .prices
+            .values()
+            .map(u256_to_big_decimal)
+            .collect(),
+        surplus_capturing_jit_order_owners: solver_competition
+            .surplus_capturing_jit_order_owners
+            .clone(),
+    };
+    Ok((auction, duplicates))
+}
+
+/// How many auctions on either side of a checkpoint are at least verified
+/// before resuming from it, see [`verify_checkpoint`].
+const VERIFIED_ON_RESUME: i64 = 10;
+
+/// Result of comparing a migrated auction with the one derived again from its
+/// solver competition.
+#[derive(Debug, Eq, PartialEq)]
+enum Rederived {
+    /// The auction is not in `competition_auctions`.
+    Missing,
+    Matches,
+    /// The names of the fields that differ.
+    Differs(Vec<&'static str>),
+    /// The solver competition would be skipped, so there is nothing to
+    /// compare with.
+    Skipped,
+}
+
+/// Compares `stored`, the migrated auction of `solver_competition` if there
+/// is one, with the auction derived again from it. Returns the result with
+/// the number of duplicate order uids dropped from the derived auction.
+fn compare_derived(
+    solver_competition: &RichSolverCompetition,
+    stored: Option<&Auction>,
+) -> (Rederived, usize) {
+    let derived = solver_competition
+        .json
+        .as_ref()
+        .and_then(|json| serde_json::from_value::<SolverCompetitionDB>(json.clone()).ok())
+        .and_then(|competition| derive_auction(solver_competition, &competition).ok())
+        .and_then(|(mut auction, duplicates)| {
+            auction
+                .canonicalize_prices()
+                .ok()
+                .map(|()| (auction, duplicates))
+        });
+    match (derived, stored) {
+        (None, _) => (Rederived::Skipped, 0),
+        (Some((_, duplicates)), None) => (Rederived::Missing, duplicates),
+        (Some((derived, duplicates)), Some(stored)) => match derived.differing_fields(stored) {
+            fields if fields.is_empty() => (Rederived::Matches, duplicates),
+            fields => (Rederived::Differs(fields), duplicates),
+        },
+    }
+}
+
+async fn rederive(
+    ex: &mut PgConnection,
+    solver_competition: &RichSolverCompetition,
+) -> Result<Rederived> {
+    let stored = crate::database_solver_competition::fetch(ex, solver_competition.id)
+        .await
+        .context("fetch migrated auction")?;
+    Ok(compare_derived(solver_competition, stored.as_ref()).0)
+}
+
+/// Verifies the auctions around `position`, a checkpoint of a pass going down
+/// through the auctions strictly between `after` and `before`, and returns
+/// where to resume.
+///
+/// The auctions right above the checkpoint were committed according to it:
+/// missing ones are populated again and ones that differ from their solver
+/// competition are flagged as suspect. The auctions right below it were
+/// committed too if the run crashed between a commit and writing its
+/// checkpoint: the resumed pass starts below the ones already migrated, so
+/// that they are neither written twice nor fail the batch with conflicts.
+async fn verify_checkpoint(
+    db: &Postgres,
+    report: &mut Report,
+    stats: &mut PopulateStats,
+    options: PopulateOptions,
+    after: i64,
+    position: i64,
+    before: i64,
+) -> Result<i64> {
+    let limit = options.batch_size.max(VERIFIED_ON_RESUME);
+    let mut ex = db.pool.acquire().await?;
+    let committed = fetch_batch(
+        &mut ex,
+        position - 1,
+        before,
+        Direction::Ascending,
+        limit,
+        options.giant_auction_bytes,
+    )
+    .await
+    .context("fetch auctions above checkpoint")?;
+    let mut missing = Vec::new();
+    for solver_competition in &committed {
+        match rederive(&mut ex, solver_competition).await? {
+            Rederived::Missing => missing.push(solver_competition.id),
+            Rederived::Differs(fields) => report.suspect(
+                solver_competition.id,
+                format_args!(
+                    "auction {} differs from its solver competition in {}",
+                    solver_competition.id,
+                    fields.join(", ")
+                ),
+            ),
+            _ => (),
+        }
+    }
+
+    let next = fetch_batch(
+        &mut ex,
+        after,
+        position,
+        Direction::Descending,
+        limit,
+        options.giant_auction_bytes,
+    )
+    .await
+    .context("fetch auctions below checkpoint")?;
+    let mut resume = position;
+    for solver_competition in &next {
+        match rederive(&mut ex, solver_competition).await? {
+            // skipped auctions don't tell whether the batch was committed
+            Rederived::Skipped => continue,
+            Rederived::Missing => break,
+            Rederived::Matches => (),
+            Rederived::Differs(fields) => report.suspect(
+                solver_competition.id,
+                format_args!(
+                    "auction {} differs from its solver competition in {}",
+                    solver_competition.id,
+                    fields.join(", ")
+                ),
+            ),
+        }
+        resume = solver_competition.id;
+    }
+    drop(ex);
+
+    info!(
+        "verified checkpoint at auction {position}: {} migrated auctions above it missing, resuming at auction {resume}",
+        missing.len()
+    );
+    for auction_id in missing {
+        info!("populating auction {auction_id} missing above the checkpoint");
+        let batch = Batch {
+            direction: Direction::Descending,
+            after: auction_id - 1,
+            before: auction_id + 1,
+        };
+        populate_batch(db, report, stats, options, batch, &BTreeSet::new()).await?;
+    }
+    Ok(resume)
+}
+
+/// Validates every JSON in `solver_competitions` against the schema of
+/// `SolverCompetitionDB`, going from the highest auction id down.
+pub async fn validate_json(db: &Postgres) -> Result<()> {
+    info!("starting validation of solver competition json");
+
+    const BATCH_SIZE: i64 = 100;
+
+    let validator = crate::json_schema::Validator::solver_competition_db()?;
+    let mut ex = db.pool.acquire().await?;
+
+    let current_auction_id: Option<i64> =
+        sqlx::query_scalar::<_, Option<i64>>("SELECT MAX(id) FROM solver_competitions;")
+            .fetch_one(ex.deref_mut())
+            .await
+            .context("fetch highest auction id")?;
+
+    let Some(current_auction_id) = current_auction_id else {
+        info!("solver_competitions is empty, nothing to process");
+        return Ok(());
+    };
+    let mut current_auction_id = current_auction_id + 1;
+
+    let (mut valid, mut invalid) = (0, 0);
+    loop {
+        let competitions =
+            fetch_competition_order_execution(&mut ex, i64::MIN, current_auction_id, BATCH_SIZE)
+                .await
+                .context("fetch solver competitions")?;
+
+        let Some(last) = competitions.last() else {
+            break;
+        };
+        current_auction_id = last.id;
+
+        for competition in &competitions {
+            let Some(json) = &competition.json else {
+                invalid += 1;
+                warn!("auction {} has null json", competition.id);
+                continue;
+            };
+            let errors = validator.validate(json);
+            if errors.is_empty() {
+                valid += 1;
+                continue;
+            }
+            invalid += 1;
+            warn!("auction {} does not match the schema:", competition.id);
+            for error in errors {
+                warn!("    {error}");
+            }
+        }
+    }
+
+    info!("validated solver competition json: {valid} valid, {invalid} invalid");
+    Ok(())
+}
+
+/// Checks every row of `competition_auctions` in `range`, going from the
+/// highest auction id down, and prints the ones that are not consistent. Then
+/// derives every auction in `range` again from `solver_competitions` and
+/// compares it field by field with the migrated one, recording the mismatches
+/// in the returned report. Auctions whose stored JSON is larger than
+/// `giant_auction_bytes` are read without their solutions, which the auction
+/// doesn't depend on.
+pub async fn verify(
+    db: &Postgres,
+    range: AuctionRange,
+    giant_auction_bytes: i32,
+) -> Result<Report> {
+    info!("starting verification of competition_auctions");
+
+    const BATCH_SIZE: i64 = 1000;
+
+    let mut ex = db.pool.acquire().await?;
+    let mut current_auction_id = range.end.map_or(i64::MAX, |end| end + 1);
+    let (mut checked, mut broken) = (0, 0);
+    'rows: loop {
+        let rows = crate::database_solver_competition::fetch_array_violations(
+            &mut ex,
+            current_auction_id,
+            BATCH_SIZE,
+        )
+        .await
+        .context("fetch array violations")?;
+        let Some(last) = rows.last() else {
+            break;
+        };
+        current_auction_id = last.id;
+
+        for row in rows {
+            if range.start.is_some_and(|start| row.id < start) {
+                break 'rows;
+            }
+            checked += 1;
+            if row.violations.is_empty() {
+                continue;
+            }
+            broken += 1;
+            warn!("auction {} has broken array columns:", row.id);
+            for violation in row.violations {
+                warn!("    {violation}");
+            }
+        }
+    }
+
+    info!(
+        "verified competition_auctions: {} rows, {} broken",
+        big(checked),
+        big(broken)
+    );
+
+    // solver competitions are much larger than auctions
+    const COMPARED_BATCH_SIZE: i64 = 100;
+
+    // recomputed fees per batch, of those converted with shadow writes
+    const FEE_SAMPLE_SIZE: i64 = 10;
+
+    let mut report = Report::new(VERIFY);
+    let after = range.start.map_or(i64::MIN, |start| start - 1);
+    let mut before = range.end.map_or(i64::MAX, |end| end + 1);
+    let (mut matching, mut compared) = (0, 0);
+    // order executions by order kind, and how many have the wrong fee token
+    let mut fee_tokens: BTreeMap<String, (i64, i64)> = BTreeMap::new();
+    let (mut fees_matching, mut fees_recomputed) = (0, 0);
+    loop {
+        let batch = fetch_batch(
+            &mut ex,
+            after,
+            before,
+            Direction::Descending,
+            COMPARED_BATCH_SIZE,
+            giant_auction_bytes,
+        )
+        .await
+        .context("fetch solver competitions")?;
+        let (Some(first), Some(last)) = (batch.first(), batch.last()) else {
+            break;
+        };
+        before = last.id;
+        let stored: BTreeMap<_, _> =
+            crate::database_solver_competition::fetch_range(&mut ex, last.id, first.id)
+                .await
+                .context("fetch migrated auctions")?
+                .into_iter()
+                .map(|auction| (auction.id, auction))
+                .collect();
+
+        let counts = crate::database_order_executions::count_fee_tokens(&mut ex, last.id, first.id)
+            .await
+            .context("count fee tokens")?;
+        for count in counts {
+            let totals = fee_tokens.entry(count.kind).or_default();
+            totals.0 += count.checked;
+            totals.1 += count.wrong;
+        }
+        for wrong in
+            crate::database_order_executions::fetch_wrong_fee_tokens(&mut ex, last.id, first.id)
+                .await
+                .context("fetch wrong fee tokens")?
+        {
+            report.mismatch(
+                wrong.auction_id,
+                &[&format!(
+                    "fee token of {} order {:?} is {:?} instead of {:?}",
+                    wrong.kind, wrong.order_uid, wrong.executed_fee_token, wrong.surplus_token
+                )],
+            );
+        }
+        for change in crate::database_order_executions::fetch_applied_fee_changes(
+            &mut ex,
+            last.id,
+            first.id,
+            FEE_SAMPLE_SIZE,
+        )
+        .await
+        .context("fetch applied fee changes")?
+        {
+            let Some(json) = batch
+                .iter()
+                .find(|solver_competition| solver_competition.id == change.auction_id)
+                .and_then(|solver_competition| solver_competition.json.as_ref())
+            else {
+                continue;
+            };
+            let Some(matches) = recompute_fee(&mut ex, json, &change, db.config.fee_rounding)
+                .await
+                .context("recompute fee")?
+            else {
+                continue;
+            };
+            fees_recomputed += 1;
+            match matches {
+                true => fees_matching += 1,
+                false => report.mismatch(
+                    change.auction_id,
+                    &[&format!(
+                        "fee of order {:?} is {} {:?}, recomputed differently",
+                        change.order_uid, change.executed_fee, change.executed_fee_token
+                    )],
+                ),
+            }
+        }
+
+        for solver_competition in batch {
+            let solver_competition = match solver_competition.json {
+                None if solver_competition.json_bytes > Some(giant_auction_bytes) => {
+                    fetch_without_solutions(&mut ex, solver_competition.id)
+                        .await
+                        .context("fetch giant auction")?
+                        .unwrap_or(solver_competition)
+                }
+                _ => solver_competition,
+            };
+            let (rederived, duplicates) =
+                compare_derived(&solver_competition, stored.get(&solver_competition.id));
+            if duplicates > 0 {
+                report.duplicate_orders(solver_competition.id, duplicates);
+            }
+            match rederived {
+                Rederived::Skipped => continue,
+                Rederived::Matches => matching += 1,
+                Rederived::Missing => report.mismatch(solver_competition.id, &["missing"]),
+                Rederived::Differs(fields) => report.mismatch(solver_competition.id, &fields),
+            }
+            compared += 1;
+        }
+    }
+
+    info!(
+        "compared competition_auctions with solver_competitions: {compared} auctions, {matching} matching"
+    );
+    for (kind, (checked, wrong)) in &fee_tokens {
+        info!(
+            "checked the fee token of {} order executions of {kind} orders, {} not in the surplus token",
+            big(checked),
+            big(wrong)
+        );
+    }
+    info!(
+        "recomputed {} converted fees from solver_competitions, {} matching",
+        big(fees_recomputed),
+        big(fees_matching)
+    );
+    Ok(report)
+}
+
+/// Converts the fee an order execution had before a proposal was applied
+/// again with the prices of `json`, the solver competition of its auction,
+/// and returns whether that is its fee now. `None` if the fee can't be
+/// converted.
+async fn recompute_fee(
+    ex: &mut PgConnection,
+    json: &serde_json::Value,
+    change: &crate::database_order_executions::AppliedFeeChange,
+    rounding: crate::prices::Rounding,
+) -> Result<Option<bool>> {
+    let Ok(competition) = serde_json::from_value::<SolverCompetitionDB>(json.clone()) else {
+        return Ok(None);
+    };
+    let orders = crate::database_orders::fetch_many(ex, &[change.order_uid])
+        .await
+        .context("fetch order")?;
+    let Some((order, _)) = orders.get(&change.order_uid) else {
+        return Ok(None);
+    };
+    let transaction_hashes: Vec<H256> =
+        crate::database_settlements::fetch_transaction_hashes(ex, change.auction_id)
+            .await
+            .context("fetch transaction hashes")?
+            .into_iter()
+            .map(H256::from)
+            .collect();
+    let winners =
+        crate::solver_competition_api::select_winning_solutions(&competition, &transaction_hashes);
+    let Some(old_fee) = crate::conversions::big_decimal_to_u256(&change.old_fee) else {
+        return Ok(None);
+    };
+    if winners.is_empty() {
+        return Ok(None);
+    }
+    let from = change.old_token.unwrap_or(order.sell_token);
+    let Ok(fee) = settling_prices(&winners, &change.order_uid).convert_rounded(
+        old_fee,
+        H160(from.0),
+        H160(order.surplus_token().0),
+        rounding,
+    ) else {
+        return Ok(None);
+    };
+    Ok(Some(
+        change.executed_fee_token == Some(order.surplus_token())
+            && change.executed_fee == u256_to_big_decimal(&fee),
+    ))
+}
+
+/// The clearing prices of the winning solution that settled `order_uid`, or
+/// of the first one if none did. `winners` must not be empty.
+fn settling_prices(
+    winners: &[&crate::solver_competition_api::SolverSettlement],
+    order_uid: &crate::database_orders::OrderUid,
+) -> crate::prices::Prices {
+    let winner = winners
+        .iter()
+        .find(|winner| winner.orders.iter().any(|o| o.id().0 == order_uid.0))
+        .unwrap_or(&winners[0]);
+    crate::prices::Prices::from(winner.clearing_prices.clone())
+}
+
+/// Prints order executions of settled auctions in `range` without trade and
+/// trades of settled auctions in `range` without order execution, at most
+/// `limit` of them, and how many there are of each.
+pub async fn check_trades(db: &Postgres, range: AuctionRange, limit: i64) -> Result<()> {
+    info!("starting consistency check of order executions against trades");
+
+    let (first, last) = (
+        range.start.unwrap_or(i64::MIN),
+        range.end.unwrap_or(i64::MAX),
+    );
+    let mut ex = db.pool.acquire().await?;
+    let mismatches =
+        crate::database_order_executions::fetch_trade_mismatches(&mut ex, first, last, limit)
+            .await
+            .context("fetch trade mismatches")?;
+    for mismatch in &mismatches {
+        warn!(
+            "auction {}: order {:?} has no {}",
+            mismatch.auction_id, mismatch.order_uid, mismatch.missing
+        );
+    }
+
+    let totals: BTreeMap<_, _> =
+        crate::database_order_executions::count_trade_mismatches(&mut ex, first, last)
+            .await
+            .context("count trade mismatches")?
+            .into_iter()
+            .collect();
+    info!(
+        "order executions without trade: {}, trades without order execution: {}",
+        big(totals.get("trade").unwrap_or(&0)),
+        big(totals.get("order_execution").unwrap_or(&0))
+    );
+    Ok(())
+}
+
+// Function to convert all rows in order_execution table, specifically the `executed_fee` column to be expressed in surplus token instead of the sell token
+#[cfg(feature = "destructive")]
+#[instrument(skip_all)]
+pub async fn convert_executed_fee(
+    db: &Postgres,
+    range: AuctionRange,
+    batch_size: i64,
+    throttle: Duration,
+    limit: Option<usize>,
+) -> Result<Report> {
+    info!(
+        "starting data migration for conversion of executed fees, rounding {:?}",
+        db.config.fee_rounding
+    );
+    let mut report = Report::new(CONVERT_EXECUTED_FEE);
+
+    let mut ex = db.pool.begin().await?;
+
+    // find entry in `solver_competition` with the highest auction_id, as a
+    // starting point
+    let snapshot_max_id = fetch_max_id(&mut ex)
+        .await
+        .context("fetch highest auction id")?;
+    let run_id = crate::database_runs::start(
+        &mut *db.pool.acquire().await?,
+        CONVERT_EXECUTED_FEE,
+        snapshot_max_id,
+        db.config.schema_variant.as_deref(),
+    )
+    .await?;
+    info!("run id: {run_id}, snapshot upper bound: {snapshot_max_id:?}");
+    report.snapshot_max_id = snapshot_max_id;
+
+    let Some(snapshot_max_id) = snapshot_max_id else {
+        info!("solver_competitions is empty, nothing to process");
+        return Ok(report);
+    };
+    let mut current_auction_id = range
+        .end
+        .map_or(snapshot_max_id, |end| end.min(snapshot_max_id))
+        + 1;
+    // runs with an upper bound start at it, so only the others are checkpointed;
+    // shadow writes leave the order executions to a later run
+    let checkpointed = range.end.is_none() && !db.config.shadow_writes;
+    let checkpoint = match checkpointed {
+        true => crate::database_runs::fetch_progress(&mut ex, CONVERT_EXECUTED_FEE)
+            .await
+            .context("fetch checkpoint")?,
+        false => None,
+    };
+    if let Some(checkpoint) = checkpoint {
+        info!("resuming from the checkpoint of a crashed run at auction {checkpoint}");
+        current_auction_id = checkpoint;
+    }
+    let after = range.start.map_or(i64::MIN, |start| start - 1);
+
+    let starting_auction_number = current_auction_id;
+    let mut processed = 0;
+    let mut write_ratio = db
+        .config
+        .max_write_ratio_deviation
+        .map(crate::anomalies::WriteRatio::new);
+
+    loop {
+        let batch_size = match limit {
+            Some(limit) if processed >= limit => {
+                info!(
+                    "processed {} auctions, stopping at the limit",
+                    big(processed)
+                );
+                break;
+            }
+            Some(limit) => batch_size.min((limit - processed) as i64),
+            None => batch_size,
+        };
+        info!(
+            "populating from auction {}, executed in percent: {}",
+            current_auction_id,
+            (starting_auction_number - current_auction_id) as f64 / starting_auction_number as f64
+                * 100.0
+        );
+        let failures = report.errors.len();
+        // order executions of the batch, and how many of them are updated
+        let (mut scanned, mut modified) = (0, 0);
+        // the changes of the batch, audited once it is committed
+        let mut audited = Vec::new();
+        report.progress(current_auction_id);
+        let foreign = crate::database_runs::fetch_foreign_ownership(&mut ex, OWNER)
+            .await
+            .context("fetch ownership")?;
+
+        let competitions =
+            fetch_competition_order_execution(&mut ex, after, current_auction_id, batch_size).await;
+        let Ok(competitions) = competitions else {
+            report.skip(
+                SkipReason::Deserialize,
+                current_auction_id,
+                format_args!("failed to fetch auctions below {}", current_auction_id),
+            );
+            abort_on_failure(&report, db.config.on_error, failures)?;
+            current_auction_id -= 1;
+            continue;
+        };
+
+        if competitions.is_empty() {
+            info!("no more competitions to process");
+            break;
+        }
+
+        info!("processing {} competitions", big(competitions.len()));
+        for solver_competition in &competitions {
+            if skip_foreign(&mut report, &foreign, solver_competition.id) {
+                continue;
+            }
+            let Some(json) = &solver_competition.json else {
+                report.skip(
+                    SkipReason::NullJson,
+                    solver_competition.id,
+                    format_args!("auction: {}", solver_competition.id),
+                );
+                continue;
+            };
+            let start = Instant::now();
+            let competition: SolverCompetitionDB = match serde_json::from_value(json.clone()) {
+                Ok(competition) => competition,
+                Err(err) => {
+                    report.skip(
+                        SkipReason::Deserialize,
+                        solver_competition.id,
+                        format_args!("auction: {}, error: {err}", solver_competition.id),
+                    );
+                    continue;
+                }
+            };
+            let skipped = skipped_orders(json, &competition);
+            if skipped > 0 {
+                warn!(
+                    "skipped {} malformed orders, auction: {}",
+                    skipped, solver_competition.id
+                );
+            }
+
+            // Auctions without solutions have no winner, so no prices to convert
+            // with. They are still populated, only execution level backfills skip them.
+            if competition.solutions.is_empty() {
+                report.skip(
+                    SkipReason::EmptySolutions,
+                    solver_competition.id,
+                    format_args!("auction: {}", solver_competition.id),
+                );
+                continue;
+            }
+            let transaction_hashes: Vec<H256> =
+                crate::database_settlements::fetch_transaction_hashes(
+                    &mut ex,
+                    solver_competition.id,
+                )
+                .await
+                .context("fetch transaction hashes")?
+                .into_iter()
+                .map(H256::from)
+                .collect();
+            let winners = select_winning_solutions(&competition, &transaction_hashes);
+
+            let mut db_time = Duration::ZERO;
+            let query = Instant::now();
+            // find rows in order_execution table with auction_id = solver_competition.id
+            let order_executions: Vec<crate::database_order_executions::OrderExecution> =
+                crate::database_order_executions::fetch(&mut ex, solver_competition.id)
+                    .await
+                    .context("fetch order executions")?;
+            scanned += order_executions.len() as u64;
 
-pub async fn start(args: impl Iterator<Item = String>) {
-    let args = crate::arguments::Arguments::parse_from(args);
+            // find the orders of all order executions at once
+            let order_uids: Vec<_> = order_executions
+                .iter()
+                .map(|order_execution| order_execution.order_uid)
+                .collect();
+            let orders = match &db.config.orders_snapshot {
+                Some(snapshot) => snapshot.fetch_many(&order_uids),
+                None => crate::database_orders::fetch_many(&mut ex, &order_uids)
+                    .await
+                    .context("fetch orders")?,
+            };
+            db_time += query.elapsed();
 
-    let db = Postgres::new(args.db_url.as_str(), NonZero::new(500).unwrap())
-        .await
-        .unwrap();
+            let mut result = Vec::new();
+            let auction_valid_to = competition.auction.median_valid_to();
+            for order_execution in &order_executions {
+                let order = orders.get(&order_execution.order_uid).cloned();
+                check_valid_to(
+                    &mut report,
+                    solver_competition.id,
+                    auction_valid_to,
+                    &order_execution.order_uid,
+                );
+                match order {
+                    Some((order, source)) => result.push((order_execution, order, source)),
+                    None => {
+                        report.order_not_found(solver_competition.id, &order_execution.order_uid)
+                    }
+                }
+            }
 
-    populate_historic_auctions(&db).await.unwrap();
+            // the fee changes of the auction, written at once after all are computed
+            let mut changes = Vec::new();
+            for (order_execution, order, source) in &result {
+                // early rows have no fee token, their fee is in the sell token
+                let fee_token = order_execution
+                    .executed_fee_token
+                    .unwrap_or(order.sell_token);
+                if let Some(surplus_token) = order.fee_conversion(fee_token) {
+                    // update the executed_fee to be in surplus token, using the prices of the
+                    // winning solution that settled the order (there is at least one winner
+                    // since auctions without solutions were skipped)
+                    let prices = settling_prices(&winners, &order_execution.order_uid);
 
-    // sleep for 10 minutes
-    std::thread::sleep(std::time::Duration::from_secs(600));
+                    let Some(executed_fee) = big_decimal_to_u256(&order_execution.executed_fee)
+                    else {
+                        report.skip(
+                            SkipReason::Overflow,
+                            solver_competition.id,
+                            format_args!(
+                                "executed fee {} is not a u256, order_uid: {:?}, auction_id: {}",
+                                order_execution.executed_fee,
+                                order_execution.order_uid,
+                                solver_competition.id
+                            ),
+                        );
+                        continue;
+                    };
+
+                    let fee_in_surplus_token = match prices.convert_rounded(
+                        executed_fee,
+                        H160(fee_token.0),
+                        H160(surplus_token.0),
+                        db.config.fee_rounding,
+                    ) {
+                        Ok(fee) => fee,
+                        Err(err) => {
+                            let reason = match err {
+                                ConversionError::MissingPrice(_) => SkipReason::MissingPrice,
+                                ConversionError::Overflow => SkipReason::Overflow,
+                            };
+                            report.skip(
+                                reason,
+                                solver_competition.id,
+                                format_args!(
+                                    "failed to convert fee for order_uid: {:?}, auction_id: {}, error: {}",
+                                    order_execution.order_uid, solver_competition.id, err
+                                ),
+                            );
+                            continue;
+                        }
+                    };
+
+                    let update = crate::database_order_executions::OrderExecution {
+                        order_uid: order_execution.order_uid,
+                        auction_id: order_execution.auction_id,
+                        executed_fee: u256_to_big_decimal(&fee_in_surplus_token),
+                        executed_fee_token: Some(surplus_token),
+                    };
+                    if db.config.fee_audit.is_some() {
+                        let rate = crate::fee_audits::rate(
+                            &prices,
+                            H160(fee_token.0),
+                            H160(surplus_token.0),
+                        )
+                        .context("conversion rate")?;
+                        audited.push(crate::fee_audits::FeeChange::new(
+                            order_execution,
+                            &update,
+                            &rate,
+                        ));
+                    }
+                    if db.config.dry_run {
+                        report.would_write("order_execution", 1, format_args!("{update:?}"));
+                    } else {
+                        changes.push(((*order_execution).clone(), update));
+                    }
+                    modified += 1;
+                    report.converted(
+                        *source,
+                        format_args!(
+                            "order_uid: {:?}, auction_id: {}, fee: {} -> {}",
+                            order_execution.order_uid,
+                            solver_competition.id,
+                            big(executed_fee),
+                            big(fee_in_surplus_token)
+                        ),
+                    );
+                } else if order_execution.executed_fee_token.is_none() {
+                    // only backfill the fee token, the fee itself stays as it is
+                    let update = crate::database_order_executions::OrderExecution {
+                        executed_fee_token: Some(fee_token),
+                        ..(*order_execution).clone()
+                    };
+                    if db.config.fee_audit.is_some() {
+                        audited.push(crate::fee_audits::FeeChange::new(
+                            order_execution,
+                            &update,
+                            &bigdecimal::BigDecimal::from(1),
+                        ));
+                    }
+                    if db.config.dry_run {
+                        report.would_write("order_execution", 1, format_args!("{update:?}"));
+                    } else {
+                        changes.push(((*order_execution).clone(), update));
+                    }
+                    modified += 1;
+                }
+            }
+            if !changes.is_empty() {
+                let query = Instant::now();
+                write_fees(&mut ex, &db.config, run_id, changes).await?;
+                db_time += query.elapsed();
+            }
+            report.processed(solver_competition.id, json, start, db_time);
+            processed += 1;
+            if !db.config.dry_run {
+                let mut parts = 0;
+                if !report.is_skipped(solver_competition.id) && !db.config.shadow_writes {
+                    parts |= crate::database_auction_status::EXECUTIONS;
+                }
+                if !transaction_hashes.is_empty() {
+                    parts |= crate::database_auction_status::TX_HASHES;
+                }
+                crate::database_auction_status::mark(&mut ex, &[solver_competition.id], parts)
+                    .await
+                    .context("mark auction status")?;
+            }
+        }
+
+        // update the current auction id
+        current_auction_id = competitions.last().unwrap().id;
+
+        // commit each batch separately, together with its checkpoint
+        if checkpointed && !db.config.dry_run {
+            crate::database_runs::save_progress(
+                &mut ex,
+                CONVERT_EXECUTED_FEE,
+                run_id,
+                current_auction_id,
+            )
+            .await
+            .context("save checkpoint")?;
+        }
+        abort_on_failure(&report, db.config.on_error, failures)?;
+        db.config.check_max_updates()?;
+        if let Some(write_ratio) = &mut write_ratio {
+            check_write_ratio(write_ratio, CONVERT_EXECUTED_FEE, scanned, modified)?;
+        }
+        ex.commit().await?;
+        report.committed();
+        if let Some(audit) = &db.config.fee_audit {
+            audit.lock().unwrap().write(&audited)?;
+        }
+
+        db.config.clock.sleep(throttle).await;
+
+        ex = db.pool.begin().await?;
+    }
+
+    report.print();
+
+    crate::database_runs::finish(&mut *db.pool.acquire().await?, run_id).await?;
+    Ok(report)
 }
 
-pub async fn populate_historic_auctions(db: &Postgres) -> Result<()> {
-    println!("starting data migration for auction data");
+/// Writes the fees and fee tokens of the second element of each of `changes`
+/// to the order execution of the first in one statement, or proposes to with
+/// `--shadow-writes`. Fails if any of them changed since it was fetched.
+#[cfg(feature = "destructive")]
+async fn write_fees(
+    ex: &mut PgConnection,
+    config: &Config,
+    run_id: i64,
+    changes: Vec<(
+        crate::database_order_executions::OrderExecution,
+        crate::database_order_executions::OrderExecution,
+    )>,
+) -> Result<()> {
+    if config.shadow_writes {
+        crate::database_order_executions::propose(ex, run_id, &changes)
+            .await
+            .context("propose fees")?;
+    } else {
+        let changed = crate::database_order_executions::update(ex, Some(run_id), &changes)
+            .await
+            .context("database_order_executions::update")?;
+        if changed.len() < changes.len() {
+            bail!(
+                "{} of {} order executions of auction {} changed while their fees were converted",
+                changes.len() - changed.len(),
+                changes.len(),
+                changes[0].0.auction_id
+            );
+        }
+        for (_, update) in &changes {
+            crate::sinks::write_order_execution(&config.sinks, update)?;
+        }
+    }
+    config.count_updates(changes.len() as u64);
+    Ok(())
+}
 
-    const BATCH_SIZE: i64 = 1;
+/// Applies the changes in `file`, computed offline: the fee changes of a
+/// `--fee-audit-file` if it ends in `.csv`, else the auctions of an `export`.
+/// The whole file is applied in one transaction, `batch_size` changes per
+/// statement, and nothing is applied if the fee of an order execution changed
+/// since the file was computed. Dry runs roll the transaction back.
+#[cfg(feature = "destructive")]
+#[instrument(skip_all)]
+pub async fn apply_from_file(db: &Postgres, file: &std::path::Path, batch_size: i64) -> Result<()> {
+    info!("starting to apply the changes in {}", file.display());
+    let reader = std::io::BufReader::new(std::fs::File::open(file).context("open change file")?);
+    let batch_size = usize::try_from(batch_size).unwrap_or(1).max(1);
 
     let mut ex = db.pool.begin().await?;
+    let applied = match file.extension().is_some_and(|extension| extension == "csv") {
+        true => apply_fee_changes(&mut ex, &db.config, reader, batch_size).await?,
+        false => {
+            let mut auctions = crate::exports::read(reader).peekable();
+            let mut applied = 0;
+            while auctions.peek().is_some() {
+                let batch = auctions
+                    .by_ref()
+                    .take(batch_size)
+                    .collect::<Result<Vec<_>>>()?;
+                applied += batch.len();
+                // the exported auctions don't say whether they have a winner
+                save_in_savepoint(&mut ex, &db.config, batch, &BTreeSet::new()).await?;
+            }
+            applied
+        }
+    };
+    if db.config.dry_run {
+        info!("would apply {} changes, rolling back", big(applied));
+        return Ok(());
+    }
+    db.config.check_max_updates()?;
+    ex.commit().await?;
+    info!("applied {} changes", big(applied));
+    Ok(())
+}
 
-    // find entry in `competition_auctions` with the lowest auction_id, as a
-    // starting point
-    let current_auction_id: Option<i64> =
-        sqlx::query_scalar::<_, Option<i64>>("SELECT MIN(id) FROM competition_auctions;")
-            .fetch_one(ex.deref_mut())
+/// Applies the fee changes of an audit trail, see [`crate::fee_audits`], and
+/// returns how many there were. Fails if any of them is stale.
+#[cfg(feature = "destructive")]
+async fn apply_fee_changes(
+    ex: &mut PgConnection,
+    config: &Config,
+    reader: impl std::io::Read,
+    batch_size: usize,
+) -> Result<usize> {
+    let mut changes = crate::fee_audits::read(reader).peekable();
+    let mut applied = 0;
+    while changes.peek().is_some() {
+        let batch = changes
+            .by_ref()
+            .take(batch_size)
+            .map(|change| change?.executions())
+            .collect::<Result<Vec<_>>>()?;
+        let changed = crate::database_order_executions::update(ex, None, &batch)
             .await
-            .context("fetch lowest auction id")?;
+            .context("apply fee changes")?;
+        if changed.len() < batch.len() {
+            let changed: std::collections::HashSet<_> = changed.into_iter().collect();
+            let (stale, _) = batch
+                .iter()
+                .find(|(old, _)| !changed.contains(&(old.order_uid, old.auction_id)))
+                .context("no stale change although fewer rows were updated")?;
+            bail!(
+                "{} fee changes are stale, like the one of order {:?} in auction {}: the order \
+                 execution changed or is missing since the file was computed, applied none",
+                batch.len() - changed.len(),
+                stale.order_uid,
+                stale.auction_id
+            );
+        }
+        config.count_updates(batch.len() as u64);
+        applied += batch.len();
+    }
+    Ok(applied)
+}
+
+/// Writes the fees proposed by shadow writes of `convert_executed_fee` to
+/// `order_execution`, in batches of `batch_size`.
+#[cfg(feature = "destructive")]
+#[instrument(skip_all)]
+pub async fn apply_proposals(db: &Postgres, batch_size: i64, throttle: Duration) -> Result<()> {
+    info!("starting to apply the proposed fees");
 
-    let Some(mut current_auction_id) = current_auction_id else {
-        println!("competition_auctions is empty, nothing to process");
+    if db.config.dry_run {
+        let proposed =
+            crate::database_order_executions::count_proposals(&mut *db.pool.acquire().await?)
+                .await
+                .context("count proposals")?;
+        info!("would apply up to {} proposed fees", big(proposed));
         return Ok(());
-    };
+    }
 
-    let starting_auction_number = current_auction_id;
+    let (mut applied, mut stale) = (0, 0);
+    loop {
+        let mut ex = db.pool.begin().await?;
+        let (batch_applied, batch_stale) =
+            crate::database_order_executions::apply_proposals(&mut ex, batch_size)
+                .await
+                .context("apply proposals")?;
+        if batch_applied + batch_stale == 0 {
+            break;
+        }
+        db.config.count_updates(batch_applied as u64);
+        db.config.check_max_updates()?;
+        ex.commit().await?;
+        applied += batch_applied;
+        stale += batch_stale;
+        info!("applied {} proposed fees so far", big(applied));
+
+        db.config.clock.sleep(throttle).await;
+    }
+    if stale > 0 {
+        warn!(
+            "{} proposals are stale because their order execution changed since, \
+             run convert-fees again to propose them anew",
+            big(stale)
+        );
+    }
+    info!(
+        "applied {} proposed fees, {} stale",
+        big(applied),
+        big(stale)
+    );
+    Ok(())
+}
+
+/// Restores what `migration` overwrote from its backup, in batches of
+/// `batch_size`, unless it changed since, and clears the part it wrote from
+/// the status of the restored auctions.
+#[cfg(feature = "destructive")]
+#[instrument(skip_all)]
+pub async fn rollback(
+    db: &Postgres,
+    migration: crate::arguments::RollbackMigration,
+    batch_size: i64,
+    throttle: Duration,
+) -> Result<()> {
+    let crate::arguments::RollbackMigration::ConvertFees = migration;
+    info!("starting to roll back the converted fees");
+
+    if db.config.dry_run {
+        let backups =
+            crate::database_order_executions::count_backups(&mut *db.pool.acquire().await?)
+                .await
+                .context("count backups")?;
+        info!("would restore {} fees", big(backups));
+        return Ok(());
+    }
+
+    let (mut restored, mut skipped) = (0, 0);
+    loop {
+        let mut ex = db.pool.begin().await?;
+        let backups = crate::database_order_executions::restore_backup(&mut ex, batch_size)
+            .await
+            .context("restore backup")?;
+        if backups.is_empty() {
+            break;
+        }
+        let mut auction_ids = Vec::new();
+        for backup in backups {
+            if backup.restored {
+                auction_ids.push(backup.auction_id);
+            } else {
+                warn!(
+                    "not restoring the fee of order {:?} in auction {}, it changed since it was converted",
+                    backup.order_uid, backup.auction_id
+                );
+                skipped += 1;
+            }
+        }
+        restored += auction_ids.len();
+        db.config.count_updates(auction_ids.len() as u64);
+        auction_ids.sort_unstable();
+        auction_ids.dedup();
+        crate::database_auction_status::unmark(
+            &mut ex,
+            &auction_ids,
+            crate::database_auction_status::EXECUTIONS,
+        )
+        .await
+        .context("unmark auction status")?;
+        db.config.check_max_updates()?;
+        ex.commit().await?;
+        info!("restored {} fees so far", big(restored));
+
+        db.config.clock.sleep(throttle).await;
+    }
+    info!(
+        "restored {} fees, skipped {} that changed since",
+        big(restored),
+        big(skipped)
+    );
+    Ok(())
+}
+
+/// Fills the fee token of order executions from before the column existed
+/// with the sell token of their order, which is what those fees are in.
+#[cfg(feature = "destructive")]
+#[instrument(skip_all)]
+pub async fn backfill_executed_fee_token(db: &Postgres, throttle: Duration) -> Result<Report> {
+    info!("starting data migration for backfilling executed fee tokens");
+    let mut report = Report::new(BACKFILL_EXECUTED_FEE_TOKEN);
+
+    const BATCH_SIZE: i64 = 100;
+
+    let mut ex = db.pool.begin().await?;
+
+    let snapshot_max_id = fetch_max_id(&mut ex)
+        .await
+        .context("fetch highest auction id")?;
+    let run_id = crate::database_runs::start(
+        &mut *db.pool.acquire().await?,
+        BACKFILL_EXECUTED_FEE_TOKEN,
+        snapshot_max_id,
+        db.config.schema_variant.as_deref(),
+    )
+    .await?;
+    info!("run id: {run_id}, snapshot upper bound: {snapshot_max_id:?}");
+    report.snapshot_max_id = snapshot_max_id;
+
+    let Some(snapshot_max_id) = snapshot_max_id else {
+        info!("solver_competitions is empty, nothing to process");
+        return Ok(report);
+    };
+    let mut current_auction_id = snapshot_max_id + 1;
+    let mut processed_auctions = 0;
 
     loop {
-        println!(
-            "populating historic auctions from auction {}, executed in percent: {}",
+        report.progress(current_auction_id);
+        let foreign = crate::database_runs::fetch_foreign_ownership(&mut ex, OWNER)
+            .await
+            .context("fetch ownership")?;
+        let auction_ids = crate::database_order_executions::fetch_auctions_without_fee_token(
+            &mut ex,
             current_auction_id,
-            (starting_auction_number - current_auction_id) as f64 / starting_auction_number as f64
-                * 100.0
+            BATCH_SIZE,
+        )
+        .await
+        .context("fetch auctions without fee token")?;
+        let Some(&last_auction_id) = auction_ids.last() else {
+            info!("no more auctions to process");
+            break;
+        };
+        info!(
+            "backfilling fee tokens of {} auctions from auction {}",
+            big(auction_ids.len()),
+            auction_ids[0]
         );
 
-        // fetch the next batch of auctions
-        let competitions = fetch_batch(&mut ex, current_auction_id, BATCH_SIZE).await;
-        let Ok(competitions) = competitions else {
-            // added because auction 3278851 has null json - unexpected entry in the database
-            println!("failed to deserialize {}", current_auction_id);
-            current_auction_id -= 1;
-            continue;
-        };
+        for auction_id in auction_ids {
+            if skip_foreign(&mut report, &foreign, auction_id) {
+                continue;
+            }
+            let (updated, missing) =
+                crate::database_order_executions::backfill_fee_token(&mut ex, auction_id)
+                    .await
+                    .context("backfill fee token")?;
+            match db.config.dry_run {
+                true => report.would_write(
+                    "order_execution",
+                    updated as usize,
+                    format_args!("fee tokens of {updated} executions of auction {auction_id}"),
+                ),
+                false => db.config.count_updates(updated),
+            }
+            for order_uid in missing {
+                report.order_not_found(auction_id, &order_uid);
+            }
+            processed_auctions += 1;
+        }
 
-        if competitions.is_empty() {
-            println!("no more auctions to process");
-            break;
+        // commit each batch separately, a dry run only computes the updates
+        match db.config.dry_run {
+            true => ex.rollback().await?,
+            false => {
+                db.config.check_max_updates()?;
+                ex.commit().await?
+            }
         }
+        report.committed();
 
-        println!("processing {} auctions", competitions.len());
+        db.config.clock.sleep(throttle).await;
 
-        for solver_competition in &competitions {
-            let competition: SolverCompetitionDB =
-                serde_json::from_value(solver_competition.json.clone())
-                    .context("deserialize SolverCompetitionDB")?;
-
-            // populate historic auctions
-            let auction = Auction {
-                id: solver_competition.id,
-                block: i64::try_from(competition.auction_start_block).context("block overflow")?,
-                deadline: solver_competition.deadline,
-                order_uids: competition
-                    .auction
-                    .orders
-                    .iter()
-                    .map(|order| ByteArray(order.0))
-                    .collect(),
-                price_tokens: competition
-                    .auction
-                    .prices
-                    .keys()
-                    .map(|token| ByteArray(token.0))
-                    .collect(),
-                price_values: competition
-                    .auction
-                    .prices
-                    .values()
-                    .map(crate::database_solver_competition::u256_to_big_decimal)
-                    .collect(),
-                surplus_capturing_jit_order_owners: solver_competition
-                    .surplus_capturing_jit_order_owners
-                    .clone(),
+        ex = db.pool.begin().await?;
+
+        current_auction_id = last_auction_id;
+    }
+
+    info!("auctions processed: {}", processed_auctions);
+    report.print();
+
+    crate::database_runs::finish(&mut *db.pool.acquire().await?, run_id).await?;
+    Ok(report)
+}
+
+/// The sum of an executed fee and a surplus fee, `None` if it doesn't fit the
+/// uint256 it is stored as.
+#[cfg(feature = "destructive")]
+fn consolidated_fee(
+    executed_fee: &bigdecimal::BigDecimal,
+    surplus_fee: &bigdecimal::BigDecimal,
+) -> Option<bigdecimal::BigDecimal> {
+    let sum = big_decimal_to_u256(executed_fee)?.checked_add(big_decimal_to_u256(surplus_fee)?)?;
+    Some(u256_to_big_decimal(&sum))
+}
+
+/// Folds the legacy solver determined `surplus_fee` of order executions into
+/// `executed_fee`, so there is a single fee per execution.
+///
+/// Surplus fees are in the sell token of the order, like signed fees were, so
+/// the consolidated fee is their sum in the sell token. Executions whose fee
+/// is already in another token are skipped rather than adding up amounts of
+/// different tokens, and so are executions whose sum does not fit a uint256.
+#[cfg(feature = "destructive")]
+#[instrument(skip_all)]
+pub async fn consolidate_surplus_fee(db: &Postgres, throttle: Duration) -> Result<Report> {
+    info!("starting data migration for consolidating surplus fees");
+    let mut report = Report::new(CONSOLIDATE_SURPLUS_FEE);
+
+    const BATCH_SIZE: i64 = 100;
+
+    let mut ex = db.pool.begin().await?;
+
+    let snapshot_max_id = fetch_max_id(&mut ex)
+        .await
+        .context("fetch highest auction id")?;
+    report.snapshot_max_id = snapshot_max_id;
+
+    if !crate::database_order_executions::has_surplus_fee_column(&mut ex)
+        .await
+        .context("check surplus_fee column")?
+    {
+        info!("order_execution has no surplus_fee column, nothing to process");
+        return Ok(report);
+    }
+
+    let run_id = crate::database_runs::start(
+        &mut *db.pool.acquire().await?,
+        CONSOLIDATE_SURPLUS_FEE,
+        snapshot_max_id,
+        db.config.schema_variant.as_deref(),
+    )
+    .await?;
+    info!("run id: {run_id}, snapshot upper bound: {snapshot_max_id:?}");
+
+    let Some(snapshot_max_id) = snapshot_max_id else {
+        info!("solver_competitions is empty, nothing to process");
+        return Ok(report);
+    };
+    let mut current_auction_id = snapshot_max_id + 1;
+    let mut consolidated = 0;
+
+    loop {
+        report.progress(current_auction_id);
+        let foreign = crate::database_runs::fetch_foreign_ownership(&mut ex, OWNER)
+            .await
+            .context("fetch ownership")?;
+        let order_executions = crate::database_order_executions::fetch_legacy_surplus_fees(
+            &mut ex,
+            current_auction_id,
+            BATCH_SIZE,
+        )
+        .await
+        .context("fetch legacy surplus fees")?;
+        let Some(last) = order_executions.last() else {
+            info!("no more auctions to process");
+            break;
+        };
+        let last_auction_id = last.auction_id;
+        info!(
+            "consolidating {} surplus fees from auction {}",
+            big(order_executions.len()),
+            order_executions[0].auction_id
+        );
+
+        for order_execution in order_executions {
+            if skip_foreign(&mut report, &foreign, order_execution.auction_id) {
+                continue;
+            }
+            let order = crate::database_orders::fetch(&mut ex, &order_execution.order_uid)
+                .await
+                .context("fetch order")?;
+            let Some((order, _)) = order else {
+                report.order_not_found(order_execution.auction_id, &order_execution.order_uid);
+                continue;
             };
 
-            if let Err(err) = crate::database_solver_competition::save(&mut ex, auction).await {
-                println!(
-                    "failed to save auction: {:?}, auction: {}",
-                    err, solver_competition.id
+            let fee_token = order_execution
+                .executed_fee_token
+                .unwrap_or(order.sell_token);
+            if fee_token != order.sell_token {
+                report.skip(
+                    SkipReason::FeeTokenMismatch,
+                    order_execution.auction_id,
+                    format_args!(
+                        "executed fee is in {:?}, surplus fee in {:?}, order_uid: {:?}, auction_id: {}",
+                        fee_token,
+                        order.sell_token,
+                        order_execution.order_uid,
+                        order_execution.auction_id
+                    ),
+                );
+                continue;
+            }
+
+            let executed_fee = order_execution.executed_fee.clone().unwrap_or_default();
+            let Some(consolidated_fee) =
+                consolidated_fee(&executed_fee, &order_execution.surplus_fee)
+            else {
+                report.skip(
+                    SkipReason::Overflow,
+                    order_execution.auction_id,
+                    format_args!(
+                        "executed fee {} + surplus fee {} is not a u256, order_uid: {:?}, auction_id: {}",
+                        executed_fee,
+                        order_execution.surplus_fee,
+                        order_execution.order_uid,
+                        order_execution.auction_id
+                    ),
                 );
+                continue;
+            };
+            let update = crate::database_order_executions::OrderExecution {
+                order_uid: order_execution.order_uid,
+                auction_id: order_execution.auction_id,
+                executed_fee: consolidated_fee.clone(),
+                executed_fee_token: Some(fee_token),
+            };
+            if db.config.dry_run {
+                report.would_write("order_execution", 1, format_args!("{update:?}"));
+            } else {
+                crate::database_order_executions::consolidate_surplus_fee(&mut ex, update)
+                    .await
+                    .context("consolidate surplus fee")?;
+                db.config.count_updates(1);
             }
+            info!(
+                "consolidated: order_uid: {:?}, auction_id: {}, executed fee {} + surplus fee {} = {} in {:?}",
+                order_execution.order_uid,
+                order_execution.auction_id,
+                executed_fee,
+                order_execution.surplus_fee,
+                consolidated_fee,
+                fee_token
+            );
+            consolidated += 1;
         }
 
         // commit each batch separately
+        db.config.check_max_updates()?;
         ex.commit().await?;
+        report.committed();
 
-        // sleep for 50ms
-        std::thread::sleep(std::time::Duration::from_millis(50));
+        db.config.clock.sleep(throttle).await;
 
         ex = db.pool.begin().await?;
 
-        // update the current auction id
-        current_auction_id = competitions.last().unwrap().id;
+        current_auction_id = last_auction_id;
     }
 
-    Ok(())
+    info!("consolidated surplus fees: {}", consolidated);
+    report.print();
+
+    crate::database_runs::finish(&mut *db.pool.acquire().await?, run_id).await?;
+    Ok(report)
 }
 
-// Function to convert all rows in order_execution table, specifically the `executed_fee` column to be expressed in surplus token instead of the sell token
-pub async fn convert_executed_fee(db: &Postgres) -> Result<()> {
-    println!("starting data migration for conversion of executed fees");
+/// Writes the executed amounts of legacy orders, which only store the amount
+/// of the side fixed by their kind, to `proposed_trade_executions` for every
+/// solution of every auction.
+///
+/// Orders of the winning solutions, which got settled, are given the amounts
+/// of their trade. The orders of all other solutions are derived from the
+/// clearing prices of their solution, see
+/// [`crate::solver_competition_api::SolverSettlement::executed_amounts`].
+#[cfg(feature = "destructive")]
+#[instrument(skip_all)]
+pub async fn recompute_executed_amounts(db: &Postgres, throttle: Duration) -> Result<Report> {
+    info!("starting data migration for recomputing executed amounts");
+    let mut report = Report::new(RECOMPUTE_EXECUTED_AMOUNTS);
 
-    let mut ex = db.pool.begin().await?;
+    const BATCH_SIZE: i64 = 1;
 
-    // find entry in `solver_competition` with the lowest auction_id, as a
-    // starting point
-    let current_auction_id: Option<i64> =
-        sqlx::query_scalar::<_, Option<i64>>("SELECT MAX(id) FROM solver_competitions;")
-            .fetch_one(ex.deref_mut())
-            .await
-            .context("fetch highest auction id")?;
+    let mut ex = db.pool.begin().await?;
 
-    let Some(mut current_auction_id) = current_auction_id else {
-        println!("solver_competitions is empty, nothing to process");
-        return Ok(());
-    };
+    let snapshot_max_id = fetch_max_id(&mut ex)
+        .await
+        .context("fetch highest auction id")?;
+    report.snapshot_max_id = snapshot_max_id;
 
-    let starting_auction_number = current_auction_id;
+    if !crate::database_proposed_solutions::has_trade_executions_table(&mut ex)
+        .await
+        .context("check proposed_trade_executions table")?
+    {
+        info!("proposed_trade_executions does not exist, nothing to process");
+        return Ok(report);
+    }
 
-    loop {
-        println!(
-            "populating from auction {}, executed in percent: {}",
-            current_auction_id,
-            (starting_auction_number - current_auction_id) as f64 / starting_auction_number as f64
-                * 100.0
-        );
+    let run_id = crate::database_runs::start(
+        &mut *db.pool.acquire().await?,
+        RECOMPUTE_EXECUTED_AMOUNTS,
+        snapshot_max_id,
+        db.config.schema_variant.as_deref(),
+    )
+    .await?;
+    info!("run id: {run_id}, snapshot upper bound: {snapshot_max_id:?}");
 
-        let competitions = fetch_competition_order_execution(&mut ex, current_auction_id, 1).await;
-        let Ok(competitions) = competitions else {
-            // added because auction 3278851 has null json - unexpected entry in the database
-            println!("failed to deserialize {}", current_auction_id);
-            current_auction_id -= 1;
-            continue;
-        };
+    let Some(snapshot_max_id) = snapshot_max_id else {
+        info!("solver_competitions is empty, nothing to process");
+        return Ok(report);
+    };
+    let mut current_auction_id = snapshot_max_id + 1;
+    let mut recomputed = 0;
 
-        if competitions.is_empty() {
-            println!("no more competitions to process");
+    loop {
+        let failures = report.errors.len();
+        report.progress(current_auction_id);
+        let foreign = crate::database_runs::fetch_foreign_ownership(&mut ex, OWNER)
+            .await
+            .context("fetch ownership")?;
+        let competitions =
+            fetch_competition_order_execution(&mut ex, i64::MIN, current_auction_id, BATCH_SIZE)
+                .await
+                .context("fetch solver competitions")?;
+        let Some(last) = competitions.last() else {
+            info!("no more auctions to process");
             break;
-        }
+        };
+        current_auction_id = last.id;
 
-        println!("processing {} competitions", competitions.len());
         for solver_competition in &competitions {
-            let competition: SolverCompetitionDB =
-                serde_json::from_value(solver_competition.json.clone())
-                    .context("deserialize SolverCompetitionDB")?;
-
-            // find rows in order_execution table with auction_id = solver_competition.id
-            let order_executions: Vec<crate::database_order_executions::OrderExecution> =
-                crate::database_order_executions::fetch(&mut ex, solver_competition.id)
-                    .await
-                    .context("fetch order executions")?;
+            if skip_foreign(&mut report, &foreign, solver_competition.id) {
+                continue;
+            }
+            let Some(json) = &solver_competition.json else {
+                report.skip(
+                    SkipReason::NullJson,
+                    solver_competition.id,
+                    format_args!("auction: {}", solver_competition.id),
+                );
+                continue;
+            };
+            let start = Instant::now();
+            let competition: SolverCompetitionDB = match serde_json::from_value(json.clone()) {
+                Ok(competition) => competition,
+                Err(err) => {
+                    report.skip(
+                        SkipReason::Deserialize,
+                        solver_competition.id,
+                        format_args!("auction: {}, error: {}", solver_competition.id, err),
+                    );
+                    continue;
+                }
+            };
 
-            // find orders for each order_execution
-            let mut result = Vec::new();
-            for order_execution in &order_executions {
-                // find order in orders table with order_uid = order_execution.order_uid
-                let order: Option<crate::database_orders::Order> =
-                    crate::database_orders::fetch_from_orders(&mut ex, &order_execution.order_uid)
+            let mut db_time = Duration::ZERO;
+            let query = Instant::now();
+            let trades = crate::database_settlements::fetch_trades(&mut ex, solver_competition.id)
+                .await
+                .context("fetch trades")?;
+            let transaction_hashes: Vec<H256> =
+                crate::database_settlements::fetch_transaction_hashes(
+                    &mut ex,
+                    solver_competition.id,
+                )
+                .await
+                .context("fetch transaction hashes")?
+                .into_iter()
+                .map(H256::from)
+                .collect();
+            db_time += query.elapsed();
+            let winners = select_winning_solutions(&competition, &transaction_hashes);
+            // the executed amounts of the settled trades by order, the sell
+            // amounts without fee like solutions state them
+            let mut traded: std::collections::HashMap<_, Vec<_>> = Default::default();
+            for trade in &trades {
+                let amounts = big_decimal_to_u256(&trade.sell_amount)
+                    .zip(big_decimal_to_u256(&trade.fee_amount))
+                    .and_then(|(sell_amount, fee_amount)| sell_amount.checked_sub(fee_amount))
+                    .zip(big_decimal_to_u256(&trade.buy_amount));
+                if let Some(amounts) = amounts {
+                    traded.entry(trade.order_uid).or_default().push(amounts);
+                }
+            }
+            let auction_valid_to = competition.auction.median_valid_to();
+            for (solution_uid, solution) in competition.solutions.iter().enumerate() {
+                for order in &solution.orders {
+                    if !matches!(order, Order::Legacy { .. }) {
+                        continue;
+                    }
+                    let order_uid = crate::database_orders::ByteArray(order.id().0);
+                    check_valid_to(
+                        &mut report,
+                        solver_competition.id,
+                        auction_valid_to,
+                        &order_uid,
+                    );
+                    let query = Instant::now();
+                    let details = crate::database_orders::fetch(&mut ex, &order_uid)
                         .await
                         .context("fetch order")?;
-                match order {
-                    Some(order) => {
-                        result.push((order_execution, order));
-                    }
-                    None => {
-                        // find order in jit_orders table with order_uid = order_execution.order_uid
-                        let jit_order: Option<crate::database_orders::Order> =
-                            crate::database_orders::fetch_from_jit_orders(
-                                &mut ex,
-                                &order_execution.order_uid,
-                            )
-                            .await
-                            .context("fetch jit order")?;
-                        match jit_order {
-                            Some(jit_order) => {
-                                result.push((order_execution, jit_order));
-                            }
-                            None => {
-                                println!(
-                                    "order not found for order_uid: {:?}, auction_id: {}",
-                                    order_execution.order_uid, solver_competition.id
-                                );
-                            }
+                    db_time += query.elapsed();
+                    let Some((details, _)) = details else {
+                        report.order_not_found(solver_competition.id, &order_uid);
+                        continue;
+                    };
+                    let trades = traded.get(&order_uid).map_or(&[][..], Vec::as_slice);
+                    let amounts = solution.executed_amounts(
+                        order,
+                        &winners,
+                        details.kind,
+                        H160(details.sell_token.0),
+                        H160(details.buy_token.0),
+                        trades,
+                    );
+                    let (executed_sell, executed_buy) = match amounts {
+                        Ok(amounts) => amounts,
+                        Err(err) => {
+                            let reason = match err {
+                                ConversionError::MissingPrice(_) => SkipReason::MissingPrice,
+                                ConversionError::Overflow => SkipReason::Overflow,
+                            };
+                            report.skip(
+                                reason,
+                                solver_competition.id,
+                                format_args!(
+                                    "order_uid: {:?}, auction_id: {}, solution: {}, error: {}",
+                                    order_uid, solver_competition.id, solution_uid, err
+                                ),
+                            );
+                            continue;
                         }
+                    };
+
+                    let trade = crate::database_proposed_solutions::TradeExecution {
+                        auction_id: solver_competition.id,
+                        solution_uid: solution_uid as i64,
+                        order_uid,
+                        executed_sell: u256_to_big_decimal(&executed_sell),
+                        executed_buy: u256_to_big_decimal(&executed_buy),
+                    };
+                    if db.config.dry_run {
+                        report.would_write(
+                            "proposed_trade_executions",
+                            1,
+                            format_args!("{trade:?}"),
+                        );
+                    } else {
+                        let query = Instant::now();
+                        crate::database_proposed_solutions::upsert_trade_execution(&mut ex, trade)
+                            .await
+                            .context("upsert trade execution")?;
+                        db.config.count_updates(1);
+                        db_time += query.elapsed();
                     }
+                    recomputed += 1;
                 }
             }
-
-            for (order_execution, order) in &result {
-                // fee needs to be updated for sell orders that have fee in sell token
-                if order.kind == crate::database_orders::OrderKind::Sell
-                    && order_execution.executed_fee_token == order.sell_token
-                {
-                    // update the executed_fee to be in buy token
-                    let sell_token_price = competition
-                        .solutions
-                        .last()
-                        .unwrap()
-                        .clearing_prices
-                        .get(&H160(order.sell_token.0));
-                    let buy_token_price = competition
-                        .solutions
-                        .last()
-                        .unwrap()
-                        .clearing_prices
-                        .get(&H160(order.buy_token.0));
-                    let (sell_token_price, buy_token_price) =
-                        match (sell_token_price, buy_token_price) {
-                            (Some(sell_token_price), Some(buy_token_price)) => {
-                                (sell_token_price, buy_token_price)
-                            }
-                            _ => {
-                                println!(
-                                    "prices not found for order_uid: {:?}, auction_id: {}",
-                                    order_execution.order_uid, solver_competition.id
-                                );
-                                continue;
-                            }
-                        };
-
-                    let executed_fee = big_decimal_to_u256(&order_execution.executed_fee).unwrap();
-
-                    let fee_in_buy_token = executed_fee * sell_token_price / buy_token_price;
-
-                    crate::database_order_executions::update(
-                        &mut ex,
-                        crate::database_order_executions::OrderExecution {
-                            order_uid: order_execution.order_uid,
-                            auction_id: order_execution.auction_id,
-                            executed_fee: crate::database_solver_competition::u256_to_big_decimal(
-                                &fee_in_buy_token,
-                            ),
-                            executed_fee_token: order.buy_token,
-                        },
-                    )
-                    .await
-                    .context("database_order_executions::update")?;
-                }
+            report.processed(solver_competition.id, json, start, db_time);
+            if !db.config.dry_run && !report.is_skipped(solver_competition.id) {
+                crate::database_auction_status::mark(
+                    &mut ex,
+                    &[solver_competition.id],
+                    crate::database_auction_status::SOLUTIONS,
+                )
+                .await
+                .context("mark auction status")?;
             }
         }
 
         // commit each batch separately
+        abort_on_failure(&report, db.config.on_error, failures)?;
+        db.config.check_max_updates()?;
         ex.commit().await?;
+        report.committed();
 
-        // sleep for 50ms
-        std::thread::sleep(std::time::Duration::from_millis(50));
+        db.config.clock.sleep(throttle).await;
 
         ex = db.pool.begin().await?;
-
-        // update the current auction id
-        current_auction_id = competitions.last().unwrap().id;
     }
 
-    Ok(())
+    info!("recomputed executed amounts: {}", recomputed);
+    report.print();
+
+    crate::database_runs::finish(&mut *db.pool.acquire().await?, run_id).await?;
+    Ok(report)
 }
 
-// pub async fn fix_missing_historic_auctions(db: &Postgres) -> Result<()> {
-//     println!("starting data migration fix for auction data");
-
-//     const BATCH_SIZE: i64 = 1;
-
-//     let mut ex = db.pool.begin().await?;
-
-//     // there is a gap of entries in `competition_auctions` that need to be filled
-
-//     // we identify this gap by looking at the `solver_competitions` table
-
-//     loop {
-//         // fetch the next batch of auctions
-//         let competitions = fetch_batch(&mut ex, BATCH_SIZE).await;
-//         let Ok(competitions) = competitions else {
-//             // added because auction 3278851 has null json - this is a one-off fix
-//             println!("failed to deserialize");
-//             continue;
-//         };
-
-//         if competitions.is_empty() {
-//             println!("no more auctions to process");
-//             break;
-//         }
-
-//         println!(
-//             "processing {} auctions, first one {}",
-//             competitions.len(),
-//             competitions.last().map(|c| c.id).unwrap_or(0)
-//         );
-
-//         for solver_competition in &competitions {
-//             let competition =
-//                 serde_json::from_value::<SolverCompetitionDB>(solver_competition.json.clone())
-//                     .context("deserialize SolverCompetitionDB");
-
-//             let Ok(competition) = competition else {
-//                 println!(
-//                     "failed to deserialize SolverCompetitionDB, auction: {}",
-//                     solver_competition.id
-//                 );
-//                 continue;
-//             };
-
-//             // populate historic auctions
-//             let auction = Auction {
-//                 id: solver_competition.id,
-//                 block: i64::try_from(competition.auction_start_block).context("block overflow")?,
-//                 deadline: solver_competition.deadline,
-//                 order_uids: competition
-//                     .auction
-//                     .orders
-//                     .iter()
-//                     .map(|order| ByteArray(order.0))
-//                     .collect(),
-//                 price_tokens: competition
-//                     .auction
-//                     .prices
-//                     .keys()
-//                     .map(|token| ByteArray(token.0))
-//                     .collect(),
-//                 price_values: competition
-//                     .auction
-//                     .prices
-//                     .values()
-//                     .map(crate::database_solver_competition::u256_to_big_decimal)
-//                     .collect(),
-//                 surplus_capturing_jit_order_owners: solver_competition
-//                     .surplus_capturing_jit_order_owners
-//                     .clone(),
-//             };
-
-//             if let Err(err) = crate::database_solver_competition::save(&mut ex, auction).await {
-//                 println!(
-//                     "failed to save auction: {:?}, auction: {}",
-//                     err, solver_competition.id
-//                 );
-//             }
-//         }
-
-//         // commit each batch separately
-//         ex.commit().await?;
-
-//         // sleep for 50ms
-//         std::thread::sleep(std::time::Duration::from_millis(50));
-
-//         ex = db.pool.begin().await?;
-//     }
-
-//     Ok(())
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partition_into_workers() {
+        let ranges = |workers: Vec<WorkerProgress>| {
+            workers
+                .iter()
+                .map(|worker| {
+                    assert_eq!(worker.last_auction_id, worker.end_id + 1);
+                    (worker.start_id, worker.end_id)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ranges(partition(1, 10, 3)), vec![(7, 10), (4, 6), (1, 3)]);
+        assert_eq!(ranges(partition(5, 6, 4)), vec![(6, 6), (5, 5)]);
+        assert_eq!(ranges(partition(5, 5, 1)), vec![(5, 5)]);
+        assert!(partition(6, 5, 2).is_empty());
+    }
+
+    #[test]
+    fn frontier_kinds() {
+        let unbounded = AuctionRange::default();
+        let bounded = AuctionRange {
+            start: Some(100),
+            end: Some(200),
+        };
+        assert_eq!(
+            frontier_kind(Direction::Descending, unbounded),
+            FrontierKind::Descending
+        );
+        assert_eq!(
+            frontier_kind(
+                Direction::Descending,
+                AuctionRange {
+                    start: Some(100),
+                    end: None
+                }
+            ),
+            FrontierKind::Descending
+        );
+        // the ascending pass of an interrupted bidirectional run leaves a gap
+        // above it
+        assert_eq!(
+            frontier_kind(Direction::Ascending, unbounded),
+            FrontierKind::Ascending
+        );
+        // so does the reprocessing of an old window
+        assert_eq!(
+            frontier_kind(Direction::Descending, bounded),
+            FrontierKind::Bounded
+        );
+    }
+
+    #[test]
+    fn retry_backoff() {
+        let clock = crate::clock::Clock::manual();
+        futures::executor::block_on(async {
+            for attempt in 1..RETRY_ATTEMPTS {
+                back_off(&clock, attempt).await;
+            }
+        });
+        assert_eq!(clock.waited(), Duration::from_secs(3));
+    }
+
+    #[cfg(feature = "destructive")]
+    #[test]
+    fn consolidated_fee_overflow() {
+        use primitive_types::U256;
+
+        let big = |value: U256| u256_to_big_decimal(&value);
+        assert_eq!(
+            consolidated_fee(&big(U256::from(2)), &big(U256::from(3))),
+            Some(big(U256::from(5)))
+        );
+        assert_eq!(
+            consolidated_fee(&big(U256::MAX - 1), &big(U256::one())),
+            Some(big(U256::MAX))
+        );
+        // the sum, or either fee, exceeding a uint256 skips the execution
+        assert_eq!(consolidated_fee(&big(U256::MAX), &big(U256::one())), None);
+        assert_eq!(
+            consolidated_fee(
+                &(big(U256::MAX) + bigdecimal::BigDecimal::from(1)),
+                &big(U256::zero())
+            ),
+            None
+        );
+        assert_eq!(
+            consolidated_fee(&big(U256::zero()), &bigdecimal::BigDecimal::from(-1)),
+            None
+        );
+    }
+}
@@ -1,12 +1,41 @@
 use {
     primitive_types::U256,
+    schemars::{
+        gen::SchemaGenerator,
+        schema::{InstanceType, NumberValidation, Schema, SchemaObject},
+        JsonSchema,
+    },
     serde::{de, Deserializer, Serializer},
     serde_with::{DeserializeAs, SerializeAs},
     std::fmt,
 };
 
+/// Serializes a `U256` as a decimal string.
+///
+/// Deserialization additionally accepts 0x prefixed hex strings and, because
+/// some historic auctions stored prices that way, JSON numbers and decimal
+/// strings in scientific notation like `"1.5e18"`. Values with a fractional
+/// part are rejected.
 pub struct HexOrDecimalU256;
 
+impl JsonSchema for HexOrDecimalU256 {
+    fn schema_name() -> String {
+        "HexOrDecimalU256".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(vec![InstanceType::String, InstanceType::Number].into()),
+            number: Some(Box::new(NumberValidation {
+                minimum: Some(0.),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
 impl<'de> DeserializeAs<'de, U256> for HexOrDecimalU256 {
     fn deserialize_as<D>(deserializer: D) -> Result<U256, D::Error>
     where
@@ -43,10 +72,42 @@ where
         fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
             write!(
                 formatter,
-                "a u256 encoded either as 0x hex prefixed or decimal encoded string"
+                "a u256 encoded either as 0x hex prefixed or decimal encoded string or as a number"
             )
         }
 
+        fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(value.into())
+        }
+
+        fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            u64::try_from(value)
+                .map(Into::into)
+                .map_err(|_| de::Error::custom(format!("{value} is negative")))
+        }
+
+        fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            if !value.is_finite() || value < 0. || value.fract() != 0. {
+                return Err(de::Error::custom(format!(
+                    "{value} is not a non-negative integer"
+                )));
+            }
+            // `Display` prints the shortest decimal that round trips, which is
+            // the value the legacy writer intended.
+            U256::from_dec_str(&value.to_string()).map_err(|err| {
+                de::Error::custom(format!("failed to decode {value} as u256: {err}"))
+            })
+        }
+
         fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
         where
             E: de::Error,
@@ -55,6 +116,10 @@ where
                 U256::from_str_radix(s, 16).map_err(|err| {
                     de::Error::custom(format!("failed to decode {s:?} as hex u256: {err}"))
                 })
+            } else if s.contains(['e', 'E']) {
+                parse_scientific(s).ok_or_else(|| {
+                    de::Error::custom(format!("failed to decode {s:?} as scientific u256"))
+                })
             } else {
                 U256::from_dec_str(s).map_err(|err| {
                     de::Error::custom(format!("failed to decode {s:?} as decimal u256: {err}"))
@@ -63,7 +128,36 @@ where
         }
     }
 
-    deserializer.deserialize_str(Visitor {})
+    deserializer.deserialize_any(Visitor {})
+}
+
+/// Parses an integer in scientific notation like `1.5e18` or `2E+3` exactly.
+/// Returns `None` if the value is malformed, not an integer or overflows.
+fn parse_scientific(s: &str) -> Option<U256> {
+    let (mantissa, exponent) = s.trim().split_once(['e', 'E'])?;
+    let exponent: i64 = exponent.parse().ok()?;
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{integer}{fraction}");
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let shift = exponent.checked_sub(i64::try_from(fraction.len()).ok()?)?;
+    if shift >= 0 {
+        let value = U256::from_dec_str(&digits).ok()?;
+        let factor = U256::from(10).checked_pow(u32::try_from(shift).ok()?.into())?;
+        value.checked_mul(factor)
+    } else {
+        // drop the fractional digits, which must all be zero
+        let keep = digits.len().checked_sub(usize::try_from(-shift).ok()?)?;
+        let (integer, fraction) = digits.split_at(keep);
+        if fraction.bytes().any(|b| b != b'0') {
+            return None;
+        }
+        match integer {
+            "" => Some(U256::zero()),
+            integer => U256::from_dec_str(integer).ok(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -84,4 +178,29 @@ mod tests {
         let deserializer: StrDeserializer<ValueError> = "10".into_deserializer();
         assert_eq!(deserialize(deserializer), Ok(10.into()));
     }
+
+    #[test]
+    fn test_deserialization_of_legacy_encodings() {
+        let parse = |json: &str| deserialize(&mut serde_json::Deserializer::from_str(json));
+
+        assert_eq!(parse("10").unwrap(), 10.into());
+        assert_eq!(parse("1.5e18").unwrap(), U256::exp10(17) * 15);
+        assert_eq!(parse("1e30").unwrap(), U256::exp10(30));
+        assert_eq!(parse("\"1.5e18\"").unwrap(), U256::exp10(17) * 15);
+        assert_eq!(parse("\"2E+3\"").unwrap(), 2000.into());
+        assert_eq!(parse("\"1000e-3\"").unwrap(), 1.into());
+        assert_eq!(parse("\"0.0e0\"").unwrap(), 0.into());
+        assert_eq!(
+            parse("\"1.15792089237316195423570985008687907853269984665640564039457584007913129639935e77\"")
+                .unwrap(),
+            U256::MAX
+        );
+
+        assert!(parse("-1").is_err());
+        assert!(parse("1.5").is_err());
+        assert!(parse("\"1.5e0\"").is_err());
+        assert!(parse("\"1e78\"").is_err());
+        assert!(parse("\"e5\"").is_err());
+        assert!(parse("\"1e\"").is_err());
+    }
 }
@@ -0,0 +1,40 @@
+//! Destinations the rows a migration writes to Postgres are copied to as
+//! well, like the Parquet files of `parquet_export`. Sinks are behind
+//! features, so binaries that only migrate the database don't build them.
+
+use {
+    crate::{database_order_executions::OrderExecution, database_solver_competition::Auction},
+    anyhow::Result,
+    std::{
+        fmt,
+        sync::{Arc, Mutex},
+    },
+};
+
+/// Receives the rows of each batch once they are written.
+pub trait Sink: fmt::Debug + Send {
+    fn write_auctions(&mut self, auctions: &[Auction]) -> Result<()>;
+
+    fn write_order_execution(&mut self, order_execution: &OrderExecution) -> Result<()>;
+}
+
+/// A sink shared by the clones of a [`crate::database::Config`].
+pub type SharedSink = Arc<Mutex<dyn Sink>>;
+
+/// Passes `auctions` to every sink.
+pub fn write_auctions(sinks: &[SharedSink], auctions: &[Auction]) -> Result<()> {
+    for sink in sinks {
+        sink.lock().unwrap().write_auctions(auctions)?;
+    }
+    Ok(())
+}
+
+/// Passes `order_execution` to every sink.
+pub fn write_order_execution(sinks: &[SharedSink], order_execution: &OrderExecution) -> Result<()> {
+    for sink in sinks {
+        sink.lock()
+            .unwrap()
+            .write_order_execution(order_execution)?;
+    }
+    Ok(())
+}
@@ -1,19 +1,40 @@
 use std::fmt::{self, Display};
 
 use {
-    crate::serialization::HexOrDecimalU256,
+    crate::{
+        database_orders::OrderKind,
+        prices::{ConversionError, Prices},
+        serialization::HexOrDecimalU256,
+    },
     derivative::Derivative,
     primitive_types::{H160, H256, U256},
+    schemars::{
+        gen::SchemaGenerator,
+        schema::{InstanceType, Schema, SchemaObject, StringValidation},
+        JsonSchema,
+    },
     serde::{de, Deserializer, Serializer},
     serde::{Deserialize, Serialize},
-    serde_with::serde_as,
-    std::collections::BTreeMap,
+    serde_with::{serde_as, VecSkipError},
+    std::collections::{BTreeMap, HashSet},
 };
 
 // uid as 56 bytes: 32 for orderDigest, 20 for ownerAddress and 4 for validTo
 #[derive(Clone, Copy, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub struct OrderUid(pub [u8; 56]);
 
+impl OrderUid {
+    /// The owner embedded in the uid, after the 32 byte order digest.
+    pub fn owner(&self) -> H160 {
+        H160::from_slice(&self.0[32..52])
+    }
+
+    /// The validTo timestamp embedded in the uid, its last 4 bytes.
+    pub fn valid_to(&self) -> u32 {
+        u32::from_be_bytes(self.0[52..56].try_into().unwrap())
+    }
+}
+
 impl Display for OrderUid {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut bytes = [0u8; 2 + 56 * 2];
@@ -32,6 +53,24 @@ impl fmt::Debug for OrderUid {
     }
 }
 
+impl JsonSchema for OrderUid {
+    fn schema_name() -> String {
+        "OrderUid".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            string: Some(Box::new(StringValidation {
+                pattern: Some("^(0[xX])?[0-9a-fA-F]{112}$".to_string()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
 impl Serialize for OrderUid {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -58,11 +97,12 @@ impl<'de> Deserialize<'de> for OrderUid {
             where
                 E: de::Error,
             {
-                let s = s.strip_prefix("0x").ok_or_else(|| {
-                    de::Error::custom(format!(
-                        "{s:?} can't be decoded as hex uid because it does not start with '0x'"
-                    ))
-                })?;
+                // Some historic solutions stored uids without the 0x prefix.
+                let s = s.trim();
+                let s = s
+                    .strip_prefix("0x")
+                    .or_else(|| s.strip_prefix("0X"))
+                    .unwrap_or(s);
                 let mut value = [0u8; 56];
                 hex::decode_to_slice(s, value.as_mut()).map_err(|err| {
                     de::Error::custom(format!("failed to decode {s:?} as hex uid: {err}"))
@@ -77,7 +117,7 @@ impl<'de> Deserialize<'de> for OrderUid {
 
 /// Stored directly in the database and turned into SolverCompetitionAPI for the
 /// `/solver_competition` endpoint.
-#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SolverCompetitionDB {
     pub auction_start_block: u64,
@@ -87,61 +127,162 @@ pub struct SolverCompetitionDB {
 }
 
 /// Returned by the `/solver_competition` endpoint.
-#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SolverCompetitionAPI {
     #[serde(default)]
     pub auction_id: i64,
+    #[schemars(with = "Vec<String>")]
     pub transaction_hashes: Vec<H256>,
     #[serde(flatten)]
     pub common: SolverCompetitionDB,
 }
 
 #[serde_as]
-#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CompetitionAuction {
+    /// Malformed uids are skipped, see [`skipped_orders`].
+    #[serde_as(as = "VecSkipError<_>")]
+    #[schemars(with = "Vec<OrderUid>")]
     pub orders: Vec<OrderUid>,
     #[serde_as(as = "BTreeMap<_, HexOrDecimalU256>")]
+    #[schemars(with = "BTreeMap<String, HexOrDecimalU256>")]
     pub prices: BTreeMap<H160, U256>,
 }
 
+impl CompetitionAuction {
+    /// Returns the order uids without duplicates, in order of first
+    /// occurrence, and the number of duplicate entries that were dropped.
+    /// Some legacy auctions list the same order more than once.
+    pub fn unique_orders(&self) -> (Vec<OrderUid>, usize) {
+        let mut seen = HashSet::new();
+        let unique: Vec<_> = self
+            .orders
+            .iter()
+            .filter(|uid| seen.insert(**uid))
+            .copied()
+            .collect();
+        let duplicates = self.orders.len() - unique.len();
+        (unique, duplicates)
+    }
+
+    /// The median validTo of the orders, ignoring EthFlow orders whose validTo
+    /// is the maximum. Orders had to be valid when the auction ran, so this is
+    /// a rough estimate of its time.
+    pub fn median_valid_to(&self) -> Option<u32> {
+        let mut valid_to: Vec<u32> = self
+            .orders
+            .iter()
+            .map(OrderUid::valid_to)
+            .filter(|valid_to| *valid_to != u32::MAX)
+            .collect();
+        valid_to.sort_unstable();
+        valid_to.get(valid_to.len() / 2).copied()
+    }
+}
+
 #[serde_as]
-#[derive(Clone, Default, Deserialize, Serialize, PartialEq, Derivative)]
+#[derive(Clone, Default, Deserialize, Serialize, PartialEq, Derivative, JsonSchema)]
 #[derivative(Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct SolverSettlement {
     pub solver: String,
     #[serde(default)]
+    #[schemars(with = "String")]
     pub solver_address: H160,
     #[serde(flatten)]
+    #[schemars(with = "ScoreSchema")]
     pub score: Option<Score>,
     #[serde(default)]
     pub ranking: usize,
     #[serde_as(as = "BTreeMap<_, HexOrDecimalU256>")]
+    #[schemars(with = "BTreeMap<String, HexOrDecimalU256>")]
     pub clearing_prices: BTreeMap<H160, U256>,
+    /// Malformed orders are skipped, see [`skipped_orders`].
+    #[serde_as(as = "VecSkipError<_>")]
+    #[schemars(with = "Vec<Order>")]
     pub orders: Vec<Order>,
 }
 
+impl SolverSettlement {
+    /// Executed sell and buy amounts of `order` in this solution.
+    ///
+    /// Legacy orders of a solution among the `winners` of its auction, which
+    /// got settled, take the amounts of their trade out of `trades`, see
+    /// [`Order::traded_amounts`]. The amounts of all other orders are derived
+    /// from the clearing prices of this solution, see
+    /// [`Order::executed_amounts`].
+    pub fn executed_amounts(
+        &self,
+        order: &Order,
+        winners: &[&SolverSettlement],
+        kind: OrderKind,
+        sell_token: H160,
+        buy_token: H160,
+        trades: &[(U256, U256)],
+    ) -> Result<(U256, U256), ConversionError> {
+        let settled = winners.iter().any(|winner| std::ptr::eq(*winner, self));
+        match order.traded_amounts(kind, trades).filter(|_| settled) {
+            Some(amounts) => Ok(amounts),
+            None => order.executed_amounts(
+                kind,
+                sell_token,
+                buy_token,
+                &Prices::from(self.clearing_prices.clone()),
+            ),
+        }
+    }
+}
+
 #[serde_as]
-#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum Score {
     /// The score is provided by the solver.
     #[serde(rename = "score")]
-    Solver(#[serde_as(as = "HexOrDecimalU256")] U256),
+    Solver(
+        #[serde_as(as = "HexOrDecimalU256")]
+        #[schemars(with = "HexOrDecimalU256")]
+        U256,
+    ),
     /// The score is calculated by the protocol (and equal to the objective
     /// function).
     #[serde(rename = "scoreProtocol")]
-    Protocol(#[serde_as(as = "HexOrDecimalU256")] U256),
+    Protocol(
+        #[serde_as(as = "HexOrDecimalU256")]
+        #[schemars(with = "HexOrDecimalU256")]
+        U256,
+    ),
     /// The score is calculated by the protocol and success_probability provided
     /// by solver is taken into account
     #[serde(rename = "scoreProtocolWithSolverRisk")]
-    ProtocolWithSolverRisk(#[serde_as(as = "HexOrDecimalU256")] U256),
+    ProtocolWithSolverRisk(
+        #[serde_as(as = "HexOrDecimalU256")]
+        #[schemars(with = "HexOrDecimalU256")]
+        U256,
+    ),
     /// The score is calculated by the protocol, by applying a discount to the
     /// `Self::Protocol` value.
     /// [DEPRECATED] Kept to not brake the solver competition API.
     #[serde(rename = "scoreDiscounted")]
-    Discounted(#[serde_as(as = "HexOrDecimalU256")] U256),
+    Discounted(
+        #[serde_as(as = "HexOrDecimalU256")]
+        #[schemars(with = "HexOrDecimalU256")]
+        U256,
+    ),
+}
+
+/// Schema of the flattened `Score`. The derived enum schema forbids sibling
+/// fields in each variant, which doesn't work when flattened into
+/// `SolverSettlement`.
+#[derive(JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+struct ScoreSchema {
+    score: Option<HexOrDecimalU256>,
+    score_protocol: Option<HexOrDecimalU256>,
+    score_protocol_with_solver_risk: Option<HexOrDecimalU256>,
+    score_discounted: Option<HexOrDecimalU256>,
 }
 
 impl Default for Score {
@@ -162,7 +303,7 @@ impl Score {
 }
 
 #[serde_as]
-#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema)]
 #[serde(untagged)]
 pub enum Order {
     #[serde(rename_all = "camelCase")]
@@ -170,15 +311,611 @@ pub enum Order {
         id: OrderUid,
         /// The effective amount that left the user's wallet including all fees.
         #[serde_as(as = "HexOrDecimalU256")]
+        #[schemars(with = "HexOrDecimalU256")]
         sell_amount: U256,
         /// The effective amount the user received after all fees.
         #[serde_as(as = "HexOrDecimalU256")]
+        #[schemars(with = "HexOrDecimalU256")]
         buy_amount: U256,
     },
     #[serde(rename_all = "camelCase")]
     Legacy {
         id: OrderUid,
         #[serde_as(as = "HexOrDecimalU256")]
+        #[schemars(with = "HexOrDecimalU256")]
         executed_amount: U256,
     },
 }
+
+impl Order {
+    pub fn id(&self) -> OrderUid {
+        match self {
+            Self::Colocated { id, .. } | Self::Legacy { id, .. } => *id,
+        }
+    }
+
+    /// Executed sell and buy amounts of the order in a solution with
+    /// `clearing_prices`.
+    ///
+    /// Legacy orders only store the executed amount of the side fixed by the
+    /// order kind. The other side is derived with the uniform clearing prices,
+    /// rounded in favour of the user like the settlement contract does.
+    pub fn executed_amounts(
+        &self,
+        kind: OrderKind,
+        sell_token: H160,
+        buy_token: H160,
+        clearing_prices: &Prices,
+    ) -> Result<(U256, U256), ConversionError> {
+        match *self {
+            Self::Colocated {
+                sell_amount,
+                buy_amount,
+                ..
+            } => Ok((sell_amount, buy_amount)),
+            Self::Legacy {
+                executed_amount, ..
+            } => match kind {
+                OrderKind::Sell => Ok((
+                    executed_amount,
+                    clearing_prices.convert(executed_amount, sell_token, buy_token)?,
+                )),
+                OrderKind::Buy => Ok((
+                    clearing_prices.convert_up(executed_amount, buy_token, sell_token)?,
+                    executed_amount,
+                )),
+            },
+        }
+    }
+
+    /// Executed sell and buy amounts of the order from the trade that settled
+    /// it in this solution, out of the `trades` of the order in the
+    /// settlements of its auction, as sell amounts without fee and buy
+    /// amounts.
+    ///
+    /// Legacy orders store the executed amount of one side only, so the trade
+    /// executing them is the one whose amount of that side matches it. Only
+    /// meaningful for solutions that got settled: fill-or-kill orders execute
+    /// the same amount in every solution, so the trade of the winner matches
+    /// the order in the solutions that lost too.
+    pub fn traded_amounts(&self, kind: OrderKind, trades: &[(U256, U256)]) -> Option<(U256, U256)> {
+        let Self::Legacy {
+            executed_amount, ..
+        } = *self
+        else {
+            return None;
+        };
+        trades
+            .iter()
+            .find(|(sell_amount, buy_amount)| match kind {
+                OrderKind::Sell => *sell_amount == executed_amount,
+                OrderKind::Buy => *buy_amount == executed_amount,
+            })
+            .copied()
+    }
+}
+
+/// Returns how many orders of the auction and its solutions were skipped
+/// during deserialization of `json` into `competition` because they were
+/// malformed.
+pub fn skipped_orders(json: &serde_json::Value, competition: &SolverCompetitionDB) -> usize {
+    let count = |value: &serde_json::Value| value.as_array().map_or(0, Vec::len);
+    let auction =
+        count(&json["auction"]["orders"]).saturating_sub(competition.auction.orders.len());
+    let solutions = competition
+        .solutions
+        .iter()
+        .enumerate()
+        .map(|(i, solution)| {
+            count(&json["solutions"][i]["orders"]).saturating_sub(solution.orders.len())
+        })
+        .sum::<usize>();
+    auction + solutions
+}
+
+/// Decodes `json` with its solutions left out, for competitions that only
+/// fail to decode because of them. `None` if the rest doesn't decode either.
+pub fn decode_without_solutions(json: &serde_json::Value) -> Option<SolverCompetitionDB> {
+    let mut json = json.clone();
+    json.as_object_mut()?
+        .insert("solutions".to_string(), serde_json::json!([]));
+    serde_json::from_value(json).ok()
+}
+
+/// Returns the solutions of a competition that won and got settled, best
+/// first.
+///
+/// Every winning solution is settled in its own transaction, so the number of
+/// winners is taken from `transaction_hashes` (at least one if there are any
+/// solutions). Competitions that store a `ranking` are ordered by it (rank 1 is
+/// the best, 0 means unranked). Legacy competitions without rankings are
+/// ordered by descending score if every solution has one. Otherwise they are
+/// taken to be sorted by ascending score, as the services stored them, so the
+/// winners are at the end.
+pub fn select_winning_solutions<'a>(
+    competition: &'a SolverCompetitionDB,
+    transaction_hashes: &[H256],
+) -> Vec<&'a SolverSettlement> {
+    let winners = transaction_hashes.len().max(1);
+    let mut solutions: Vec<_> = competition.solutions.iter().collect();
+    if solutions.iter().any(|solution| solution.ranking > 0) {
+        solutions.sort_by_key(|solution| match solution.ranking {
+            0 => usize::MAX,
+            ranking => ranking,
+        });
+    } else {
+        solutions.reverse();
+        if solutions.iter().all(|solution| solution.score.is_some()) {
+            // stable, so of equal scores the later one still wins
+            solutions.sort_by_key(|solution| {
+                std::cmp::Reverse(solution.score.as_ref().map(Score::score))
+            });
+        }
+    }
+    solutions.truncate(winners);
+    solutions
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, serde_json::json};
+
+    fn competition(solutions: serde_json::Value) -> SolverCompetitionDB {
+        serde_json::from_value(json!({
+            "auctionStartBlock": 17000000,
+            "competitionSimulationBlock": 17000001,
+            "auction": {
+                "orders": [],
+                "prices": {},
+            },
+            "solutions": solutions,
+        }))
+        .unwrap()
+    }
+
+    fn solvers(solutions: &[&SolverSettlement]) -> Vec<String> {
+        solutions
+            .iter()
+            .map(|solution| solution.solver.clone())
+            .collect()
+    }
+
+    #[test]
+    fn no_solutions() {
+        let competition = competition(json!([]));
+        assert!(select_winning_solutions(&competition, &[]).is_empty());
+        assert!(select_winning_solutions(&competition, &[H256::zero()]).is_empty());
+    }
+
+    #[test]
+    fn legacy_winner_is_last() {
+        let competition = competition(json!([
+            {
+                "solver": "paraswap",
+                "objective": { "total": 1.0 },
+                "clearingPrices": {},
+                "orders": [],
+            },
+            {
+                "solver": "quasimodo",
+                "scoreProtocol": "200",
+                "clearingPrices": {},
+                "orders": [],
+            },
+        ]));
+        assert_eq!(
+            solvers(&select_winning_solutions(&competition, &[])),
+            ["quasimodo"]
+        );
+    }
+
+    #[test]
+    fn legacy_winner_by_score() {
+        let competition = competition(json!([
+            {
+                "solver": "quasimodo",
+                "scoreProtocol": "300",
+                "clearingPrices": {},
+                "orders": [],
+            },
+            {
+                "solver": "paraswap",
+                "scoreProtocol": "100",
+                "clearingPrices": {},
+                "orders": [],
+            },
+            {
+                "solver": "baseline",
+                "scoreProtocol": "300",
+                "clearingPrices": {},
+                "orders": [],
+            },
+        ]));
+        assert_eq!(
+            solvers(&select_winning_solutions(
+                &competition,
+                &[H256::zero(), H256::repeat_byte(1)]
+            )),
+            ["baseline", "quasimodo"]
+        );
+    }
+
+    #[test]
+    fn ranked_winner() {
+        let competition = competition(json!([
+            {
+                "solver": "gnosis",
+                "score": "300",
+                "ranking": 1,
+                "clearingPrices": {},
+                "orders": [],
+            },
+            {
+                "solver": "baseline",
+                "score": "100",
+                "ranking": 3,
+                "clearingPrices": {},
+                "orders": [],
+            },
+            {
+                "solver": "seasolver",
+                "score": "200",
+                "ranking": 2,
+                "clearingPrices": {},
+                "orders": [],
+            },
+        ]));
+        assert_eq!(
+            solvers(&select_winning_solutions(&competition, &[])),
+            ["gnosis"]
+        );
+        assert_eq!(
+            solvers(&select_winning_solutions(
+                &competition,
+                &[H256::zero(), H256::repeat_byte(1)]
+            )),
+            ["gnosis", "seasolver"]
+        );
+    }
+
+    #[test]
+    fn unranked_solutions_lose() {
+        let competition = competition(json!([
+            {
+                "solver": "unranked",
+                "score": "300",
+                "clearingPrices": {},
+                "orders": [],
+            },
+            {
+                "solver": "ranked",
+                "score": "100",
+                "ranking": 1,
+                "clearingPrices": {},
+                "orders": [],
+            },
+        ]));
+        assert_eq!(
+            solvers(&select_winning_solutions(&competition, &[])),
+            ["ranked"]
+        );
+    }
+
+    #[test]
+    fn fixture_winners() {
+        let fixture = |json: &str| serde_json::from_str::<SolverCompetitionDB>(json).unwrap();
+        let hashes = [H256([1; 32]), H256([2; 32])];
+
+        // stored in ascending order of objective, without scores or rankings
+        let legacy = fixture(include_str!("../testdata/competition_legacy.json"));
+        assert_eq!(
+            solvers(&select_winning_solutions(&legacy, &[])),
+            ["Quasimodo"]
+        );
+        assert_eq!(
+            solvers(&select_winning_solutions(&legacy, &hashes[..1])),
+            ["Quasimodo"]
+        );
+
+        // settled in two transactions, so the two best ranked solutions won
+        let ranked = fixture(include_str!("../testdata/competition_ranked.json"));
+        assert_eq!(
+            solvers(&select_winning_solutions(&ranked, &hashes)),
+            ["gnosis", "quasimodo"]
+        );
+        assert_eq!(solvers(&select_winning_solutions(&ranked, &[])), ["gnosis"]);
+
+        let golden = serde_json::from_str::<SolverCompetitionAPI>(include_str!(
+            "../testdata/solver_competition_api.json"
+        ))
+        .unwrap();
+        assert_eq!(
+            solvers(&select_winning_solutions(
+                &golden.common,
+                &golden.transaction_hashes
+            )),
+            ["colocated"]
+        );
+    }
+
+    #[test]
+    fn lenient_order_uids() {
+        let uid = "ab".repeat(56);
+        let json = json!({
+            "auctionStartBlock": 1,
+            "competitionSimulationBlock": 1,
+            "auction": {
+                "orders": [format!("0x{uid}"), uid.clone(), "AB".repeat(56), "0x12", 1],
+                "prices": {},
+            },
+            "solutions": [{
+                "solver": "solver",
+                "clearingPrices": {},
+                "orders": [
+                    { "id": uid, "executedAmount": "1" },
+                    { "id": "0xinvalid", "executedAmount": "1" },
+                ],
+            }],
+        });
+        let competition: SolverCompetitionDB = serde_json::from_value(json.clone()).unwrap();
+
+        assert_eq!(competition.auction.orders, vec![OrderUid([0xab; 56]); 3]);
+        assert_eq!(competition.solutions[0].orders.len(), 1);
+        assert_eq!(skipped_orders(&json, &competition), 3);
+    }
+
+    #[test]
+    fn unique_orders() {
+        let auction = CompetitionAuction {
+            orders: vec![
+                OrderUid([2; 56]),
+                OrderUid([1; 56]),
+                OrderUid([2; 56]),
+                OrderUid([2; 56]),
+            ],
+            prices: Default::default(),
+        };
+        assert_eq!(
+            auction.unique_orders(),
+            (vec![OrderUid([2; 56]), OrderUid([1; 56])], 2)
+        );
+    }
+
+    #[test]
+    fn uid_parts() {
+        let mut uid = [1; 56];
+        uid[32..52].fill(2);
+        uid[52..].copy_from_slice(&1_700_000_000u32.to_be_bytes());
+        let uid = OrderUid(uid);
+        assert_eq!(uid.owner(), H160([2; 20]));
+        assert_eq!(uid.valid_to(), 1_700_000_000);
+
+        let with_valid_to = |valid_to: u32| {
+            let mut uid = [1; 56];
+            uid[52..].copy_from_slice(&valid_to.to_be_bytes());
+            OrderUid(uid)
+        };
+        let auction = CompetitionAuction {
+            orders: [30, 10, u32::MAX, 20, u32::MAX]
+                .into_iter()
+                .map(with_valid_to)
+                .collect(),
+            prices: Default::default(),
+        };
+        assert_eq!(auction.median_valid_to(), Some(20));
+        assert_eq!(CompetitionAuction::default().median_valid_to(), None);
+    }
+
+    fn golden_competition() -> SolverCompetitionAPI {
+        let uid = |byte: u8| OrderUid([byte; 56]);
+        let token = |byte: u8| H160([byte; 20]);
+        SolverCompetitionAPI {
+            auction_id: 42,
+            transaction_hashes: vec![H256([0x42; 32])],
+            common: SolverCompetitionDB {
+                auction_start_block: 17_000_000,
+                competition_simulation_block: 17_000_001,
+                auction: CompetitionAuction {
+                    orders: vec![uid(1), uid(2)],
+                    prices: BTreeMap::from([
+                        (token(1), U256::exp10(18)),
+                        (token(2), U256::from(1_500_000u64)),
+                    ]),
+                },
+                solutions: vec![
+                    SolverSettlement {
+                        solver: "legacy".to_string(),
+                        solver_address: token(0xaa),
+                        score: None,
+                        ranking: 0,
+                        clearing_prices: BTreeMap::from([(token(1), U256::from(2))]),
+                        orders: vec![Order::Legacy {
+                            id: uid(1),
+                            executed_amount: U256::from(100),
+                        }],
+                    },
+                    SolverSettlement {
+                        solver: "colocated".to_string(),
+                        solver_address: token(0xbb),
+                        score: Some(Score::Solver(U256::from(300))),
+                        ranking: 1,
+                        clearing_prices: BTreeMap::from([
+                            (token(1), U256::from(3)),
+                            (token(2), U256::MAX),
+                        ]),
+                        orders: vec![Order::Colocated {
+                            id: uid(2),
+                            sell_amount: U256::from(10),
+                            buy_amount: U256::from(20),
+                        }],
+                    },
+                ],
+            },
+        }
+    }
+
+    /// Serializes `value` the way the golden files are formatted and checks
+    /// that the golden file deserializes back into `value`.
+    fn assert_golden<T>(value: &T, golden: &str)
+    where
+        T: Serialize + serde::de::DeserializeOwned + PartialEq + fmt::Debug,
+    {
+        let serialized = serde_json::to_string_pretty(value).unwrap() + "\n";
+        assert_eq!(serialized, golden);
+        assert_eq!(&serde_json::from_str::<T>(golden).unwrap(), value);
+    }
+
+    #[test]
+    fn golden_solver_competition_api() {
+        assert_golden(
+            &golden_competition(),
+            include_str!("../testdata/solver_competition_api.json"),
+        );
+    }
+
+    #[test]
+    fn golden_scores() {
+        let scores = vec![
+            Score::Solver(1.into()),
+            Score::Protocol(2.into()),
+            Score::ProtocolWithSolverRisk(3.into()),
+            Score::Discounted(4.into()),
+        ];
+        assert_golden(&scores, include_str!("../testdata/scores.json"));
+    }
+
+    #[test]
+    fn golden_orders() {
+        let orders = vec![
+            Order::Colocated {
+                id: OrderUid([1; 56]),
+                sell_amount: U256::MAX,
+                buy_amount: 0.into(),
+            },
+            Order::Legacy {
+                id: OrderUid([2; 56]),
+                executed_amount: 1.into(),
+            },
+        ];
+        assert_golden(&orders, include_str!("../testdata/orders.json"));
+    }
+
+    #[test]
+    fn executed_amounts() {
+        let (sell_token, buy_token) = (H160([1; 20]), H160([2; 20]));
+        let prices = Prices::from(BTreeMap::from([
+            (sell_token, U256::from(3)),
+            (buy_token, U256::from(2)),
+        ]));
+        let legacy = Order::Legacy {
+            id: OrderUid([0; 56]),
+            executed_amount: 5.into(),
+        };
+
+        // sell orders receive the buy amount rounded down
+        assert_eq!(
+            legacy.executed_amounts(OrderKind::Sell, sell_token, buy_token, &prices),
+            Ok((5.into(), 7.into()))
+        );
+        // buy orders pay the sell amount rounded up
+        assert_eq!(
+            legacy.executed_amounts(OrderKind::Buy, sell_token, buy_token, &prices),
+            Ok((4.into(), 5.into()))
+        );
+        let colocated = Order::Colocated {
+            id: OrderUid([0; 56]),
+            sell_amount: 1.into(),
+            buy_amount: 2.into(),
+        };
+        assert_eq!(
+            colocated.executed_amounts(OrderKind::Sell, sell_token, buy_token, &Prices::default()),
+            Ok((1.into(), 2.into()))
+        );
+    }
+
+    #[test]
+    fn traded_amounts() {
+        let legacy = Order::Legacy {
+            id: OrderUid([0; 56]),
+            executed_amount: 5.into(),
+        };
+        let trades = [(4.into(), 5.into()), (5.into(), 7.into())];
+
+        // the trade is matched by the side fixed by the order kind
+        assert_eq!(
+            legacy.traded_amounts(OrderKind::Sell, &trades),
+            Some((5.into(), 7.into()))
+        );
+        assert_eq!(
+            legacy.traded_amounts(OrderKind::Buy, &trades),
+            Some((4.into(), 5.into()))
+        );
+        // trades of other amounts don't match
+        assert_eq!(
+            legacy.traded_amounts(OrderKind::Sell, &[(6.into(), 7.into())]),
+            None
+        );
+        assert_eq!(legacy.traded_amounts(OrderKind::Sell, &[]), None);
+        let colocated = Order::Colocated {
+            id: OrderUid([0; 56]),
+            sell_amount: 5.into(),
+            buy_amount: 7.into(),
+        };
+        assert_eq!(colocated.traded_amounts(OrderKind::Sell, &trades), None);
+    }
+
+    #[test]
+    fn settled_executed_amounts() {
+        let (sell_token, buy_token) = (H160([1; 20]), H160([2; 20]));
+        let solution = |solver: &str, sell_price: &str| {
+            json!({
+                "solver": solver,
+                "clearingPrices": {
+                    "0x0101010101010101010101010101010101010101": sell_price,
+                    "0x0202020202020202020202020202020202020202": "1",
+                },
+                "orders": [{ "id": format!("0x{}", "11".repeat(56)), "executedAmount": "5" }],
+            })
+        };
+        // a fill-or-kill order executing the same amount in both solutions
+        let competition = competition(json!([solution("loser", "3"), solution("winner", "2")]));
+        let winners = select_winning_solutions(&competition, &[H256::zero()]);
+        let trades = [(5.into(), 12.into())];
+        let amounts = |solution: &SolverSettlement| {
+            solution
+                .executed_amounts(
+                    &solution.orders[0],
+                    &winners,
+                    OrderKind::Sell,
+                    sell_token,
+                    buy_token,
+                    &trades,
+                )
+                .unwrap()
+        };
+
+        // the settled solution takes the amounts of the trade
+        assert_eq!(amounts(&competition.solutions[1]), (5.into(), 12.into()));
+        // the losing one matches the trade too, but keeps its own prices
+        assert_eq!(amounts(&competition.solutions[0]), (5.into(), 15.into()));
+    }
+
+    #[test]
+    fn decodes_without_solutions() {
+        let json = json!({
+            "auctionStartBlock": 17000000,
+            "competitionSimulationBlock": 17000001,
+            "auction": {
+                "orders": [],
+                "prices": {},
+            },
+            "solutions": "corrupt",
+        });
+        assert!(serde_json::from_value::<SolverCompetitionDB>(json.clone()).is_err());
+        let competition = decode_without_solutions(&json).unwrap();
+        assert_eq!(competition.auction_start_block, 17000000);
+        assert!(competition.solutions.is_empty());
+        assert!(decode_without_solutions(&json!({ "solutions": [] })).is_none());
+        assert!(decode_without_solutions(&json!("corrupt")).is_none());
+    }
+}
@@ -0,0 +1,179 @@
+//! Sources of the solver competition JSON of an auction other than
+//! `solver_competitions`, tried in order when the stored JSON is missing or
+//! doesn't decode.
+
+use {
+    crate::{arguments::Arguments, solver_competition_api::SolverCompetitionDB},
+    anyhow::{Context, Result},
+    futures::{future::BoxFuture, FutureExt},
+    serde_json::Value as JsonValue,
+    std::{fmt, io, path::PathBuf, sync::Arc},
+    tracing::warn,
+};
+
+/// Kinds of fallback sources, as given on the command line.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum FallbackSource {
+    /// A `<auction id>.json` file in `--archive-dir`.
+    Archive,
+    /// The `/solver_competition` endpoint of the API at `--api-url`, with the
+    /// `api` feature.
+    Api,
+}
+
+/// A place the JSON of auctions can be fetched from. Integrations other than
+/// the archive directory are behind features, see the manifest.
+pub trait Source: fmt::Debug + Send + Sync {
+    /// Name of the source in logs and reports.
+    fn name(&self) -> &'static str;
+
+    /// The JSON of `auction_id`, `None` if the source doesn't have it.
+    fn fetch(&self, auction_id: i64) -> BoxFuture<'_, Result<Option<JsonValue>>>;
+}
+
+/// `<auction id>.json` files in a directory.
+#[derive(Debug)]
+struct Archive(PathBuf);
+
+impl Source for Archive {
+    fn name(&self) -> &'static str {
+        "archive"
+    }
+
+    fn fetch(&self, auction_id: i64) -> BoxFuture<'_, Result<Option<JsonValue>>> {
+        async move {
+            let bytes = match tokio::fs::read(self.0.join(format!("{auction_id}.json"))).await {
+                Ok(bytes) => bytes,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+                Err(err) => return Err(err).context("read archive file"),
+            };
+            Ok(Some(serde_json::from_slice(&bytes).context("parse JSON")?))
+        }
+        .boxed()
+    }
+}
+
+/// The `/solver_competition` endpoint of the API.
+#[cfg(feature = "api")]
+#[derive(Debug)]
+struct Api {
+    client: reqwest::Client,
+    url: url::Url,
+}
+
+#[cfg(feature = "api")]
+impl Source for Api {
+    fn name(&self) -> &'static str {
+        "api"
+    }
+
+    fn fetch(&self, auction_id: i64) -> BoxFuture<'_, Result<Option<JsonValue>>> {
+        async move {
+            let url = format!(
+                "{}/api/v1/solver_competition/{auction_id}",
+                self.url.as_str().trim_end_matches('/')
+            );
+            let response = self.client.get(url).send().await.context("request")?;
+            if response.status() == reqwest::StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
+            let bytes = response
+                .error_for_status()?
+                .bytes()
+                .await
+                .context("read response")?;
+            Ok(Some(serde_json::from_slice(&bytes).context("parse JSON")?))
+        }
+        .boxed()
+    }
+}
+
+/// The configured fallback sources, none by default.
+#[derive(Clone, Debug, Default)]
+pub struct Fallbacks {
+    sources: Vec<Arc<dyn Source>>,
+}
+
+impl Fallbacks {
+    pub fn from_arguments(args: &Arguments) -> Result<Self> {
+        let sources = args
+            .json_fallbacks
+            .iter()
+            .map(|source| -> Result<Arc<dyn Source>> {
+                Ok(match source {
+                    FallbackSource::Archive => Arc::new(Archive(
+                        args.archive_dir
+                            .clone()
+                            .context("the archive fallback needs --archive-dir")?,
+                    )),
+                    #[cfg(feature = "api")]
+                    FallbackSource::Api => Arc::new(Api {
+                        client: reqwest::Client::new(),
+                        url: args
+                            .api_url
+                            .clone()
+                            .context("the api fallback needs --api-url")?,
+                    }),
+                    #[cfg(not(feature = "api"))]
+                    FallbackSource::Api => {
+                        anyhow::bail!("the api fallback needs the `api` feature")
+                    }
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { sources })
+    }
+
+    /// Tries `source` after the configured ones.
+    pub fn push(&mut self, source: Arc<dyn Source>) {
+        self.sources.push(source);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// The JSON of `auction_id` from the first source that has one that
+    /// decodes, with the name of that source. Sources that fail are logged
+    /// and passed over.
+    pub async fn fetch(&self, auction_id: i64) -> Option<(JsonValue, &'static str)> {
+        for source in &self.sources {
+            match source.fetch(auction_id).await {
+                Ok(Some(json)) if decodes(&json) => return Some((json, source.name())),
+                Ok(Some(_)) => warn!(
+                    "auction {auction_id}: the JSON from {} doesn't decode either",
+                    source.name()
+                ),
+                Ok(None) => (),
+                Err(err) => warn!(
+                    "auction {auction_id}: failed to fetch from {}: {:?}",
+                    source.name(),
+                    err
+                ),
+            }
+        }
+        None
+    }
+}
+
+/// Whether `json` decodes as a solver competition. The API format decodes
+/// too, it has the stored one flattened into it.
+pub fn decodes(json: &JsonValue) -> bool {
+    serde_json::from_value::<SolverCompetitionDB>(json.clone()).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::solver_competition_api::SolverCompetitionAPI};
+
+    #[test]
+    fn api_format_decodes() {
+        let api = serde_json::to_value(SolverCompetitionAPI {
+            auction_id: 1,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(decodes(&api));
+        assert!(!decodes(&serde_json::json!({ "corrupt": true })));
+    }
+}
@@ -0,0 +1,179 @@
+//! Where the time of a migration run went, and a suggested configuration for
+//! the next run on similar hardware.
+
+use crate::numbers::big;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    time::{Duration, Instant},
+};
+use tracing::info;
+
+/// Part of processing a batch that time is spent in.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Stage {
+    /// Reading from the database.
+    Fetch,
+    /// Decoding and transforming the fetched rows.
+    Parse,
+    /// Writing to the database and committing.
+    Write,
+    /// Throttling between batches.
+    Sleep,
+}
+
+impl Stage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Fetch => "fetch",
+            Self::Parse => "parse",
+            Self::Write => "write",
+            Self::Sleep => "sleep",
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How a run processes its batches.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Config {
+    pub batch_size: i64,
+    pub parallelism: usize,
+    pub throttle: Duration,
+}
+
+/// Time spent per stage by one run.
+#[derive(Debug, Default)]
+pub struct Timings {
+    stages: BTreeMap<Stage, Duration>,
+}
+
+impl Timings {
+    /// Adds the time since `start` to `stage` of `migration`.
+    pub fn record(&mut self, migration: &str, stage: Stage, start: Instant) {
+        let elapsed = start.elapsed();
+        *self.stages.entry(stage).or_default() += elapsed;
+        crate::metrics::get()
+            .stage_seconds
+            .with_label_values(&[migration, stage.as_str()])
+            .inc_by(elapsed.as_secs_f64());
+    }
+
+    /// Adds the timings of another worker of the same run.
+    pub fn merge(&mut self, other: Self) {
+        for (stage, time) in other.stages {
+            *self.stages.entry(stage).or_default() += time;
+        }
+    }
+
+    fn share(&self, stages: &[Stage]) -> f64 {
+        let total: Duration = self.stages.values().sum();
+        if total.is_zero() {
+            return 0.;
+        }
+        let part: Duration = stages
+            .iter()
+            .filter_map(|stage| self.stages.get(stage))
+            .sum();
+        part.as_secs_f64() / total.as_secs_f64()
+    }
+
+    /// Suggests how to change `current` given where the time went, on a
+    /// machine with `cores` cores:
+    /// - mostly sleeping: halve the throttle,
+    /// - mostly waiting for the database: batch more auctions per round trip,
+    /// - mostly parsing: process batches on all cores.
+    pub fn suggest(&self, current: Config, cores: usize) -> Config {
+        let mut suggested = current;
+        if self.share(&[Stage::Sleep]) > 0.25 {
+            suggested.throttle = current.throttle / 2;
+        }
+        if self.share(&[Stage::Fetch, Stage::Write]) > 0.5 {
+            suggested.batch_size = (current.batch_size * 10).min(1000);
+        }
+        if self.share(&[Stage::Parse]) > 0.5 {
+            suggested.parallelism = cores.max(current.parallelism);
+        }
+        suggested
+    }
+
+    /// Prints where the time of a run that took `elapsed` went, for `count`
+    /// auctions, and the suggested configuration for the next run.
+    pub fn print(&self, elapsed: Duration, count: usize, current: Config) {
+        info!(
+            "processed {} auctions in {:.1}s, {:.1} auctions per second",
+            big(count),
+            elapsed.as_secs_f64(),
+            count as f64 / elapsed.as_secs_f64()
+        );
+        for (stage, time) in &self.stages {
+            info!(
+                "    {stage}: {:.1}s, {:.1}%",
+                time.as_secs_f64(),
+                self.share(&[*stage]) * 100.
+            );
+        }
+        let cores = std::thread::available_parallelism().map_or(1, usize::from);
+        let suggested = self.suggest(current, cores);
+        info!(
+            "suggested configuration for the next run: batch size {}, parallelism {}, throttle {}ms",
+            suggested.batch_size,
+            suggested.parallelism,
+            suggested.throttle.as_millis()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggest() {
+        let timings = |stages: &[(Stage, u64)]| Timings {
+            stages: stages
+                .iter()
+                .map(|(stage, secs)| (*stage, Duration::from_secs(*secs)))
+                .collect(),
+        };
+        let current = Config {
+            batch_size: 1,
+            parallelism: 1,
+            throttle: Duration::from_millis(50),
+        };
+
+        let database_bound = timings(&[(Stage::Fetch, 5), (Stage::Write, 4), (Stage::Parse, 1)]);
+        assert_eq!(
+            database_bound.suggest(current, 8),
+            Config {
+                batch_size: 10,
+                ..current
+            }
+        );
+
+        let sleeping = timings(&[(Stage::Sleep, 6), (Stage::Parse, 4)]);
+        assert_eq!(
+            sleeping.suggest(current, 8),
+            Config {
+                throttle: Duration::from_millis(25),
+                ..current
+            }
+        );
+
+        let parsing = timings(&[(Stage::Parse, 8), (Stage::Fetch, 1), (Stage::Sleep, 1)]);
+        assert_eq!(
+            parsing.suggest(current, 8),
+            Config {
+                parallelism: 8,
+                ..current
+            }
+        );
+
+        assert_eq!(Timings::default().suggest(current, 8), current);
+    }
+}
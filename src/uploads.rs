@@ -0,0 +1,164 @@
+//! Resumable uploads of exports to object storage, using the resumable upload
+//! protocol of Google Cloud Storage: a session is started once and the file is
+//! sent in chunks to it, so after a network error the upload continues at the
+//! last chunk the storage acknowledged instead of starting over. The session
+//! is kept in `migration_uploads` so it survives restarts of the tool.
+//!
+//! S3 multipart uploads need every request signed and are not supported.
+
+use {
+    anyhow::{bail, Context, Result},
+    reqwest::{header, Client, StatusCode},
+    url::Url,
+};
+
+/// Chunks other than the last one have to be a multiple of this size.
+pub const CHUNK_ALIGNMENT: usize = 256 * 1024;
+
+/// Where an upload stands after a request to its session.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Progress {
+    /// The storage has the bytes before this offset.
+    Incomplete(u64),
+    /// The object is stored.
+    Complete,
+}
+
+/// A client for resumable uploads, which doesn't follow the `308` responses
+/// of the protocol like redirects.
+pub fn client() -> Result<Client> {
+    Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .context("build http client")
+}
+
+/// Starts an upload of `size` bytes to `object` at the upload endpoint of the
+/// bucket `url`, like
+/// `https://storage.googleapis.com/upload/storage/v1/b/<bucket>/o`, and
+/// returns the url of its session.
+pub async fn start(
+    client: &Client,
+    url: &Url,
+    object: &str,
+    token: Option<&str>,
+    size: u64,
+) -> Result<String> {
+    let mut url = url.clone();
+    url.query_pairs_mut()
+        .append_pair("uploadType", "resumable")
+        .append_pair("name", object);
+    let mut request = client
+        .post(url)
+        .header("X-Upload-Content-Length", size)
+        .header(header::CONTENT_LENGTH, 0);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let response = request
+        .send()
+        .await
+        .context("request")?
+        .error_for_status()?;
+    let session = response
+        .headers()
+        .get(header::LOCATION)
+        .context("no session url in response")?
+        .to_str()
+        .context("session url")?;
+    Ok(session.to_string())
+}
+
+/// Asks the session how many of the `size` bytes it has, `None` if the
+/// session expired and the upload has to start over.
+pub async fn query(client: &Client, session: &str, size: u64) -> Result<Option<Progress>> {
+    let response = client
+        .put(session)
+        .header(header::CONTENT_RANGE, content_range(0, 0, size))
+        .header(header::CONTENT_LENGTH, 0)
+        .send()
+        .await
+        .context("request")?;
+    if matches!(response.status(), StatusCode::NOT_FOUND | StatusCode::GONE) {
+        return Ok(None);
+    }
+    progress(response).map(Some)
+}
+
+/// Sends `chunk`, the bytes of the upload starting at `offset`, of `size`
+/// bytes in total.
+pub async fn send(
+    client: &Client,
+    session: &str,
+    offset: u64,
+    chunk: Vec<u8>,
+    size: u64,
+) -> Result<Progress> {
+    let response = client
+        .put(session)
+        .header(
+            header::CONTENT_RANGE,
+            content_range(offset, chunk.len() as u64, size),
+        )
+        .body(chunk)
+        .send()
+        .await
+        .context("request")?;
+    progress(response)
+}
+
+fn progress(response: reqwest::Response) -> Result<Progress> {
+    match response.status() {
+        StatusCode::OK | StatusCode::CREATED => Ok(Progress::Complete),
+        StatusCode::PERMANENT_REDIRECT => {
+            let range = response
+                .headers()
+                .get(header::RANGE)
+                .map(|range| range.to_str().context("range header"))
+                .transpose()?;
+            Ok(Progress::Incomplete(received(range)?))
+        }
+        status => bail!("unexpected response status {status}"),
+    }
+}
+
+/// The `Content-Range` of `len` bytes at `offset` of `size` bytes, or of no
+/// bytes if `len` is 0.
+fn content_range(offset: u64, len: u64, size: u64) -> String {
+    match len {
+        0 => format!("bytes */{size}"),
+        _ => format!("bytes {offset}-{}/{size}", offset + len - 1),
+    }
+}
+
+/// The number of bytes the storage has according to the `Range` header of
+/// its response, like `bytes=0-1023`, which is missing if it has none.
+fn received(range: Option<&str>) -> Result<u64> {
+    let Some(range) = range else {
+        return Ok(0);
+    };
+    let last = range
+        .strip_prefix("bytes=0-")
+        .with_context(|| format!("unexpected range {range:?}"))?;
+    let last: u64 = last
+        .parse()
+        .with_context(|| format!("unexpected range {range:?}"))?;
+    Ok(last + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        assert_eq!(content_range(0, 1024, 4096), "bytes 0-1023/4096");
+        assert_eq!(content_range(1024, 3072, 4096), "bytes 1024-4095/4096");
+        assert_eq!(content_range(0, 0, 4096), "bytes */4096");
+
+        assert_eq!(received(None).unwrap(), 0);
+        assert_eq!(received(Some("bytes=0-1023")).unwrap(), 1024);
+        assert!(received(Some("bytes=10-1023")).is_err());
+        assert!(received(Some("bytes=0-")).is_err());
+    }
+}